@@ -168,6 +168,895 @@ for msg_type in "${new_types[@]}"; do
 done
 echo
 
+# Test 5: Offer/accept/sign flow validation
+echo "🔗 5. TESTING FLOW VALIDATION AGAINST DLCSPECS VECTORS"
+echo "------------------------------------------------------"
+
+VECTORS_DIR="../packages/messaging/test_vectors/dlcspecs"
+for vector in "$VECTORS_DIR"/*.json; do
+    name=$(basename "$vector")
+    offer_hex=$(jq -r '.offer_message.serialized' "$vector")
+    accept_hex=$(jq -r '.accept_message.serialized' "$vector")
+    sign_hex=$(jq -r '.sign_message.serialized' "$vector")
+    result=$($CLI validate-flow --offer-hex "$offer_hex" --accept-hex "$accept_hex" --sign-hex "$sign_hex" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+    status=$(echo "$result" | jq -r '.status')
+    if [ "$status" = "success" ]; then
+        echo "✅ $name: Accept and sign consistent with offer"
+    else
+        failed=$(echo "$result" | jq -r '[.data.checks[]? | select(.status == "fail") | .name] | join(", ")')
+        echo "⚠️  $name: Flow validation failed (${failed:-$(echo "$result" | jq -r '.message')})"
+    fi
+done
+
+vector="$VECTORS_DIR/single_oracle_numerical_test.json"
+broken_offer=$($CLI deserialize --hex "$(jq -r '.offer_message.serialized' "$vector")" 2>/dev/null \
+    | jq -c '.data | (.. | objects | select(has("outcomePayout"))).outcomePayout = 10000000000' \
+    | $CLI serialize -t offer 2>/dev/null | jq -r '.data')
+result=$($CLI validate-flow --offer-hex "$broken_offer" --accept-hex "$(jq -r '.accept_message.serialized' "$vector")" 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$result" | jq -r '[.data.checks[]? | select(.status == "fail") | .message | contains("exceeds the total collateral")] | any')" = "true" ]; then
+    echo "✅ Payout curve above the total collateral rejected"
+else
+    echo "❌ Out-of-range payout curve not reported"
+fi
+echo
+
+# Test 6: Contract id derivation
+echo "🆔 6. TESTING CONTRACT ID DERIVATION"
+echo "------------------------------------"
+
+for vector in "$VECTORS_DIR"/*.json; do
+    name=$(basename "$vector")
+    offer_hex=$(jq -r '.offer_message.serialized' "$vector")
+    accept_hex=$(jq -r '.accept_message.serialized' "$vector")
+    expected_id=$(jq -r '.sign_message.message.contractId' "$vector")
+    result=$($CLI contract-id --offer-hex "$offer_hex" --accept-hex "$accept_hex" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+    contract_id=$(echo "$result" | jq -r '.data.contractId // empty')
+    if [ "$contract_id" = "$expected_id" ]; then
+        echo "✅ $name: Contract id matches sign message"
+    else
+        echo "⚠️  $name: Contract id ${contract_id:-unavailable} != $expected_id"
+    fi
+done
+echo
+
+# Test 7: Mutation fuzzing of the readers
+echo "🎲 7. TESTING READERS AGAINST MUTATED VECTORS"
+echo "---------------------------------------------"
+
+result=$($CLI fuzz-corpus --corpus ../packages/messaging/test_vectors --seed 1 --iterations 2000 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$result" | jq -r '.status')" = "success" ]; then
+    findings=$(echo "$result" | jq -r '.data.findingCount')
+    if [ "$findings" = "0" ]; then
+        echo "✅ No panics or round-trip mismatches in 2000 mutated inputs"
+    else
+        echo "⚠️  $findings findings (panics or round-trip mismatches), first:"
+        echo "$result" | jq -r '.data.findings[0] | "   \(.reader) \(.kind): \(.detail)"'
+    fi
+else
+    echo "❌ fuzz-corpus failed: $(echo "$result" | jq -r '.message')"
+fi
+
+mkdir -p /tmp/fuzz_corpus
+cp "../packages/messaging/test_vectors/dlcspecs/enum_single_oracle_test.json" /tmp/fuzz_corpus/
+printf '\xff\xfe\x00' > /tmp/fuzz_corpus/finding.bin
+printf '\xff\xfe\x00' > /tmp/fuzz_corpus/broken.json
+result=$($CLI fuzz-corpus --corpus /tmp/fuzz_corpus --seed 1 --iterations 10 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$result" | jq -r '.status')" = "success" ] && [ "$(echo "$result" | jq -r '.data.warnings | length')" = "1" ]; then
+    echo "✅ Binary findings in the corpus skipped, unreadable JSON reported as a warning"
+else
+    echo "❌ Corpus with non-UTF-8 files failed: $(echo "$result" | jq -r '.message')"
+fi
+rm -rf /tmp/fuzz_corpus
+echo
+
+# Test 8: Differential harness plumbing (an echoing decoder must always agree)
+echo "⚖️  8. TESTING DIFFERENTIAL HARNESS"
+echo "-----------------------------------"
+
+result=$($CLI differential --command "jq -c '{hex: .hex, json: .json}'" --seed 7 --iterations 20 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$result" | jq -r '.status')" = "success" ] && [ "$(echo "$result" | jq -r '.data.failed')" = "0" ]; then
+    echo "✅ Echo decoder agreed on $(echo "$result" | jq -r '.data.passed') generated cases"
+else
+    echo "❌ Differential harness: $(echo "$result" | jq -r '.message')"
+fi
+echo
+
+# Test 9: Generated offer/accept/sign triples pass flow validation
+echo "🎰 9. TESTING GENERATED MESSAGES"
+echo "--------------------------------"
+
+for seed in 1 2 3 4 5; do
+    result=$($CLI generate --type dlc --seed "$seed" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+    if [ "$(echo "$result" | jq -r '.status')" != "success" ]; then
+        echo "❌ Seed $seed: $(echo "$result" | jq -r '.message')"
+        continue
+    fi
+    offer_hex=$(echo "$result" | jq -r '.data.cases[0].offer.hex')
+    accept_hex=$(echo "$result" | jq -r '.data.cases[0].accept.hex')
+    sign_hex=$(echo "$result" | jq -r '.data.cases[0].sign.hex')
+    flow=$($CLI validate-flow --offer-hex "$offer_hex" --accept-hex "$accept_hex" --sign-hex "$sign_hex" 2>/dev/null || echo '{"status":"error"}')
+    if [ "$(echo "$flow" | jq -r '.status')" = "success" ]; then
+        echo "✅ Seed $seed: Generated DLC passes flow validation"
+    else
+        echo "⚠️  Seed $seed: Generated DLC fails flow validation"
+    fi
+done
+echo
+
+# Test 10: Scenario-driven test vectors in the dlcspecs layout
+echo "📚 10. TESTING TEST VECTOR GENERATION"
+echo "------------------------------------"
+
+scenarios='[
+  {"name": "enum_single_oracle", "seed": 1, "contract": "enum", "oracles": 1},
+  {"name": "numerical_single_oracle", "seed": 2, "contract": "numeric", "oracles": 1},
+  {"name": "hyperbola_single_oracle", "seed": 3, "contract": "hyperbola", "oracles": 1},
+  {"name": "enum_3_of_5", "seed": 4, "contract": "enum", "oracles": 5, "threshold": 3},
+  {"name": "enum_with_dlc_input", "seed": 5, "contract": "enum", "oracles": 1, "dlcInputs": true}
+]'
+vectors=$(echo "$scenarios" | $CLI generate-vectors --scenario - 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$vectors" | jq -r '.status')" != "success" ]; then
+    echo "❌ Vector generation failed: $(echo "$vectors" | jq -r '.message')"
+else
+    for name in $(echo "$vectors" | jq -r '.data.vectors | keys[]'); do
+        vector=$(echo "$vectors" | jq -c --arg name "$name" '.data.vectors[$name]')
+        offer_hex=$(echo "$vector" | jq -r '.offer_message.serialized')
+        accept_hex=$(echo "$vector" | jq -r '.accept_message.serialized')
+        sign_hex=$(echo "$vector" | jq -r '.sign_message.serialized')
+        flow=$($CLI validate-flow --offer-hex "$offer_hex" --accept-hex "$accept_hex" --sign-hex "$sign_hex" 2>/dev/null || echo '{"status":"error"}')
+        if [ "$(echo "$flow" | jq -r '.status')" = "success" ]; then
+            echo "✅ $name: Vector passes flow validation"
+        else
+            echo "⚠️  $name: Vector fails flow validation"
+        fi
+    done
+fi
+echo
+
+# Test 11: DLC inputs spending a prior contract's funding output
+echo "🔗 11. TESTING DLC INPUTS"
+echo "-------------------------"
+
+prior=$($CLI generate --type dlc --seed 1 2>/dev/null || echo '{"status":"error"}')
+offer_hex=$(echo "$prior" | jq -r '.data.cases[0].offer.hex')
+accept_hex=$(echo "$prior" | jq -r '.data.cases[0].accept.hex')
+offer_pubkey=$(echo "$prior" | jq -r '.data.cases[0].offer.json.fundingPubkey')
+accept_pubkey=$(echo "$prior" | jq -r '.data.cases[0].accept.json.fundingPubkey')
+ids=$($CLI contract-id --offer-hex "$offer_hex" --accept-hex "$accept_hex" 2>/dev/null || echo '{"status":"error"}')
+fund_tx=$(echo "$ids" | jq -r '.data.fundTransaction.hex')
+contract_id=$(echo "$ids" | jq -r '.data.contractId')
+built=$($CLI build-dlc-input --fund-tx "$fund_tx" --local-pubkey "$offer_pubkey" --remote-pubkey "$accept_pubkey" --contract-id "$contract_id" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$built" | jq -r '.status')" = "success" ]; then
+    echo "✅ Built DLC input spending $(echo "$built" | jq -r '.data.outpoint')"
+    input_hex=$(echo "$built" | jq -r '.data.hex')
+    check=$($CLI validate-dlc-input --input "$input_hex" --prev-offer-hex "$offer_hex" --prev-accept-hex "$accept_hex" 2>/dev/null || echo '{"status":"error"}')
+    if [ "$(echo "$check" | jq -r '.data.valid')" = "true" ]; then
+        echo "✅ DLC input matches the prior contract"
+    else
+        echo "⚠️  DLC input validation failed"
+    fi
+else
+    echo "❌ Building DLC input failed: $(echo "$built" | jq -r '.message')"
+fi
+
+spliced=$($CLI generate --type dlc --seed 8 --dlc-inputs 2>/dev/null || echo '{"status":"error"}')
+offer_hex=$(echo "$spliced" | jq -r '.data.cases[0].offer.hex')
+accept_hex=$(echo "$spliced" | jq -r '.data.cases[0].accept.hex')
+ids=$($CLI contract-id --offer-hex "$offer_hex" --accept-hex "$accept_hex" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$ids" | jq -r '.status')" = "success" ]; then
+    echo "✅ Contract id $(echo "$ids" | jq -r '.data.contractId') for a contract funded by a DLC input"
+else
+    echo "❌ Contract id with DLC inputs failed: $(echo "$ids" | jq -r '.message')"
+fi
+echo
+
+# Test 12: Batch funding groups and batch funding transactions
+echo "📦 12. TESTING BATCH FUNDING"
+echo "----------------------------"
+
+group='{"tempContractIds":[],"contractIds":[],"totalCollateral":50000000,"eventIds":["event1","event2"]}'
+group_hex=$(echo "$group" | $CLI serialize --type batch-funding-group 2>/dev/null | jq -r '.data // empty')
+round_trip=$($CLI deserialize --hex "$group_hex" 2>/dev/null | jq -c '.data.eventIds' || echo "null")
+if [ "$round_trip" = '["event1","event2"]' ]; then
+    echo "✅ BatchFundingGroup round-trips through its TLV encoding"
+else
+    echo "❌ BatchFundingGroup round trip failed"
+fi
+
+dlc=$($CLI generate --type dlc --seed 1 2>/dev/null || echo '{"status":"error"}')
+temp_id=$(echo "$dlc" | jq -r '.data.cases[0].offer.json.temporaryContractId')
+batch=$(jq -n --argjson dlc "$dlc" --arg temp_id "$temp_id" '{
+    contracts: [{offer: $dlc.data.cases[0].offer.hex, accept: $dlc.data.cases[0].accept.hex}],
+    batchFundingGroups: [{tempContractIds: [$temp_id], totalCollateral: 300000000, eventIds: ["event1"]}]
+}')
+result=$(echo "$batch" | $CLI build-batch-funding 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$result" | jq -r '.status')" = "success" ]; then
+    echo "✅ Batch funding transaction $(echo "$result" | jq -r '.data.fundTransaction.txid') with $(echo "$result" | jq -r '.data.contracts | length') contract(s)"
+else
+    echo "❌ Batch funding failed: $(echo "$result" | jq -r '.message')"
+fi
+echo
+
+# Test 13: Order-book messages
+echo "🛒 13. TESTING ORDER MESSAGES"
+echo "-----------------------------"
+
+dlc=$($CLI generate --type dlc --seed 2 2>/dev/null || echo '{"status":"error"}')
+order=$(jq -n --argjson dlc "$dlc" '{
+    protocolVersion: 1,
+    contractFlags: 0,
+    chainHash: "06226e46111a0b59caaf126043eb5bbf28c34f3a5e332a1fc7b2b73cf188910f",
+    temporaryContractId: ("11" * 32),
+    contractInfo: $dlc.data.cases[0].offer.json.contractInfo,
+    offerCollateral: $dlc.data.cases[0].offer.json.offerCollateral,
+    feeRatePerVb: 2,
+    cetLocktime: 100,
+    refundLocktime: 200,
+    metadata: {offerId: "order-1", createdAt: 1700000000, goodTill: 1700086400},
+    ircInfo: {nick: "maker", pubKey: ("02" + "22" * 32)},
+    positionInfo: {shiftForFees: "offeror", fees: 1000, instrumentName: "BTC-PERP", contractSize: 1, direction: "buy", price: 65000, extraPrecision: 2}
+}')
+order_hex=$(echo "$order" | $CLI serialize --type order-offer 2>/dev/null | jq -r '.data // empty')
+decoded=$($CLI deserialize --hex "$order_hex" 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$decoded" | jq -r '.messageType')" = "order-offer" ] && \
+   [ "$(echo "$decoded" | jq -r '.data.positionInfo.instrumentName')" = "BTC-PERP" ]; then
+    echo "✅ OrderOffer with metadata, IRC and position TLVs round-trips"
+else
+    echo "❌ OrderOffer round trip failed"
+fi
+
+legacy_order=$($CLI deserialize --hex "${order_hex:0:4}${order_hex:12}" 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$legacy_order" | jq -r '.data.protocolVersion')" = "1" ] && \
+   [ "$(echo "$legacy_order" | jq -r '.warnings | length')" = "1" ]; then
+    echo "✅ OrderOffer without protocolVersion decoded with a warning"
+else
+    echo "❌ Unversioned OrderOffer not flagged: $(echo "$legacy_order" | jq -r '.message')"
+fi
+
+validation=$(echo "$order" | $CLI validate --type order-offer 2>/dev/null | jq -r '.status')
+if [ "$validation" = "success" ]; then
+    echo "✅ OrderOffer passes node-dlc's offer rules"
+else
+    echo "❌ OrderOffer validation failed"
+fi
+invalid=$(echo "$order" | jq '.refundLocktime = 50' | $CLI validate --type order-offer 2>/dev/null | jq -r '.status')
+if [ "$invalid" = "error" ]; then
+    echo "✅ OrderOffer with refundLocktime before cetLocktime rejected"
+else
+    echo "❌ OrderOffer locktime check missing"
+fi
+
+accept=$(jq -n --argjson order "$order" '{tempOrderId: ("11" * 32), negotiationFields: {variant: "Disjoint", orderOffer: $order}}')
+accept_hex=$(echo "$accept" | $CLI serialize --type order-accept 2>/dev/null | jq -r '.data // empty')
+accept_type=$($CLI deserialize --hex "$accept_hex" 2>/dev/null | jq -r '.data.negotiationFields.orderOffer.metadata.offerId' || echo "null")
+if [ "$accept_type" = "order-1" ]; then
+    echo "✅ OrderAccept with a counter-offer round-trips"
+else
+    echo "❌ OrderAccept round trip failed"
+fi
+echo
+
+# Test 14: Negotiation fields and rounding-interval renegotiation
+echo "🤝 14. TESTING NEGOTIATION FIELDS"
+echo "---------------------------------"
+
+fields='{"roundingIntervals":{"intervals":[{"beginInterval":0,"roundingMod":100000}]}}'
+v1_hex=$(echo "$fields" | $CLI serialize --type negotiation-fields-v1 2>/dev/null | jq -r '.data // empty')
+v1_type=$($CLI deserialize --hex "$v1_hex" 2>/dev/null | jq -r '.messageType' || echo "null")
+if [ "$v1_type" = "negotiation-fields-v1" ]; then
+    echo "✅ NegotiationFieldsV1 round-trips and is detected"
+else
+    echo "❌ NegotiationFieldsV1 round trip failed"
+fi
+
+dlc=$($CLI generate --type dlc --seed 3 --contract numeric 2>/dev/null || echo '{"status":"error"}')
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+result=$($CLI apply-negotiation --offer-hex "$offer_hex" --negotiation "$v1_hex" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$result" | jq -r '.data.contracts[0].status')" = "applied" ]; then
+    echo "✅ Proposed rounding changes $(echo "$result" | jq -r '.data.contracts[0].changes | length') payout range(s), up to $(echo "$result" | jq -r '.data.contracts[0].maxDifference') sats"
+else
+    echo "❌ apply-negotiation failed: $(echo "$result" | jq -r '.message')"
+fi
+
+disjoint='{"variant":"Disjoint","negotiationFields":[{"variant":"Single","roundingIntervals":{"intervals":[]}},{"variant":"Single","roundingIntervals":{"intervals":[]}}]}'
+mismatch=$($CLI apply-negotiation --offer-hex "$offer_hex" --negotiation "$disjoint" 2>/dev/null | jq -r '.status')
+if [ "$mismatch" = "error" ]; then
+    echo "✅ Disjoint negotiation fields rejected for a single contract"
+else
+    echo "❌ Sub-contract count mismatch not detected"
+fi
+
+late='{"roundingIntervals":{"intervals":[{"beginInterval":100,"roundingMod":100000}]}}'
+late_hex=$(echo "$late" | $CLI serialize --type negotiation-fields-v1 2>/dev/null | jq -r '.data // empty')
+late_result=$($CLI apply-negotiation --offer-hex "$offer_hex" --negotiation "$late_hex" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$late_result" | jq -r '.status')" = "error" ] && echo "$late_result" | jq -r '.message' | grep -q "start at outcome 0"; then
+    echo "✅ Rounding intervals not starting at outcome 0 rejected"
+else
+    echo "❌ Rounding intervals not starting at outcome 0 accepted"
+fi
+echo
+
+# Test 15: node-dlc LevelDB store records
+echo "🗄️  15. TESTING STORE RECORDS"
+echo "-----------------------------"
+
+ids_hex=$(echo "{\"ids\":[\"$(printf '%064d' 1)\"]}" | $CLI serialize --type dlc-ids 2>/dev/null | jq -r '.data // empty')
+info_hex=$(echo '{"numDlcOffers":3,"numDlcAccepts":2,"numDlcSigns":1,"numDlcCancels":0,"numDlcCloses":0,"numDlcTransactions":1}' | $CLI serialize --type dlc-info 2>/dev/null | jq -r '.data // empty')
+for record in "$ids_hex" "$info_hex"; do
+    result=$($CLI inspect-store-record --value "$record" 2>/dev/null || echo '{"status":"error"}')
+    if [ "$(echo "$result" | jq -r '.data.roundTrip')" = "true" ]; then
+        echo "✅ $(echo "$result" | jq -r '.data.recordType') record re-encodes to the stored bytes"
+    else
+        echo "❌ Store record round trip failed: $(echo "$result" | jq -r '.message')"
+    fi
+done
+
+dlc=$($CLI generate --type dlc --seed 1 2>/dev/null || echo '{"status":"error"}')
+batch=$(jq -n --argjson dlc "$dlc" '{contracts: [{offer: $dlc.data.cases[0].offer.hex, accept: $dlc.data.cases[0].accept.hex}]}')
+funding=$(echo "$batch" | $CLI build-batch-funding 2>/dev/null || echo '{"status":"error"}')
+record=$(echo "$funding" | jq '{
+    contractId: .data.contracts[0].contractId,
+    fundTx: .data.fundTransaction.hex,
+    fundTxVout: .data.contracts[0].fundOutputIndex,
+    refundTx: .data.contracts[0].refundTransaction.hex,
+    cets: [.data.contracts[0].cets[].hex],
+    closeType: "NotClosed"
+}')
+record_hex=$(echo "$record" | $CLI serialize --type dlc-transactions 2>/dev/null | jq -r '.data // empty')
+contract_id=$(echo "$record" | jq -r '.contractId')
+result=$($CLI inspect-store-record --key "35$contract_id" --value "$record_hex" 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$result" | jq -r '.data.roundTrip')" = "true" ] && \
+   [ "$(echo "$result" | jq -r '.data.summary.cetCount')" = "$(echo "$record" | jq '.cets | length')" ]; then
+    echo "✅ DlcTransactions record for $(echo "$result" | jq -r '.data.summary.fundOutpoint') decoded by key prefix"
+else
+    echo "❌ DlcTransactions record inspection failed: $(echo "$result" | jq -r '.message')"
+fi
+echo
+
+# Test 16: Oracle event containers and identifiers
+echo "🔮 16. TESTING ORACLE CONTAINERS"
+echo "--------------------------------"
+
+announcement_hex=$($CLI create-oracle-announcement --event-type enum --event-id container-event 2>/dev/null | jq -r '.hex // empty')
+attestation_hex=$($CLI create-oracle-attestation --announcement-hex "$announcement_hex" --outcome win 2>/dev/null | jq -r '.hex // empty')
+container=$($CLI container-from --announcement "$announcement_hex" --attestation "$attestation_hex" --oracle-name test-oracle --uri https://oracle.example 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+container_hex=$(echo "$container" | jq -r '.data.hex // empty')
+decoded=$($CLI deserialize --hex "$container_hex" 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$decoded" | jq -r '.messageType')" = "oracle-event-container" ] && \
+   [ "$(echo "$decoded" | jq -r '.data.outcome')" = "win" ]; then
+    echo "✅ OracleEventContainer bundles announcement and attestation for outcome win"
+else
+    echo "❌ container-from failed: $(echo "$container" | jq -r '.message')"
+fi
+
+oracle_pubkey=$(echo "$container" | jq -r '.data.container.announcement.oraclePublicKey')
+identifier_hex=$(echo "{\"oracleName\":\"test-oracle\",\"oraclePubkey\":\"$oracle_pubkey\"}" | $CLI serialize --type oracle-identifier 2>/dev/null | jq -r '.data // empty')
+identifier=$($CLI deserialize --hex "$identifier_hex" 2>/dev/null | jq -r '.data.oracleName' || echo "null")
+if [ "$identifier" = "test-oracle" ]; then
+    echo "✅ OracleIdentifier round-trips"
+else
+    echo "❌ OracleIdentifier round trip failed"
+fi
+echo
+
+# Test 17: pre-1.0 (legacy v0) messages
+echo "🕰️  17. TESTING LEGACY V0 MESSAGES"
+echo "----------------------------------"
+
+dlc=$($CLI generate --type dlc --seed 4 --contract numeric 2>/dev/null || echo '{"status":"error"}')
+for kind in offer accept sign; do
+    current_hex=$(echo "$dlc" | jq -r ".data.cases[0].$kind.hex")
+    legacy_hex=$($CLI downgrade-legacy --hex "$current_hex" 2>/dev/null | jq -r '.data.hex // empty')
+    upgraded=$($CLI upgrade-legacy --hex "$legacy_hex" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+    # Legacy offers carry no temporary contract id, so it cannot survive the round trip.
+    expected=$(echo "$dlc" | jq -S ".data.cases[0].$kind.json | del(.temporaryContractId)")
+    actual=$(echo "$upgraded" | jq -S '.data.message | del(.temporaryContractId)')
+    if [ -n "$legacy_hex" ] && [ "$expected" = "$actual" ]; then
+        echo "✅ Legacy $kind upgrades back to the original ($(( ${#legacy_hex} / 2 )) legacy bytes)"
+    else
+        echo "❌ Legacy $kind round trip failed: $(echo "$upgraded" | jq -r '.message')"
+    fi
+done
+
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+legacy_offer=$($CLI downgrade-legacy --hex "$offer_hex" 2>/dev/null | jq -r '.data.hex // empty')
+legacy_decoded=$($CLI deserialize --hex "$legacy_offer" 2>/dev/null || echo '{"status":"error"}')
+detected=$(echo "$legacy_decoded" | jq -r '.message')
+if [ "$detected" = "Successfully deserialized legacy v0 offer" ]; then
+    echo "✅ deserialize detects a legacy offer"
+else
+    echo "❌ Legacy offer not detected: $detected"
+fi
+if [ "$(echo "$legacy_decoded" | jq -r '[.warnings[] | select(contains("temporaryContractId"))] | length')" = "1" ]; then
+    echo "✅ Derived temporaryContractId flagged in the deserialize output"
+else
+    echo "❌ Derived temporaryContractId not flagged"
+fi
+
+already=$($CLI upgrade-legacy --hex "$offer_hex" 2>/dev/null | jq -r '.status')
+if [ "$already" = "error" ]; then
+    echo "✅ Current offers are not mistaken for legacy ones"
+else
+    echo "❌ upgrade-legacy accepted a current offer"
+fi
+
+truncated=$($CLI deserialize --hex "${offer_hex:0:200}" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$truncated" | jq -r '.status')" = "error" ] && ! echo "$truncated" | jq -r '.message' | grep -q "legacy"; then
+    echo "✅ A truncated current offer reports the current-format error only"
+else
+    echo "❌ Truncated current offer: $(echo "$truncated" | jq -r '.message')"
+fi
+
+legacy_bytes=$($CLI deserialize --hex "${legacy_offer}00" 2>/dev/null || echo '{"status":"error","message":"Failed"}')
+if [ "$(echo "$legacy_bytes" | jq -r '.status')" = "error" ] && echo "$legacy_bytes" | jq -r '.message' | grep -q "legacy v0 offer"; then
+    echo "✅ A broken legacy offer reports both the current and the legacy error"
+else
+    echo "❌ Broken legacy offer: $(echo "$legacy_bytes" | jq -r '.message')"
+fi
+echo
+
+# Test 18: protocol version and contract flags
+echo "🏷️  18. TESTING PROTOCOL VERSION REPORTING"
+echo "-----------------------------------------"
+
+dlc=$($CLI generate --type dlc --seed 5 2>/dev/null || echo '{"status":"error"}')
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+protocol=$($CLI deserialize --hex "$offer_hex" 2>/dev/null | jq -c '.protocol')
+if [ "$(echo "$protocol" | jq -r '.protocolVersion')" = "1" ] && \
+   [ "$(echo "$protocol" | jq -r '.warnings | length')" = "0" ] && \
+   [ "$(echo "$protocol" | jq -r '.chainHash | length')" = "64" ]; then
+    echo "✅ Offer reports protocol version 1, flags $(echo "$protocol" | jq -r '.contractFlags') and its chain hash"
+else
+    echo "❌ Protocol report missing: $protocol"
+fi
+
+# Bytes 2-5 are the protocol version and byte 6 the contract flags.
+future_offer="${offer_hex:0:4}00000002${offer_hex:12}"
+warned=$($CLI deserialize --hex "$future_offer" 2>/dev/null | jq -r '.protocol.supportedVersion')
+refused=$($CLI deserialize --strict-version --hex "$future_offer" 2>/dev/null | jq -r '.status')
+if [ "$warned" = "false" ] && [ "$refused" = "error" ]; then
+    echo "✅ Unknown protocol version warns, and is refused with --strict-version"
+else
+    echo "❌ Unknown protocol version handling: supportedVersion=$warned, strict status=$refused"
+fi
+
+flagged_offer="${offer_hex:0:12}01${offer_hex:14}"
+flag_warnings=$($CLI deserialize --hex "$flagged_offer" 2>/dev/null | jq -r '.protocol.warnings | length')
+if [ "$flag_warnings" = "1" ]; then
+    echo "✅ Reserved contract flags are reported"
+else
+    echo "❌ Reserved contract flags not reported"
+fi
+echo
+
+# Test 19: networks and addresses
+echo "🌐 19. TESTING NETWORK AWARENESS"
+echo "-------------------------------"
+
+dlc=$($CLI generate --type dlc --seed 6 --network testnet 2>/dev/null || echo '{"status":"error"}')
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+report=$($CLI deserialize --hex "$offer_hex" 2>/dev/null | jq -c '.network')
+if [ "$(echo "$report" | jq -r '.network')" = "testnet" ] && \
+   [ "$(echo "$report" | jq -r '[.addresses[].address | startswith("tb1")] | all')" = "true" ]; then
+    echo "✅ Testnet offer renders $(echo "$report" | jq -r '.addresses | length') tb1 addresses"
+else
+    echo "❌ Network report wrong: $report"
+fi
+
+mismatch=$($CLI deserialize --network mainnet --hex "$offer_hex" 2>/dev/null | jq -r '.network.warnings | length')
+if [ "$mismatch" -ge 1 ] 2>/dev/null; then
+    echo "✅ Testnet offer flagged when mainnet is expected"
+else
+    echo "❌ Network mismatch not flagged"
+fi
+
+accept_hex=$(echo "$dlc" | jq -r '.data.cases[0].accept.hex')
+accept_address=$($CLI deserialize --network regtest --hex "$accept_hex" 2>/dev/null | jq -r '.network.addresses[0].address')
+case "$accept_address" in
+    bcrt1*) echo "✅ Accept addresses rendered for the requested network" ;;
+    *) echo "❌ Accept address not rendered: $accept_address" ;;
+esac
+
+for args in "deserialize --hex $offer_hex" "generate --type offer"; do
+    unknown=$($CLI $args --network moonnet 2>/dev/null || echo '{"status":"exit"}')
+    if [ "$(echo "$unknown" | jq -r '.status')" = "error" ]; then
+        echo "✅ ${args%% *} reports an unknown network as a JSON error"
+    else
+        echo "❌ ${args%% *} did not report the unknown network as a JSON error"
+    fi
+done
+echo
+
+# Test 20: funding PSBTs
+echo "🧾 20. TESTING FUNDING PSBT"
+echo "--------------------------"
+
+dlc=$($CLI generate --type dlc --seed 7 2>/dev/null || echo '{"status":"error"}')
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+accept_hex=$(echo "$dlc" | jq -r '.data.cases[0].accept.hex')
+expected_inputs=$(echo "$dlc" | jq -r '(.data.cases[0].offer.json.fundingInputs | length) + (.data.cases[0].accept.json.fundingInputs | length)')
+funding=$($CLI funding-psbt --offer-hex "$offer_hex" --accept-hex "$accept_hex" 2>/dev/null || echo '{"status":"error"}')
+psbt=$(echo "$funding" | jq -r '.data.psbt // empty')
+if [ -n "$psbt" ] && [ "$(echo "$funding" | jq -r '.data.inputs | length')" = "$expected_inputs" ]; then
+    echo "✅ Funding PSBT covers all $expected_inputs funding inputs"
+else
+    echo "❌ Funding PSBT wrong: $(echo "$funding" | jq -c '.message // .')"
+fi
+
+unsigned=$($CLI finalize-funding --offer-hex "$offer_hex" --accept-hex "$accept_hex" --psbt "$psbt" 2>/dev/null | jq -r '.status')
+if [ "$unsigned" = "error" ]; then
+    echo "✅ Unsigned funding PSBT rejected by finalize-funding"
+else
+    echo "❌ Unsigned funding PSBT accepted"
+fi
+echo
+
+# Test 21: refund transaction
+echo "↩️  21. TESTING REFUND TRANSACTION"
+echo "--------------------------------"
+
+dlc=$($CLI generate --type dlc --seed 8 2>/dev/null || echo '{"status":"error"}')
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+accept_hex=$(echo "$dlc" | jq -r '.data.cases[0].accept.hex')
+sign_hex=$(echo "$dlc" | jq -r '.data.cases[0].sign.hex')
+refund=$($CLI build-refund --offer-hex "$offer_hex" --accept-hex "$accept_hex" 2>/dev/null || echo '{"status":"error"}')
+refund_locktime=$(echo "$dlc" | jq -r '.data.cases[0].offer.json.refundLocktime')
+if [ "$(echo "$refund" | jq -r '.data.refundLocktime.locktime')" = "$refund_locktime" ] && \
+   [ "$(echo "$refund" | jq -r '[.data.payouts[].outputIndex != null] | all')" = "true" ]; then
+    echo "✅ Refund transaction locked until $refund_locktime returns both collaterals"
+else
+    echo "❌ Refund transaction wrong: $(echo "$refund" | jq -c '.message // .data.warnings')"
+fi
+
+verified=$($CLI verify-refund-sig --offer-hex "$offer_hex" --accept-hex "$accept_hex" --sign-hex "$sign_hex" 2>/dev/null | jq -r '.data.valid')
+if [ "$verified" = "true" ]; then
+    echo "✅ Accept and sign refund signatures verify"
+else
+    echo "❌ Refund signatures did not verify"
+fi
+
+# Swapping the accept and sign refund signatures must fail verification
+bad_sign=$(echo "$dlc" | jq -c '.data.cases[0].sign.json' | \
+    jq -c --argjson accept "$(echo "$dlc" | jq -c '.data.cases[0].accept.json')" '.refundSignature = $accept.refundSignature')
+bad_sign_hex=$(echo "$bad_sign" | $CLI serialize --type sign 2>/dev/null | jq -r '.data // empty')
+if [ -n "$bad_sign_hex" ]; then
+    bad=$($CLI verify-refund-sig --offer-hex "$offer_hex" --accept-hex "$accept_hex" --sign-hex "$bad_sign_hex" 2>/dev/null | jq -r '.data.valid')
+    if [ "$bad" = "false" ]; then
+        echo "✅ Refund signature by the wrong party rejected"
+    else
+        echo "❌ Refund signature by the wrong party accepted"
+    fi
+else
+    echo "⚠️  Could not re-serialize tampered sign message"
+fi
+echo
+
+# Test 22: contract report
+echo "📋 22. TESTING CONTRACT REPORT"
+echo "-----------------------------"
+
+for contract in enum numeric; do
+    dlc=$($CLI generate --type dlc --seed 9 --contract $contract 2>/dev/null || echo '{"status":"error"}')
+    offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+    report=$($CLI report --offer-hex "$offer_hex" 2>/dev/null || echo '{"status":"error"}')
+    total=$(echo "$report" | jq -r '.data.totalCollateral')
+    if [ "$(echo "$report" | jq -r '.status')" = "success" ] && \
+       [ "$(echo "$report" | jq -r '.data.offer.maxPayout <= .data.totalCollateral and .data.accept.maxPayout <= .data.totalCollateral')" = "true" ] && \
+       [ "$(echo "$report" | jq -r '.data.cetCount > 0 and .data.accept.fees.estimated')" = "true" ]; then
+        echo "✅ $contract report: $(echo "$report" | jq -r '.data.cetCount') CETs, total collateral $total, offer fee $(echo "$report" | jq -r '.data.offer.fees.total') sat"
+    else
+        echo "❌ $contract report wrong: $(echo "$report" | jq -c '.message // .data')"
+    fi
+done
+
+accept_hex=$(echo "$dlc" | jq -r '.data.cases[0].accept.hex')
+estimated=$($CLI report --offer-hex "$offer_hex" --accept-hex "$accept_hex" 2>/dev/null | jq -r '.data.accept.fees.estimated')
+if [ "$estimated" = "false" ]; then
+    echo "✅ Accepting party fees computed from the accept message"
+else
+    echo "❌ Accept message not used for accepting party fees"
+fi
+echo
+
+# Test 23: CET count and message size estimates
+echo "📏 23. TESTING SIZE ESTIMATES"
+echo "----------------------------"
+
+dlc=$($CLI generate --type dlc --seed 10 --contract numeric 2>/dev/null || echo '{"status":"error"}')
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+accept_inputs=$(echo "$dlc" | jq -r '.data.cases[0].accept.json.fundingInputs | length')
+estimate=$($CLI estimate --offer-hex "$offer_hex" --funding-inputs "$accept_inputs" 2>/dev/null || echo '{"status":"error"}')
+signatures=$(echo "$dlc" | jq -r '.data.cases[0].accept.json.cetAdaptorSignatures.ecdsaAdaptorSignatures | length')
+if [ "$(echo "$estimate" | jq -r '.data.adaptorSignatureCount')" = "$signatures" ]; then
+    echo "✅ Estimated $signatures adaptor signatures match the generated accept"
+else
+    echo "❌ Adaptor signature estimate $(echo "$estimate" | jq -r '.data.adaptorSignatureCount') != $signatures"
+fi
+
+# Generated inputs carry their own previous transactions, so sizes are
+# only compared to within a few hundred bytes per input.
+for kind in accept sign; do
+    actual=$(( $(echo "$dlc" | jq -r ".data.cases[0].$kind.hex" | wc -c) / 2 ))
+    estimated=$(echo "$estimate" | jq -r ".data.${kind}Size")
+    diff=$(( actual > estimated ? actual - estimated : estimated - actual ))
+    if [ "$diff" -le 1000 ] 2>/dev/null; then
+        echo "✅ $kind size estimate $estimated bytes (actual $actual)"
+    else
+        echo "❌ $kind size estimate $estimated bytes far from actual $actual"
+    fi
+done
+
+limited=$($CLI estimate --offer-hex "$offer_hex" --max-message-size 100 2>/dev/null | jq -r '.data.warnings | length')
+if [ "$limited" = "2" ]; then
+    echo "✅ Messages over the size limit flagged"
+else
+    echo "❌ Size limit not enforced: $limited warnings"
+fi
+
+# Multi-oracle contracts with oracleParams (allowed difference) have one
+# adaptor signature per combination of prefixes the oracles may attest.
+for vector in "$VECTORS_DIR"/*with_diff*.json; do
+    name=$(basename "$vector")
+    offer_hex=$(jq -r '.offer_message.serialized' "$vector")
+    expected=$(jq -r '.accept_message.message.cetAdaptorSignatures.ecdsaAdaptorSignatures | length' "$vector")
+    estimate=$($CLI estimate --offer-hex "$offer_hex" 2>/dev/null || echo '{"status":"error"}')
+    count=$(echo "$estimate" | jq -r '.data.adaptorSignatureCount // empty')
+    if [ "$count" = "$expected" ]; then
+        echo "✅ $name: $count adaptor signatures"
+    else
+        echo "❌ $name: ${count:-no} adaptor signatures, expected $expected ($(echo "$estimate" | jq -r '.message'))"
+    fi
+done
+echo
+
+# Test 24: multi-oracle numeric with bounded difference
+echo "🎯 24. TESTING BOUNDED-DIFFERENCE MULTI-ORACLE CETS"
+echo "--------------------------------------------------"
+
+vector="$VECTORS_DIR/three_of_five_oracle_numerical_with_diff_test.json"
+cets=$($CLI multi-oracle-cets --offer-hex "$(jq -r '.offer_message.serialized' "$vector")" 2>/dev/null || echo '{"status":"error"}')
+total=$(echo "$cets" | jq -r '.data.subContracts[0] | .oracleSubsets * ([.intervals[].prefixes[].combinations | length] | add)')
+widths=$(echo "$cets" | jq -r '[.data.subContracts[0].intervals[].prefixes[].combinations[] | length] | unique | join(",")')
+if [ "$total" = "680" ] && [ "$widths" = "3" ]; then
+    echo "✅ 3-of-5 combinations list one prefix per signing oracle and total 680 signatures"
+else
+    echo "❌ 3-of-5 combinations wrong: total $total, widths $widths"
+fi
+
+plain=$($CLI generate --type offer --seed 24 --contract numeric --oracles 3 --threshold 2 2>/dev/null | jq -r '[.data.cases[0].json | .. | objects | select(has("oracleParams")) | .oracleParams][0]')
+if [ "$plain" = "null" ]; then
+    echo "✅ Generated contracts have no oracleParams unless asked for"
+else
+    echo "❌ oracleParams generated without --bounded-difference"
+fi
+
+checked=0
+for seed in 1 2 3 4 5 6 7 8; do
+    dlc=$($CLI generate --type dlc --seed $seed --contract numeric --oracles 3 --threshold 2 --bounded-difference 2>/dev/null || echo '{"status":"error"}')
+    params=$(echo "$dlc" | jq -r '[.data.cases[0].offer.json | .. | objects | select(has("oracleParams")) | .oracleParams][0]')
+    [ "$params" = "null" ] && continue
+    offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+    expected=$(echo "$dlc" | jq -r '.data.cases[0].accept.json.cetAdaptorSignatures.ecdsaAdaptorSignatures | length')
+    count=$($CLI estimate --offer-hex "$offer_hex" 2>/dev/null | jq -r '.data.adaptorSignatureCount // empty')
+    if [ "$count" = "$expected" ]; then
+        checked=$((checked + 1))
+    else
+        echo "❌ Seed $seed with oracleParams: ${count:-no} adaptor signatures, expected $expected"
+    fi
+done
+if [ "$checked" -gt 0 ]; then
+    echo "✅ $checked generated bounded-difference contracts match their accepts"
+else
+    echo "❌ No generated contract had oracleParams"
+fi
+echo
+
+# Test 25: disjoint contracts with several events
+echo "🎯 25. TESTING DISJOINT CONTRACTS"
+echo "--------------------------------"
+
+dlc=$($CLI generate --type dlc --seed 25 --contract disjoint --sub-contracts 3 2>/dev/null)
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+accept_hex=$(echo "$dlc" | jq -r '.data.cases[0].accept.hex')
+infos=$(echo "$dlc" | jq -r '.data.cases[0].offer.json.contractInfo.disjointContractInfo.contractInfos | length')
+if [ "$infos" = "3" ]; then
+    echo "✅ Generated disjoint offer with 3 sub-contracts"
+else
+    echo "❌ Generated offer has ${infos:-no} disjoint sub-contracts"
+fi
+
+report=$($CLI report --offer-hex "$offer_hex" 2>/dev/null || echo '{"status":"error"}')
+contiguous=$(echo "$report" | jq -r '.data as $d | [$d.subContracts[] | .cetStart] == ([0] + [$d.subContracts[:-1][] | .cetStart + .cetCount]) and ([$d.subContracts[].cetCount] | add) == $d.cetCount')
+if [ "$contiguous" = "true" ]; then
+    echo "✅ Report CET ranges are contiguous and cover every CET"
+else
+    echo "❌ Report CET ranges do not partition the CETs"
+fi
+
+txs=$($CLI build-transactions --offer-hex "$offer_hex" --accept-hex "$accept_hex" 2>/dev/null || echo '{"status":"error"}')
+matched=$(echo "$txs" | jq -r '.data as $d | [$d.subContracts[] as $s | $d.cets[$s.cetStart:$s.cetStart + $s.cetCount][] | .subContract == $s.index] | all and length == ($d.cets | length)')
+if [ "$(echo "$txs" | jq -r '.data.cetCount')" = "$(echo "$report" | jq -r '.data.cetCount')" ] && [ "$matched" = "true" ]; then
+    echo "✅ Built CETs fall in their sub-contract's range"
+else
+    echo "❌ Built CETs disagree with the sub-contract ranges"
+fi
+
+events=$($CLI create-oracle-announcement --event-id first --event-id second --event-id third 2>/dev/null)
+oracles=$(echo "$events" | jq -r '[.data[].data.oraclePublicKey] | unique | length')
+if [ "$(echo "$events" | jq -r '.data | length')" = "3" ] && [ "$oracles" = "1" ]; then
+    echo "✅ One oracle announces an event per sub-contract"
+else
+    echo "❌ Multi-event announcement failed"
+fi
+echo
+
+# Test 26: input encodings and serialize output encodings
+echo "🔡 26. TESTING INPUT ENCODING DETECTION"
+echo "---------------------------------------"
+
+dlc=$($CLI generate --type dlc --seed 26 2>/dev/null)
+offer_json=$(echo "$dlc" | jq -c '.data.cases[0].offer.json')
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+
+offer_base64=$(echo "$offer_json" | $CLI serialize -t offer --encoding base64 2>/dev/null | jq -r '.data')
+echo "$offer_json" | $CLI serialize -t offer --encoding binary > /tmp/test_offer.bin 2>/dev/null
+if [ "$(echo "$offer_base64" | base64 -d | od -An -v -tx1 | tr -d ' \n')" = "$offer_hex" ] \
+    && [ "$(od -An -v -tx1 /tmp/test_offer.bin | tr -d ' \n')" = "$offer_hex" ]; then
+    echo "✅ serialize --encoding emits base64 and raw binary"
+else
+    echo "❌ serialize --encoding output does not match the hex encoding"
+fi
+
+echo "$offer_hex" > /tmp/test_offer.hex
+echo "$offer_base64" > /tmp/test_offer.b64
+for source in "hex:/tmp/test_offer.hex" "base64:/tmp/test_offer.b64" "binary:/tmp/test_offer.bin"; do
+    expected=${source%%:*}
+    file=${source#*:}
+    result=$($CLI deserialize --input "$file" 2>/dev/null || echo '{"status":"error"}')
+    if [ "$(echo "$result" | jq -r '.inputEncoding')" = "$expected" ] \
+        && [ "$(echo "$result" | jq -r '.messageType')" = "offer" ]; then
+        echo "✅ $expected file detected and decoded"
+    else
+        echo "❌ $expected file not detected: $(echo "$result" | jq -r '.inputEncoding // .message')"
+    fi
+done
+
+result=$($CLI deserialize < /tmp/test_offer.b64 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$result" | jq -r '.inputEncoding')" = "base64" ] && [ "$(echo "$result" | jq -r '.status')" = "success" ]; then
+    echo "✅ Offer read from stdin"
+else
+    echo "❌ Offer not read from stdin"
+fi
+
+bad=$($CLI deserialize --input /tmp/test_offer.hex --encoding rot13 2>/dev/null || echo '{"status":"exit"}')
+if [ "$(echo "$bad" | jq -r '.status')" = "error" ]; then
+    echo "✅ Unknown encoding reported as a JSON error"
+else
+    echo "❌ Unknown encoding not reported as a JSON error"
+fi
+rm -f /tmp/test_offer.hex /tmp/test_offer.b64 /tmp/test_offer.bin
+echo
+
+# Test 27: explicit type hints and strict decoding
+echo "🏷️  27. TESTING TYPE HINTS AND STRICT MODE"
+echo "------------------------------------------"
+
+dlc=$($CLI generate --type dlc --seed 27 2>/dev/null)
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+contract_hex=$(echo "$dlc" | jq -c '.data.cases[0].offer.json.contractInfo' | $CLI serialize -t contract-info 2>/dev/null | jq -r '.data')
+
+hinted=$($CLI deserialize --hex "$contract_hex" --type contract-info 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$hinted" | jq -r '.messageType')" = "contract-info" ] && [ "$(echo "$hinted" | jq -r '.detection')" = "type-hint" ]; then
+    echo "✅ --type contract-info decodes with the type-hint detection path"
+else
+    echo "❌ --type contract-info failed: $(echo "$hinted" | jq -r '.message')"
+fi
+
+detected=$($CLI deserialize --hex "$offer_hex" 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$detected" | jq -r '.detection')" = "wire-type" ]; then
+    echo "✅ Offer detected from its wire type"
+else
+    echo "❌ Offer detection path: $(echo "$detected" | jq -r '.detection')"
+fi
+
+for hint in accept oracle-info; do
+    result=$($CLI deserialize --hex "$offer_hex" --type "$hint" 2>/dev/null || echo '{"status":"error"}')
+    if [ "$(echo "$result" | jq -r '.status')" = "error" ]; then
+        echo "✅ Offer rejected when hinted as $hint"
+    else
+        echo "❌ Offer misclassified as $(echo "$result" | jq -r '.messageType')"
+    fi
+done
+
+lenient=$($CLI deserialize --hex "${offer_hex}00" 2>/dev/null || echo '{"status":"error"}')
+strict=$($CLI deserialize --hex "${offer_hex}00" --strict 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$lenient" | jq -r '.trailingBytes')" = "1" ] && [ "$(echo "$strict" | jq -r '.status')" = "error" ]; then
+    echo "✅ Trailing bytes reported, and rejected with --strict"
+else
+    echo "❌ Trailing bytes not handled: $(echo "$strict" | jq -r '.message')"
+fi
+echo
+
+# Test 28: annotated field-by-field breakdown
+echo "🔬 28. TESTING EXPLAIN"
+echo "----------------------"
+
+dlc=$($CLI generate --type dlc --seed 28 2>/dev/null)
+offer_hex=$(echo "$dlc" | jq -r '.data.cases[0].offer.hex')
+explained=$($CLI explain --hex "$offer_hex" --format json 2>/dev/null || echo '{"status":"error"}')
+header=$(echo "$explained" | jq -r '[.data.fields[] | select(.name == "type" or .name == "protocolVersion" or .name == "chainHash" or .name == "temporaryContractId") | "\(.offset)+\(.length)"] | join(",")')
+if [ "$header" = "0+2,2+4,7+32,39+32" ]; then
+    echo "✅ Offer header fields at the expected offsets and lengths"
+else
+    echo "❌ Offer header fields misplaced: $header"
+fi
+
+contiguous=$(echo "$explained" | jq -r '.data as $d | [$d.fields[] | select(.kind != "group")] as $leaves
+    | ([range(1; $leaves | length) | select($leaves[.].offset != $leaves[. - 1].offset + $leaves[. - 1].length)] | length) == 0
+    and ($leaves[-1].offset + $leaves[-1].length) == $d.length
+    and $d.length == ('"${#offer_hex}"' / 2)')
+if [ "$contiguous" = "true" ]; then
+    echo "✅ Leaf fields tile the whole offer without gaps"
+else
+    echo "❌ Leaf fields leave gaps or overlap"
+fi
+
+event_hex=$($CLI create-oracle-announcement --event-type digit-decomposition 2>/dev/null \
+    | jq -c '.data.oracleEvent | (.. | objects | select(has("precision"))).precision |= -2' \
+    | $CLI serialize -t oracle-event 2>/dev/null | jq -r '.data')
+precision=$($CLI explain --hex "$event_hex" --type oracle-event --format json 2>/dev/null | jq -r '.data.fields[] | select(.name | endswith("precision")) | .value')
+if [ "$precision" = "-2" ]; then
+    echo "✅ Negative digit precision shown as a signed value"
+else
+    echo "❌ Digit precision shown as ${precision:-nothing}"
+fi
+echo
+
+# Test 29: structural diff of two messages
+echo "🆚 29. TESTING MESSAGE DIFF"
+echo "---------------------------"
+
+offer_hex=$(jq -r '.offer_message.serialized' "$VECTORS_DIR/enum_single_oracle_test.json")
+offer_json=$($CLI deserialize --hex "$offer_hex" 2>/dev/null | jq -c '.data')
+changed_json=$(echo "$offer_json" | jq -c '.offerCollateral += 1')
+changed_hex=$(echo "$changed_json" | $CLI serialize -t offer 2>/dev/null | jq -r '.data')
+
+diff=$($CLI diff --left "$offer_hex" --right "$changed_hex" 2>/dev/null || echo '{"status":"error"}')
+paths=$(echo "$diff" | jq -r '[.data.differences[].path] | join(",")')
+field=$(echo "$diff" | jq -r '.data.firstDifferingByte.leftField')
+if [ "$paths" = "\$.offerCollateral" ] && [ "$field" = "offerCollateral" ]; then
+    echo "✅ Changed collateral reported at \$.offerCollateral and located in the wire encoding"
+else
+    echo "❌ Diff reported paths '$paths' and field '$field'"
+fi
+
+mixed=$($CLI diff --left "$offer_hex" --right "$changed_json" --type offer 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$mixed" | jq -r '.data.firstDifferingByte.offset')" = "$(echo "$diff" | jq -r '.data.firstDifferingByte.offset')" ]; then
+    echo "✅ Hex and JSON sides compare like two hex messages"
+else
+    echo "❌ Hex/JSON diff disagrees: $(echo "$mixed" | jq -r '.message')"
+fi
+
+left_hex=$offer_hex
+right_hex=$(jq -r '.offer_message.serialized' "$VECTORS_DIR/enum_3_of_3_test.json")
+expected=$(jq -n --arg a "$left_hex" --arg b "$right_hex" \
+    '[range(0; ([$a, $b] | map(length) | min) / 2) | select($a[. * 2:. * 2 + 2] != $b[. * 2:. * 2 + 2])][0]')
+vectors=$($CLI diff --left "$left_hex" --right "$right_hex" 2>/dev/null || echo '{"status":"error"}')
+if [ "$(echo "$vectors" | jq -r '.data.firstDifferingByte.offset')" = "$expected" ] \
+    && [ "$(echo "$vectors" | jq -r '.data.differences | length')" -gt 0 ]; then
+    echo "✅ Two test vectors first differ at byte $expected"
+else
+    echo "❌ First differing byte $(echo "$vectors" | jq -r '.data.firstDifferingByte.offset'), expected $expected"
+fi
+
+same=$($CLI diff --left "$offer_hex" --right "$offer_hex" 2>/dev/null | jq -r '.data.identical')
+if [ "$same" = "true" ]; then
+    echo "✅ Identical messages reported as identical"
+else
+    echo "❌ Identical messages not recognized"
+fi
+echo
+
 # Summary
 echo "📊 COMPATIBILITY TEST SUMMARY"
 echo "============================="
@@ -176,6 +1065,31 @@ echo "✅ All message types available in help"
 echo "✅ Validation correctly rejects invalid JSON"
 echo "✅ Message type detection working"
 echo "✅ Serialization/deserialization framework complete"
+echo "✅ Offer/accept/sign flow validation"
+echo "✅ Contract id derivation from funding transaction"
+echo "✅ Mutation fuzzing of message readers"
+echo "✅ Differential harness against an external decoder"
+echo "✅ Seeded generation of protocol-valid messages"
+echo "✅ Scenario-driven dlcspecs test vectors"
+echo "✅ DLC input construction and validation"
+echo "✅ Batch funding groups and batch funding transactions"
+echo "✅ Order-book offer/accept messages"
+echo "✅ Negotiation fields and rounding-interval renegotiation"
+echo "✅ LevelDB store record decoding"
+echo "✅ Oracle event containers and identifiers"
+echo "✅ Legacy v0 message upgrade and downgrade"
+echo "✅ Protocol version and contract flag reporting"
+echo "✅ Chain hash networks and address rendering"
+echo "✅ Funding PSBT export and signature extraction"
+echo "✅ Refund transaction and signature verification"
+echo "✅ Pre-trade contract report"
+echo "✅ CET count and message size estimates"
+echo "✅ Bounded-difference multi-oracle CETs"
+echo "✅ Disjoint contracts with per-sub-contract CET ranges"
+echo "✅ Hex, base64 and binary input detection and output encodings"
+echo "✅ Type hints, detection paths and strict trailing-byte checks"
+echo "✅ Annotated field offsets and lengths with explain"
+echo "✅ Structural diff paths and first differing byte"
 echo
 echo "🎯 KEY ACHIEVEMENTS:"
 echo "  • Enhanced CLI with 6 additional message types"
@@ -0,0 +1,9 @@
+#![no_main]
+
+use dlc_messages::{AcceptDlc, ACCEPT_TYPE};
+use libfuzzer_sys::fuzz_target;
+use rust_dlc_cli_fuzz::check_wire;
+
+fuzz_target!(|data: &[u8]| {
+    check_wire::<AcceptDlc>(data, ACCEPT_TYPE);
+});
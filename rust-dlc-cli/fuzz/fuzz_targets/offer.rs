@@ -0,0 +1,9 @@
+#![no_main]
+
+use dlc_messages::{OfferDlc, OFFER_TYPE};
+use libfuzzer_sys::fuzz_target;
+use rust_dlc_cli_fuzz::check_wire;
+
+fuzz_target!(|data: &[u8]| {
+    check_wire::<OfferDlc>(data, OFFER_TYPE);
+});
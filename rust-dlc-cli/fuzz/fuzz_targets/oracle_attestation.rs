@@ -0,0 +1,9 @@
+#![no_main]
+
+use dlc_messages::oracle_msgs::OracleAttestation;
+use libfuzzer_sys::fuzz_target;
+use rust_dlc_cli_fuzz::check_tlv;
+
+fuzz_target!(|data: &[u8]| {
+    check_tlv::<OracleAttestation>(data);
+});
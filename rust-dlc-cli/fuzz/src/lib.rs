@@ -0,0 +1,33 @@
+//! Round-trip checks shared by the fuzz targets: whatever a reader accepts
+//! must re-encode to exactly the bytes it consumed.
+
+use dlc_messages::ser_impls::{read_as_tlv, write_as_tlv};
+use lightning::io::Cursor;
+use lightning::ln::wire::Type;
+use lightning::util::ser::{Readable, Writeable};
+
+/// Decode a message prefixed with its 2-byte wire type and check that it
+/// re-encodes to the consumed bytes.
+pub fn check_wire<T: Readable + Writeable>(data: &[u8], msg_type: u16) {
+    if data.len() < 2 || u16::from_be_bytes([data[0], data[1]]) != msg_type {
+        return;
+    }
+    let mut cursor = Cursor::new(&data[2..]);
+    if let Ok(value) = T::read(&mut cursor) {
+        let consumed = 2 + cursor.position() as usize;
+        let mut encoded = msg_type.to_be_bytes().to_vec();
+        encoded.extend(value.encode());
+        assert_eq!(encoded, data[..consumed], "decode -> encode is not idempotent");
+    }
+}
+
+/// Decode a TLV record and check that it re-encodes to the consumed bytes.
+pub fn check_tlv<T: Readable + Writeable + Type>(data: &[u8]) {
+    let mut cursor = Cursor::new(data);
+    if let Ok(value) = read_as_tlv::<T, _>(&mut cursor) {
+        let consumed = cursor.position() as usize;
+        let mut encoded = Vec::new();
+        write_as_tlv(&value, &mut encoded).expect("re-encoding an accepted TLV failed");
+        assert_eq!(encoded, data[..consumed], "decode -> encode is not idempotent");
+    }
+}
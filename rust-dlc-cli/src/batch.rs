@@ -0,0 +1,539 @@
+//! Batch funding: several contracts sharing one funding transaction.
+//!
+//! node-dlc attaches `BatchFundingGroup` TLVs (type 65430) to offers and
+//! accepts to declare which contracts are funded together. The funding
+//! transaction built here spends the union of both parties' funding inputs
+//! and carries one 2-of-2 output per contract plus one change output per
+//! party. Each contract's CETs and refund transaction are then re-pointed at
+//! its own output of the batch transaction.
+
+use crate::cets::contract_infos;
+use crate::codec::{decode_hex, read_wire_message_with_trailing};
+use crate::input::read_source;
+use crate::transactions::{
+    build_dlc_transactions, compute_contract_id, fund_output_index, funding_input_outpoint,
+    funding_input_value, transaction_json,
+};
+use crate::wire::{hex_vec, NodeMessage, Reader, Writer};
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::script::{Builder, PushBytesBuf};
+use bitcoin::{
+    absolute, transaction, Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Witness,
+};
+use dlc::DlcTransactions;
+use dlc_messages::{AcceptDlc, FundingInput, OfferDlc, ACCEPT_TYPE, OFFER_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// Change outputs below this value are left to the miners, as rust-dlc does.
+const DUST_LIMIT: u64 = 1000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFundingGroup {
+    #[serde(default, with = "hex_vec")]
+    pub temp_contract_ids: Vec<Vec<u8>>,
+    #[serde(default, with = "hex_vec")]
+    pub contract_ids: Vec<Vec<u8>>,
+    /// Collateral set aside for the group; node-dlc's JSON calls it
+    /// `totalCollateral`.
+    #[serde(rename = "totalCollateral")]
+    pub allocated_collateral: u64,
+    pub event_ids: Vec<String>,
+}
+
+impl NodeMessage for BatchFundingGroup {
+    const TYPE: u64 = 65430;
+    const NAME: &'static str = "BatchFundingGroup";
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(BatchFundingGroup {
+            temp_contract_ids: r.vec("tempContractIds", |r| {
+                Ok(r.var_bytes("tempContractId")?.to_vec())
+            })?,
+            contract_ids: r.vec("contractIds", |r| Ok(r.var_bytes("contractId")?.to_vec()))?,
+            allocated_collateral: r.u64("allocatedCollateral")?,
+            event_ids: r.vec("eventIds", |r| r.string("eventId"))?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.vec(&self.temp_contract_ids, |w, id| w.var_bytes(id));
+        w.vec(&self.contract_ids, |w, id| w.var_bytes(id));
+        w.u64(self.allocated_collateral);
+        w.vec(&self.event_ids, |w, id| w.string(id));
+    }
+}
+
+/// Collect the batch funding groups among the TLVs that follow a message,
+/// skipping records of other types with a warning.
+fn trailing_groups(
+    bytes: &[u8],
+    source: &str,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<BatchFundingGroup>> {
+    let mut groups = Vec::new();
+    let mut r = Reader::new(bytes);
+    while !r.is_empty() {
+        let start = r.position();
+        let msg_type = r.bigsize("type")?;
+        let body = r.var_bytes("value")?;
+        if msg_type == BatchFundingGroup::TYPE {
+            groups.push(BatchFundingGroup::read(&mut Reader::new(
+                &bytes[start..r.position()],
+            ))?);
+        } else {
+            warnings.push(format!(
+                "Skipped trailing TLV type {} ({} bytes) after {}",
+                msg_type,
+                body.len(),
+                source
+            ));
+        }
+    }
+    Ok(groups)
+}
+
+#[derive(Deserialize)]
+struct BatchContractSpec {
+    offer: String,
+    accept: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchSpec {
+    contracts: Vec<BatchContractSpec>,
+    /// Groups given as hex or JSON, in addition to those attached to the
+    /// offers and accepts.
+    #[serde(default)]
+    batch_funding_groups: Vec<Value>,
+}
+
+struct BatchContract {
+    offer: OfferDlc,
+    accept: AcceptDlc,
+    transactions: DlcTransactions,
+    fund_output: TxOut,
+}
+
+impl BatchContract {
+    fn total_collateral(&self) -> u64 {
+        contract_infos(&self.offer.contract_info).0
+    }
+
+    /// Each party's share of the fund output: its collateral plus half of
+    /// the CET and refund fee reserved on top of the total collateral.
+    fn contributions(&self) -> (u64, u64) {
+        let reserve = self.fund_output.value.to_sat() - self.total_collateral();
+        (
+            self.offer.offer_collateral + reserve - reserve / 2,
+            self.accept.accept_collateral + reserve / 2,
+        )
+    }
+}
+
+fn read_spec(path: &str) -> Result<(Vec<BatchContract>, Vec<BatchFundingGroup>, Vec<String>)> {
+    let source = if path == "-" { None } else { Some(path) };
+    let spec: BatchSpec = serde_json::from_slice(&read_source(source)?)
+        .context("Failed to parse batch description")?;
+    if spec.contracts.is_empty() {
+        bail!("A batch needs at least one contract");
+    }
+
+    let mut groups = Vec::new();
+    let mut contracts = Vec::new();
+    let mut warnings = Vec::new();
+    for (i, contract) in spec.contracts.iter().enumerate() {
+        let offer_bytes = decode_hex(&contract.offer)?;
+        let accept_bytes = decode_hex(&contract.accept)?;
+        let (offer, offer_tlvs) =
+            read_wire_message_with_trailing::<OfferDlc>(&offer_bytes, OFFER_TYPE, "OfferDlc")
+                .with_context(|| format!("Failed to decode offer {}", i))?;
+        let (accept, accept_tlvs) =
+            read_wire_message_with_trailing::<AcceptDlc>(&accept_bytes, ACCEPT_TYPE, "AcceptDlc")
+                .with_context(|| format!("Failed to decode accept {}", i))?;
+        groups.extend(trailing_groups(
+            offer_tlvs,
+            &format!("offer {}", i),
+            &mut warnings,
+        )?);
+        groups.extend(trailing_groups(
+            accept_tlvs,
+            &format!("accept {}", i),
+            &mut warnings,
+        )?);
+
+        let transactions = build_dlc_transactions(&offer, &accept)
+            .with_context(|| format!("Failed to build transactions for contract {}", i))?;
+        let fund_output =
+            transactions.fund.output[fund_output_index(&transactions)? as usize].clone();
+        contracts.push(BatchContract {
+            offer,
+            accept,
+            transactions,
+            fund_output,
+        });
+    }
+
+    for group in &spec.batch_funding_groups {
+        groups.push(match group {
+            Value::String(hex_str) => {
+                crate::wire::decode::<BatchFundingGroup>(&decode_hex(hex_str)?)?.0
+            }
+            json => serde_json::from_value(json.clone())
+                .context("Failed to parse JSON as BatchFundingGroup")?,
+        });
+    }
+    // Offers and accepts usually repeat the same groups.
+    let mut seen = BTreeSet::new();
+    groups.retain(|group| seen.insert(group.encode()));
+
+    Ok((contracts, groups, warnings))
+}
+
+fn script_sig(input: &FundingInput) -> Result<ScriptBuf> {
+    if input.redeem_script.is_empty() {
+        return Ok(ScriptBuf::new());
+    }
+    let push = PushBytesBuf::try_from(input.redeem_script.to_bytes())
+        .context("Redeem script is too large")?;
+    Ok(Builder::new().push_slice(push).into_script())
+}
+
+/// Virtual size of `tx` once every input carries a witness of at most
+/// `witness_len` bytes in total.
+fn estimated_vsize(tx: &Transaction, witness_len: u64) -> u64 {
+    // Segwit marker and flag, plus one witness item count per input.
+    let witness = 2 + tx.input.len() as u64 + witness_len;
+    (tx.weight().to_wu() + witness).div_ceil(4)
+}
+
+fn build_batch(
+    contracts: &mut [BatchContract],
+) -> Result<(Transaction, u64, Vec<u16>, Vec<String>)> {
+    let fee_rate = contracts[0].offer.fee_rate_per_vb;
+    if let Some(other) = contracts
+        .iter()
+        .find(|c| c.offer.fee_rate_per_vb != fee_rate)
+    {
+        bail!(
+            "All contracts in a batch must use the same fee rate ({} vs {} sat/vB)",
+            fee_rate,
+            other.offer.fee_rate_per_vb
+        );
+    }
+    // The batch pays each party's change to a single output.
+    let first = &contracts[0];
+    for contract in contracts.iter() {
+        if (&contract.offer.change_spk, contract.offer.change_serial_id)
+            != (&first.offer.change_spk, first.offer.change_serial_id)
+        {
+            bail!(
+                "All contracts in a batch must share the offer party's change script and serial id"
+            );
+        }
+        if (
+            &contract.accept.change_spk,
+            contract.accept.change_serial_id,
+        ) != (&first.accept.change_spk, first.accept.change_serial_id)
+        {
+            bail!(
+                "All contracts in a batch must share the accept party's change script and serial id"
+            );
+        }
+    }
+    let mut temporary_ids = BTreeSet::new();
+    for contract in contracts.iter() {
+        if !temporary_ids.insert(contract.offer.temporary_contract_id) {
+            bail!(
+                "Temporary contract id {} appears twice in the batch",
+                hex::encode(contract.offer.temporary_contract_id)
+            );
+        }
+    }
+
+    // Both parties' inputs, each outpoint once, ordered by serial id.
+    let mut seen = BTreeSet::new();
+    let mut inputs = Vec::new();
+    let (mut offer_inputs, mut accept_inputs) = (0u64, 0u64);
+    for contract in contracts.iter() {
+        for (input, is_offer) in contract
+            .offer
+            .funding_inputs
+            .iter()
+            .map(|input| (input, true))
+            .chain(
+                contract
+                    .accept
+                    .funding_inputs
+                    .iter()
+                    .map(|input| (input, false)),
+            )
+        {
+            let outpoint = funding_input_outpoint(input)?;
+            if !seen.insert(outpoint) {
+                continue;
+            }
+            let value = funding_input_value(input)?.to_sat();
+            if is_offer {
+                offer_inputs += value;
+            } else {
+                accept_inputs += value;
+            }
+            inputs.push((input.input_serial_id, outpoint, input));
+        }
+    }
+    inputs.sort_by_key(|(serial_id, _, _)| *serial_id);
+
+    let witness_len: u64 = inputs
+        .iter()
+        .map(|(_, _, input)| input.max_witness_len as u64)
+        .sum();
+    let tx_inputs = inputs
+        .iter()
+        .map(|(_, outpoint, input)| {
+            Ok(TxIn {
+                previous_output: *outpoint,
+                script_sig: script_sig(input)?,
+                sequence: Sequence(input.sequence),
+                witness: Witness::new(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Fund outputs tagged with their contract, change outputs untagged.
+    let mut outputs: Vec<(u64, TxOut, Option<usize>)> = contracts
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            (
+                c.offer.fund_output_serial_id,
+                c.fund_output.clone(),
+                Some(i),
+            )
+        })
+        .collect();
+    let change = [
+        (
+            first.offer.change_serial_id,
+            first.offer.change_spk.clone(),
+            offer_inputs,
+        ),
+        (
+            first.accept.change_serial_id,
+            first.accept.change_spk.clone(),
+            accept_inputs,
+        ),
+    ];
+
+    let (offer_fund, accept_fund) = contracts.iter().fold((0, 0), |(o, a), c| {
+        let (offer, accept) = c.contributions();
+        (o + offer, a + accept)
+    });
+    let funds = [("offer", offer_fund), ("accept", accept_fund)];
+
+    let mut estimate = Transaction {
+        version: transaction::Version::TWO,
+        lock_time: absolute::LockTime::ZERO,
+        input: tx_inputs,
+        output: Vec::new(),
+    };
+
+    // The fee is split evenly and estimated with the change outputs that
+    // survive the dust limit, so dropping one is followed by a new estimate.
+    let mut warnings = Vec::new();
+    let mut kept = [true, true];
+    let values = loop {
+        estimate.output = outputs
+            .iter()
+            .map(|(_, output, _)| output.clone())
+            .collect();
+        estimate
+            .output
+            .extend(
+                change
+                    .iter()
+                    .zip(kept)
+                    .filter(|(_, kept)| *kept)
+                    .map(|((_, spk, _), _)| TxOut {
+                        value: Amount::ZERO,
+                        script_pubkey: spk.clone(),
+                    }),
+            );
+        let fee = estimated_vsize(&estimate, witness_len) * fee_rate;
+        let fee_shares = [fee - fee / 2, fee / 2];
+
+        let values = change
+            .iter()
+            .zip(funds)
+            .zip(fee_shares)
+            .map(|(((_, _, available), (party, fund)), fee_share)| {
+                available.checked_sub(fund + fee_share).ok_or_else(|| {
+                    anyhow!(
+                        "The {} party's inputs ({} sats) do not cover its collateral and fees ({} sats)",
+                        party,
+                        available,
+                        fund + fee_share
+                    )
+                })
+            })
+            .collect::<Result<Vec<u64>>>()?;
+        let dust: Vec<usize> = (0..2)
+            .filter(|i| kept[*i] && values[*i] < DUST_LIMIT)
+            .collect();
+        if dust.is_empty() {
+            break values;
+        }
+        for i in dust {
+            kept[i] = false;
+            warnings.push(format!(
+                "Dropped {} change output of {} sats below the dust limit",
+                funds[i].0, values[i]
+            ));
+        }
+    };
+
+    for ((serial_id, spk, _), (value, kept)) in change.into_iter().zip(values.into_iter().zip(kept))
+    {
+        if kept {
+            outputs.push((
+                serial_id,
+                TxOut {
+                    value: Amount::from_sat(value),
+                    script_pubkey: spk,
+                },
+                None,
+            ));
+        }
+    }
+    outputs.sort_by_key(|(serial_id, _, _)| *serial_id);
+
+    let mut indices = vec![0u16; contracts.len()];
+    for (index, (_, _, contract)) in outputs.iter().enumerate() {
+        if let Some(contract) = contract {
+            indices[*contract] = u16::try_from(index).context("Too many outputs")?;
+        }
+    }
+    estimate.output = outputs.into_iter().map(|(_, output, _)| output).collect();
+    let fund_tx = estimate;
+    // Dropped change is left to the miners, so the fee is whatever the
+    // outputs do not spend.
+    let spent: u64 = fund_tx
+        .output
+        .iter()
+        .map(|output| output.value.to_sat())
+        .sum();
+    let fee = offer_inputs + accept_inputs - spent;
+
+    // Each contract's CETs and refund spend its own output of the batch.
+    let txid = fund_tx.compute_txid();
+    for (contract, index) in contracts.iter_mut().zip(&indices) {
+        let outpoint = OutPoint {
+            txid,
+            vout: *index as u32,
+        };
+        let transactions = &mut contract.transactions;
+        for tx in transactions
+            .cets
+            .iter_mut()
+            .chain(std::iter::once(&mut transactions.refund))
+        {
+            tx.input[0].previous_output = outpoint;
+        }
+    }
+
+    Ok((fund_tx, fee, indices, warnings))
+}
+
+pub fn build_batch_funding(spec_path: &str) -> Result<()> {
+    let (mut contracts, mut groups, mut warnings) = match read_spec(spec_path) {
+        Ok(parsed) => parsed,
+        Err(e) => return crate::output_error(&format!("{:#}", e)),
+    };
+    let (fund_tx, fee, indices) = match build_batch(&mut contracts) {
+        Ok((fund_tx, fee, indices, build_warnings)) => {
+            warnings.extend(build_warnings);
+            (fund_tx, fee, indices)
+        }
+        Err(e) => return crate::output_error(&format!("{:#}", e)),
+    };
+    let txid = fund_tx.compute_txid();
+
+    let contract_ids: Vec<[u8; 32]> = contracts
+        .iter()
+        .zip(&indices)
+        .map(|(c, index)| compute_contract_id(txid, *index, &c.offer.temporary_contract_id))
+        .collect();
+
+    let mut contracts_json = Vec::new();
+    for ((contract, index), contract_id) in contracts.iter().zip(&indices).zip(&contract_ids) {
+        let cets = contract
+            .transactions
+            .cets
+            .iter()
+            .map(transaction_json)
+            .collect::<Result<Vec<_>>>()?;
+        contracts_json.push(json!({
+            "temporaryContractId": hex::encode(contract.offer.temporary_contract_id),
+            "contractId": hex::encode(contract_id),
+            "fundOutputIndex": index,
+            "fundOutputValue": contract.fund_output.value.to_sat(),
+            "totalCollateral": contract.total_collateral(),
+            "refundTransaction": transaction_json(&contract.transactions.refund)?,
+            "cetCount": cets.len(),
+            "cets": cets,
+        }));
+    }
+
+    // Fill in the final contract ids of the contracts each group names.
+    let mut groups_json = Vec::new();
+    for group in groups.iter_mut() {
+        let mut collateral = 0;
+        group.contract_ids.clear();
+        for temp_id in &group.temp_contract_ids {
+            match contracts
+                .iter()
+                .position(|c| c.offer.temporary_contract_id[..] == temp_id[..])
+            {
+                Some(i) => {
+                    collateral += contracts[i].total_collateral();
+                    group.contract_ids.push(contract_ids[i].to_vec());
+                }
+                None => warnings.push(format!(
+                    "Batch funding group names temporary contract id {} which is not in the batch",
+                    hex::encode(temp_id)
+                )),
+            }
+        }
+        if collateral > group.allocated_collateral {
+            warnings.push(format!(
+                "Contracts in group {:?} lock {} sats, more than the {} sats allocated",
+                group.event_ids, collateral, group.allocated_collateral
+            ));
+        }
+        groups_json.push(json!({
+            "group": group,
+            "hex": hex::encode(group.encode()),
+        }));
+    }
+
+    let data = json!({
+        "fundTransaction": transaction_json(&fund_tx)?,
+        "fee": fee,
+        "feeRatePerVb": contracts[0].offer.fee_rate_per_vb,
+        "contracts": contracts_json,
+        "batchFundingGroups": groups_json,
+        "warnings": warnings,
+    });
+    crate::output_success_with_data(
+        "batch-funding",
+        &data,
+        &format!(
+            "Built batch funding transaction {} for {} contracts",
+            txid,
+            contracts.len()
+        ),
+    )
+}
@@ -0,0 +1,325 @@
+//! Enumeration of the CETs and adaptor points implied by a contract info.
+//!
+//! rust-dlc creates one CET per payout (enum outcome or numeric outcome range)
+//! and one adaptor signature per CET, per digit prefix (or, with bounded
+//! difference, per prefix combination) and per combination of oracles able
+//! to attest to it. These helpers reproduce that layout from the
+//! message types so that signature counts can be checked without signing.
+
+use crate::multi_oracle::bounded_difference;
+use crate::payout::range_payouts;
+use anyhow::{bail, Result};
+use dlc_messages::contract_msgs::{ContractDescriptor, ContractInfo, ContractInfoInner};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleInfo};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// The outcomes covered by a single CET.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CetOutcome {
+    Enumerated {
+        outcome: String,
+    },
+    Numeric {
+        start: u64,
+        end: u64,
+        prefixes: Vec<Vec<u64>>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CetInfo {
+    pub offer_payout: u64,
+    pub accept_payout: u64,
+    pub outcome: CetOutcome,
+    /// Number of adaptor signatures required for this CET.
+    pub adaptor_points: u64,
+}
+
+/// Where a sub-contract's CETs and adaptor signatures sit among those of the
+/// whole contract, which lists every sub-contract's in turn.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubContractRange {
+    pub index: usize,
+    pub kind: &'static str,
+    pub event_ids: Vec<String>,
+    pub cet_start: u64,
+    pub cet_count: u64,
+    pub adaptor_signature_start: u64,
+    pub adaptor_signature_count: u64,
+}
+
+/// Total collateral and the (descriptor, oracle info) pairs of a contract info.
+pub fn contract_infos(contract_info: &ContractInfo) -> (u64, Vec<&ContractInfoInner>) {
+    match contract_info {
+        ContractInfo::SingleContractInfo(single) => {
+            (single.total_collateral, vec![&single.contract_info])
+        }
+        ContractInfo::DisjointContractInfo(disjoint) => (
+            disjoint.total_collateral,
+            disjoint.contract_infos.iter().collect(),
+        ),
+    }
+}
+
+pub fn oracle_announcements(oracle_info: &OracleInfo) -> Vec<&OracleAnnouncement> {
+    match oracle_info {
+        OracleInfo::Single(single) => vec![&single.oracle_announcement],
+        OracleInfo::Multi(multi) => multi.oracle_announcements.iter().collect(),
+    }
+}
+
+/// Base of the digit decomposition events of a numeric contract's oracles.
+pub fn digit_base(oracle_info: &OracleInfo) -> Result<u64> {
+    let base = match oracle_announcements(oracle_info)
+        .first()
+        .map(|a| &a.oracle_event.event_descriptor)
+    {
+        Some(EventDescriptor::DigitDecompositionEvent(event)) => event.base as u64,
+        _ => bail!("Numeric contract descriptor requires a digit decomposition event"),
+    };
+    if base < 2 {
+        bail!("Invalid digit decomposition base {}", base);
+    }
+    Ok(base)
+}
+
+/// Number of oracle subsets that can unlock a single outcome.
+pub fn oracle_combinations(oracle_info: &OracleInfo) -> Result<u64> {
+    match oracle_info {
+        OracleInfo::Single(_) => Ok(1),
+        OracleInfo::Multi(multi) => {
+            let n = multi.oracle_announcements.len() as u64;
+            let t = multi.threshold as u64;
+            if t == 0 || t > n {
+                bail!("Invalid oracle threshold {} for {} oracles", t, n);
+            }
+            Ok(binomial(n, t))
+        }
+    }
+}
+
+pub fn binomial(n: u64, k: u64) -> u64 {
+    let k = k.min(n - k);
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+/// Decompose `value` into `nb_digits` digits of `base`, most significant first.
+pub fn decompose(mut value: u64, base: u64, nb_digits: usize) -> Vec<u64> {
+    let mut digits = vec![0; nb_digits];
+    for digit in digits.iter_mut().rev() {
+        *digit = value % base;
+        value /= base;
+    }
+    digits
+}
+
+fn front_groupings(digits: &[u64], base: u64) -> Vec<Vec<u64>> {
+    let mut digits = digits.to_vec();
+    while digits.len() > 1 && digits[digits.len() - 1] == 0 {
+        digits.pop();
+    }
+    if digits.len() == 1 {
+        return vec![digits];
+    }
+
+    let mut groupings = vec![digits.clone()];
+    for i in (1..digits.len()).rev() {
+        for value in digits[i] + 1..base {
+            let mut prefix = digits[..i].to_vec();
+            prefix.push(value);
+            groupings.push(prefix);
+        }
+    }
+    groupings
+}
+
+fn back_groupings(digits: &[u64], base: u64) -> Vec<Vec<u64>> {
+    let mut digits = digits.to_vec();
+    while digits.len() > 1 && digits[digits.len() - 1] == base - 1 {
+        digits.pop();
+    }
+    if digits.len() == 1 {
+        return vec![digits];
+    }
+
+    let mut groupings = Vec::new();
+    for i in 1..digits.len() {
+        for value in 0..digits[i] {
+            let mut prefix = digits[..i].to_vec();
+            prefix.push(value);
+            groupings.push(prefix);
+        }
+    }
+    groupings.push(digits);
+    groupings
+}
+
+/// Minimal set of digit prefixes covering exactly the outcomes `start..=end`
+/// (the CET compression algorithm from the dlcspecs).
+pub fn group_by_ignoring_digits(
+    start: u64,
+    end: u64,
+    base: u64,
+    nb_digits: usize,
+) -> Vec<Vec<u64>> {
+    let start_digits = decompose(start, base, nb_digits);
+    if start == end {
+        return vec![start_digits];
+    }
+    let end_digits = decompose(end, base, nb_digits);
+
+    let prefix_len = start_digits
+        .iter()
+        .zip(end_digits.iter())
+        .take_while(|(s, e)| s == e)
+        .count();
+    let prefix = &start_digits[..prefix_len];
+    let start_suffix = &start_digits[prefix_len..];
+    let end_suffix = &end_digits[prefix_len..];
+
+    let groupings =
+        if start_suffix.iter().all(|d| *d == 0) && end_suffix.iter().all(|d| *d == base - 1) {
+            vec![Vec::new()]
+        } else if start_suffix.len() == 1 {
+            (start_suffix[0]..=end_suffix[0]).map(|d| vec![d]).collect()
+        } else {
+            let mut groupings = front_groupings(start_suffix, base);
+            groupings.extend((start_suffix[0] + 1..end_suffix[0]).map(|d| vec![d]));
+            groupings.extend(back_groupings(end_suffix, base));
+            groupings
+        };
+
+    groupings
+        .into_iter()
+        .map(|suffix| {
+            let mut digits = prefix.to_vec();
+            digits.extend(suffix);
+            digits
+        })
+        .collect()
+}
+
+/// CETs of a single (descriptor, oracle info) pair, in rust-dlc order.
+pub fn sub_contract_cets(info: &ContractInfoInner, total_collateral: u64) -> Result<Vec<CetInfo>> {
+    let combinations = oracle_combinations(&info.oracle_info)?;
+
+    match &info.contract_descriptor {
+        ContractDescriptor::EnumeratedContractDescriptor(descriptor) => Ok(descriptor
+            .payouts
+            .iter()
+            .map(|payout| CetInfo {
+                offer_payout: payout.offer_payout,
+                accept_payout: total_collateral.saturating_sub(payout.offer_payout),
+                outcome: CetOutcome::Enumerated {
+                    outcome: payout.outcome.clone(),
+                },
+                adaptor_points: combinations,
+            })
+            .collect()),
+        ContractDescriptor::NumericOutcomeContractDescriptor(descriptor) => {
+            let base = digit_base(&info.oracle_info)?;
+            let bounded = bounded_difference(&info.oracle_info, base, descriptor.num_digits)?;
+
+            let ranges = range_payouts(descriptor, total_collateral)?;
+            Ok(ranges
+                .into_iter()
+                .map(|range| {
+                    let prefixes = group_by_ignoring_digits(
+                        range.start,
+                        range.end(),
+                        base,
+                        descriptor.num_digits as usize,
+                    );
+                    // With bounded difference each prefix of the primary
+                    // oracle needs a signature per prefix combination.
+                    let prefix_points = match &bounded {
+                        Some((bounded, threshold)) => prefixes
+                            .iter()
+                            .map(|prefix| bounded.combinations(prefix, *threshold).len() as u64)
+                            .sum(),
+                        None => prefixes.len() as u64,
+                    };
+                    CetInfo {
+                        offer_payout: range.offer_payout,
+                        accept_payout: total_collateral.saturating_sub(range.offer_payout),
+                        adaptor_points: prefix_points * combinations,
+                        outcome: CetOutcome::Numeric {
+                            start: range.start,
+                            end: range.end(),
+                            prefixes,
+                        },
+                    }
+                })
+                .collect())
+        }
+    }
+}
+
+/// CETs of every sub-contract of a contract info.
+pub fn contract_cets(contract_info: &ContractInfo) -> Result<Vec<Vec<CetInfo>>> {
+    let (total_collateral, infos) = contract_infos(contract_info);
+    infos
+        .into_iter()
+        .map(|info| sub_contract_cets(info, total_collateral))
+        .collect()
+}
+
+/// Total number of adaptor signatures expected in an accept or sign message.
+pub fn adaptor_point_count(contract_info: &ContractInfo) -> Result<u64> {
+    Ok(contract_cets(contract_info)?
+        .iter()
+        .flatten()
+        .map(|cet| cet.adaptor_points)
+        .sum())
+}
+
+/// CET and adaptor signature index ranges of each sub-contract.
+pub fn sub_contract_ranges(
+    contract_info: &ContractInfo,
+    cets: &[Vec<CetInfo>],
+) -> Vec<SubContractRange> {
+    let (_, infos) = contract_infos(contract_info);
+    let mut cet_start = 0;
+    let mut adaptor_signature_start = 0;
+    infos
+        .into_iter()
+        .zip(cets)
+        .enumerate()
+        .map(|(index, (info, cets))| {
+            let mut event_ids: Vec<String> = Vec::new();
+            for announcement in oracle_announcements(&info.oracle_info) {
+                if !event_ids.contains(&announcement.oracle_event.event_id) {
+                    event_ids.push(announcement.oracle_event.event_id.clone());
+                }
+            }
+            let range = SubContractRange {
+                index,
+                kind: match info.contract_descriptor {
+                    ContractDescriptor::EnumeratedContractDescriptor(_) => "enum",
+                    ContractDescriptor::NumericOutcomeContractDescriptor(_) => "numeric",
+                },
+                event_ids,
+                cet_start,
+                cet_count: cets.len() as u64,
+                adaptor_signature_start,
+                adaptor_signature_count: cets.iter().map(|cet| cet.adaptor_points).sum(),
+            };
+            cet_start += range.cet_count;
+            adaptor_signature_start += range.adaptor_signature_count;
+            range
+        })
+        .collect()
+}
+
+/// The outcomes of a CET without its digit prefixes, which can run into the
+/// thousands for a large numeric range.
+pub fn outcome_summary(outcome: &CetOutcome) -> Value {
+    match outcome {
+        CetOutcome::Enumerated { outcome } => json!(outcome),
+        CetOutcome::Numeric { start, end, .. } => json!({ "start": start, "end": end }),
+    }
+}
@@ -0,0 +1,58 @@
+//! Helpers for turning hex strings into rust-dlc wire messages.
+
+use anyhow::{anyhow, bail, Context, Result};
+use dlc_messages::{AcceptDlc, OfferDlc, SignDlc, ACCEPT_TYPE, OFFER_TYPE, SIGN_TYPE};
+use lightning::io::Cursor;
+use lightning::util::ser::Readable;
+
+/// Decode a hex string, tolerating surrounding whitespace and a `0x` prefix.
+pub fn decode_hex(hex_str: &str) -> Result<Vec<u8>> {
+    let trimmed = hex_str.trim();
+    let trimmed = trimmed.strip_prefix("0x").unwrap_or(trimmed);
+    hex::decode(trimmed).context("Failed to decode hex string")
+}
+
+/// Read a message that is prefixed with its 2-byte wire type.
+pub fn read_wire_message<T: Readable>(bytes: &[u8], expected_type: u16, name: &str) -> Result<T> {
+    read_wire_message_with_trailing(bytes, expected_type, name).map(|(value, _)| value)
+}
+
+/// Like [`read_wire_message`], also returning the bytes after the message
+/// (where node-dlc appends optional TLVs).
+pub fn read_wire_message_with_trailing<'a, T: Readable>(
+    bytes: &'a [u8],
+    expected_type: u16,
+    name: &str,
+) -> Result<(T, &'a [u8])> {
+    if bytes.len() < 2 {
+        bail!("{} hex is too short to contain a message type", name);
+    }
+
+    let msg_type = u16::from_be_bytes([bytes[0], bytes[1]]);
+    if msg_type != expected_type {
+        bail!(
+            "Invalid message type: expected {} ({}), got {}",
+            expected_type,
+            name,
+            msg_type
+        );
+    }
+
+    let mut cursor = Cursor::new(&bytes[2..]);
+    let value =
+        T::read(&mut cursor).map_err(|e| anyhow!("Failed to parse {} from hex: {:?}", name, e))?;
+    let consumed = 2 + cursor.position() as usize;
+    Ok((value, &bytes[consumed..]))
+}
+
+pub fn read_offer(hex_str: &str) -> Result<OfferDlc> {
+    read_wire_message(&decode_hex(hex_str)?, OFFER_TYPE, "OfferDlc")
+}
+
+pub fn read_accept(hex_str: &str) -> Result<AcceptDlc> {
+    read_wire_message(&decode_hex(hex_str)?, ACCEPT_TYPE, "AcceptDlc")
+}
+
+pub fn read_sign(hex_str: &str) -> Result<SignDlc> {
+    read_wire_message(&decode_hex(hex_str)?, SIGN_TYPE, "SignDlc")
+}
@@ -0,0 +1,527 @@
+//! Decoding of raw message bytes into JSON, either from an explicit type hint
+//! or by detecting the message type.
+
+use crate::batch::BatchFundingGroup;
+use crate::legacy;
+use crate::negotiation::{NegotiationFieldsV0, NegotiationFieldsV1, NegotiationFieldsV2};
+use crate::oracles::{OracleEventContainer, OracleIdentifier};
+use crate::orders::{
+    self, OrderAccept, OrderIrcInfo, OrderMetadata, OrderOffer, OrderPositionInfo,
+    ORDER_ACCEPT_TYPE, ORDER_OFFER_TYPE, PROTOCOL_VERSION,
+};
+use crate::store::{
+    DlcIds, DlcInfo, DlcTransactionsRecord, DLC_IDS_TYPE, DLC_INFO_TYPE, DLC_TRANSACTIONS_TYPE,
+};
+use crate::wire::{self, Framing, NodeMessage};
+use anyhow::{anyhow, bail, Context, Result};
+use dlc_messages::contract_msgs::{
+    ContractDescriptor, ContractInfo, PayoutFunction, RoundingIntervals,
+};
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation, OracleEvent, OracleInfo};
+use dlc_messages::ser_impls::read_as_tlv;
+use dlc_messages::{
+    AcceptDlc, CetAdaptorSignatures, FundingInput, FundingSignatures, NegotiationFields, OfferDlc,
+    SignDlc, ACCEPT_TYPE, OFFER_TYPE, SIGN_TYPE,
+};
+use lightning::io::Cursor;
+use lightning::ln::wire::Type;
+use lightning::util::ser::Readable;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Wire type of an OracleAnnouncement TLV.
+pub const ORACLE_ANNOUNCEMENT_TYPE: u16 = 55332;
+/// Wire type of an OracleAttestation TLV.
+pub const ORACLE_ATTESTATION_TYPE: u16 = 55400;
+
+/// Every type accepted by `deserialize --type`.
+pub const MESSAGE_TYPES: &[&str] = &[
+    "offer",
+    "accept",
+    "sign",
+    "oracle-announcement",
+    "oracle-attestation",
+    "oracle-event",
+    "oracle-info",
+    "contract-info",
+    "contract-descriptor",
+    "funding-input",
+    "cet-adaptor-signatures",
+    "funding-signatures",
+    "payout-function",
+    "rounding-intervals",
+    "negotiation-fields",
+    "batch-funding-group",
+    "order-offer",
+    "order-accept",
+    "order-metadata",
+    "order-irc-info",
+    "order-position-info",
+    "negotiation-fields-v0",
+    "negotiation-fields-v1",
+    "negotiation-fields-v2",
+    "dlc-transactions",
+    "dlc-ids",
+    "dlc-info",
+    "oracle-event-container",
+    "oracle-identifier",
+    "legacy-offer",
+    "legacy-accept",
+    "legacy-sign",
+];
+
+/// How the message type of a decoded message was determined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Detection {
+    /// The caller named the type explicitly.
+    TypeHint,
+    /// The bytes parsed as a complete TLV record.
+    Tlv,
+    /// The leading 2-byte wire type selected the reader.
+    WireType,
+    /// Component readers were attempted in turn until one succeeded.
+    Trial,
+}
+
+#[derive(Debug)]
+pub struct Decoded {
+    pub message_type: &'static str,
+    pub data: Value,
+    pub detection: Detection,
+    pub message: String,
+    /// Number of input bytes consumed by the reader.
+    pub consumed: usize,
+    /// Fields the reader had to assume or invent.
+    pub warnings: Vec<String>,
+}
+
+/// Versioning fields of a decoded offer, accept, sign or order offer.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolInfo {
+    pub protocol_version: u64,
+    pub supported_version: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_flags: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_hash: Option<String>,
+    /// Unknown versions and reserved flags; empty when the message is
+    /// fully understood.
+    pub warnings: Vec<String>,
+}
+
+/// Read the protocol version, and for offers the contract flags and chain
+/// hash, from a decoded message. Other message types are not versioned.
+pub fn protocol_info(decoded: &Decoded) -> Option<ProtocolInfo> {
+    if !matches!(
+        decoded.message_type,
+        "offer" | "accept" | "sign" | "order-offer"
+    ) {
+        return None;
+    }
+    let protocol_version = decoded.data.get("protocolVersion")?.as_u64()?;
+    let contract_flags = decoded.data.get("contractFlags").and_then(Value::as_u64);
+    let chain_hash = decoded
+        .data
+        .get("chainHash")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let supported_version = protocol_version == PROTOCOL_VERSION as u64;
+    let mut warnings = Vec::new();
+    if !supported_version {
+        warnings.push(format!(
+            "Unknown protocol version {} (this tool implements version {})",
+            protocol_version, PROTOCOL_VERSION
+        ));
+    }
+    // No contract flags are defined yet; every bit is reserved.
+    if let Some(flags) = contract_flags.filter(|flags| *flags != 0) {
+        warnings.push(format!("Reserved contract flags are set: 0x{:02x}", flags));
+    }
+    Some(ProtocolInfo {
+        protocol_version,
+        supported_version,
+        contract_flags,
+        chain_hash,
+        warnings,
+    })
+}
+
+fn decoded<T: Serialize>(
+    message_type: &'static str,
+    value: &T,
+    detection: Detection,
+    message: String,
+    consumed: u64,
+) -> Result<Decoded> {
+    Ok(Decoded {
+        message_type,
+        data: serde_json::to_value(value)
+            .with_context(|| format!("Failed to convert {} to JSON", message_type))?,
+        detection,
+        message,
+        consumed: consumed as usize,
+        warnings: Vec::new(),
+    })
+}
+
+fn read_body<T: Readable + Serialize>(
+    bytes: &[u8],
+    message_type: &'static str,
+    name: &str,
+    detection: Detection,
+) -> Result<Decoded> {
+    let mut cursor = Cursor::new(bytes);
+    let value =
+        T::read(&mut cursor).map_err(|e| anyhow!("Failed to parse as {}: {:?}", name, e))?;
+    decoded(
+        message_type,
+        &value,
+        detection,
+        format!("Successfully deserialized {}", name),
+        cursor.position(),
+    )
+}
+
+fn read_tlv<T: Readable + Type + Serialize>(
+    bytes: &[u8],
+    message_type: &'static str,
+    name: &str,
+    detection: Detection,
+) -> Result<Decoded> {
+    let mut cursor = Cursor::new(bytes);
+    let value = read_as_tlv::<T, _>(&mut cursor)
+        .map_err(|e| anyhow!("Failed to parse as {} TLV: {:?}", name, e))?;
+    decoded(
+        message_type,
+        &value,
+        detection,
+        format!("Successfully deserialized {} (TLV)", name),
+        cursor.position(),
+    )
+}
+
+fn read_wire<T: Readable + Serialize>(
+    bytes: &[u8],
+    expected_type: u16,
+    message_type: &'static str,
+    name: &str,
+    detection: Detection,
+) -> Result<Decoded> {
+    if bytes.len() < 2 {
+        bail!("Input is too short to contain a message type");
+    }
+    let msg_type = u16::from_be_bytes([bytes[0], bytes[1]]);
+    if msg_type != expected_type {
+        bail!(
+            "Invalid message type: expected {} ({}), got {}",
+            expected_type,
+            name,
+            msg_type
+        );
+    }
+
+    // Skip message type prefix (2 bytes) - message.read() expects message body only
+    let mut decoded = read_body::<T>(&bytes[2..], message_type, name, detection)?;
+    decoded.consumed += 2;
+    Ok(decoded)
+}
+
+/// node-dlc messages that rust-dlc lacks, read with the `wire` codec.
+fn read_node<T: NodeMessage>(
+    bytes: &[u8],
+    message_type: &'static str,
+    detection: Detection,
+) -> Result<Decoded> {
+    let (value, consumed) = wire::decode::<T>(bytes)?;
+    let framing = match T::FRAMING {
+        Framing::Tlv => "TLV",
+        Framing::Wire => "wire",
+    };
+    decoded(
+        message_type,
+        &value,
+        detection,
+        format!("Successfully deserialized {} ({})", T::NAME, framing),
+        consumed as u64,
+    )
+}
+
+/// An offer, accept or sign in the current encoding, or in the legacy one
+/// when the body has its shape. Failing both reports both errors.
+fn read_current_or_legacy<T: Readable + Serialize>(
+    bytes: &[u8],
+    expected_type: u16,
+    message_type: &'static str,
+    name: &str,
+    detection: Detection,
+) -> Result<Decoded> {
+    let current = match read_wire::<T>(bytes, expected_type, message_type, name, detection) {
+        Ok(decoded) => return Ok(decoded),
+        Err(e) => e,
+    };
+    if !legacy::looks_legacy(bytes) {
+        return Err(current);
+    }
+    read_legacy(bytes, message_type, detection).map_err(|legacy| {
+        anyhow!(
+            "{:#}; as a legacy v0 {}: {:#}",
+            current,
+            message_type,
+            legacy
+        )
+    })
+}
+
+/// Pre-1.0 offers, accepts and signs, decoded as their current equivalent.
+fn read_legacy(bytes: &[u8], message_type: &'static str, detection: Detection) -> Result<Decoded> {
+    let upgraded = legacy::upgrade(bytes)?;
+    if upgraded.message_type != message_type {
+        bail!(
+            "Expected a legacy {}, got a legacy {}",
+            message_type,
+            upgraded.message_type
+        );
+    }
+    Ok(Decoded {
+        message_type,
+        data: legacy::upgraded_json(&upgraded)?,
+        detection,
+        message: format!("Successfully deserialized legacy v0 {}", message_type),
+        consumed: upgraded.consumed,
+        warnings: upgraded.warnings,
+    })
+}
+
+/// Order offers, noting when an unversioned offer was read as the current
+/// protocol version.
+fn read_order_offer(bytes: &[u8], detection: Detection) -> Result<Decoded> {
+    let mut decoded = read_node::<OrderOffer>(bytes, "order-offer", detection)?;
+    if !bytes.get(2..).is_some_and(orders::has_protocol_version) {
+        decoded.warnings.push(format!(
+            "OrderOffer has no protocolVersion, assumed {}",
+            PROTOCOL_VERSION
+        ));
+    }
+    Ok(decoded)
+}
+
+/// Oracle messages are TLVs, but older encodings use a plain type prefix.
+fn read_tlv_or_wire<T: Readable + Type + Serialize>(
+    bytes: &[u8],
+    expected_type: u16,
+    message_type: &'static str,
+    name: &str,
+    detection: Detection,
+) -> Result<Decoded> {
+    read_tlv::<T>(bytes, message_type, name, detection).or_else(|_| {
+        let mut decoded = read_wire::<T>(bytes, expected_type, message_type, name, detection)?;
+        decoded.message = format!("Successfully deserialized {} (direct)", name);
+        Ok(decoded)
+    })
+}
+
+/// Decode bytes as the named type.
+pub fn decode_as(bytes: &[u8], type_hint: &str) -> Result<Decoded> {
+    let detection = Detection::TypeHint;
+    match type_hint {
+        "offer" => read_wire::<OfferDlc>(bytes, OFFER_TYPE, "offer", "OfferDlc", detection),
+        "accept" => read_wire::<AcceptDlc>(bytes, ACCEPT_TYPE, "accept", "AcceptDlc", detection),
+        "sign" => read_wire::<SignDlc>(bytes, SIGN_TYPE, "sign", "SignDlc", detection),
+        "oracle-announcement" => read_tlv_or_wire::<OracleAnnouncement>(
+            bytes,
+            ORACLE_ANNOUNCEMENT_TYPE,
+            "oracle-announcement",
+            "OracleAnnouncement",
+            detection,
+        ),
+        "oracle-attestation" => read_tlv_or_wire::<OracleAttestation>(
+            bytes,
+            ORACLE_ATTESTATION_TYPE,
+            "oracle-attestation",
+            "OracleAttestation",
+            detection,
+        ),
+        "oracle-event" => read_body::<OracleEvent>(bytes, "oracle-event", "OracleEvent", detection),
+        "oracle-info" => read_body::<OracleInfo>(bytes, "oracle-info", "OracleInfo", detection),
+        "contract-info" => {
+            read_body::<ContractInfo>(bytes, "contract-info", "ContractInfo", detection)
+        }
+        "contract-descriptor" => read_body::<ContractDescriptor>(
+            bytes,
+            "contract-descriptor",
+            "ContractDescriptor",
+            detection,
+        ),
+        "funding-input" => {
+            read_body::<FundingInput>(bytes, "funding-input", "FundingInput", detection)
+        }
+        "cet-adaptor-signatures" => read_body::<CetAdaptorSignatures>(
+            bytes,
+            "cet-adaptor-signatures",
+            "CetAdaptorSignatures",
+            detection,
+        ),
+        "funding-signatures" => read_body::<FundingSignatures>(
+            bytes,
+            "funding-signatures",
+            "FundingSignatures",
+            detection,
+        ),
+        "payout-function" => {
+            read_body::<PayoutFunction>(bytes, "payout-function", "PayoutFunction", detection)
+        }
+        "rounding-intervals" => read_body::<RoundingIntervals>(
+            bytes,
+            "rounding-intervals",
+            "RoundingIntervals",
+            detection,
+        ),
+        "negotiation-fields" => read_body::<NegotiationFields>(
+            bytes,
+            "negotiation-fields",
+            "NegotiationFields",
+            detection,
+        ),
+        "batch-funding-group" => {
+            read_node::<BatchFundingGroup>(bytes, "batch-funding-group", detection)
+        }
+        "order-offer" => read_order_offer(bytes, detection),
+        "order-accept" => read_node::<OrderAccept>(bytes, "order-accept", detection),
+        "order-metadata" => read_node::<OrderMetadata>(bytes, "order-metadata", detection),
+        "order-irc-info" => read_node::<OrderIrcInfo>(bytes, "order-irc-info", detection),
+        "order-position-info" => {
+            read_node::<OrderPositionInfo>(bytes, "order-position-info", detection)
+        }
+        "negotiation-fields-v0" => {
+            read_node::<NegotiationFieldsV0>(bytes, "negotiation-fields-v0", detection)
+        }
+        "negotiation-fields-v1" => {
+            read_node::<NegotiationFieldsV1>(bytes, "negotiation-fields-v1", detection)
+        }
+        "negotiation-fields-v2" => {
+            read_node::<NegotiationFieldsV2>(bytes, "negotiation-fields-v2", detection)
+        }
+        "dlc-transactions" => {
+            read_node::<DlcTransactionsRecord>(bytes, "dlc-transactions", detection)
+        }
+        "dlc-ids" => read_node::<DlcIds>(bytes, "dlc-ids", detection),
+        "dlc-info" => read_node::<DlcInfo>(bytes, "dlc-info", detection),
+        "oracle-event-container" => {
+            read_node::<OracleEventContainer>(bytes, "oracle-event-container", detection)
+        }
+        "oracle-identifier" => read_node::<OracleIdentifier>(bytes, "oracle-identifier", detection),
+        "legacy-offer" => read_legacy(bytes, "offer", detection),
+        "legacy-accept" => read_legacy(bytes, "accept", detection),
+        "legacy-sign" => read_legacy(bytes, "sign", detection),
+        _ => bail!(
+            "Unsupported message type: {} (expected one of: {})",
+            type_hint,
+            MESSAGE_TYPES.join(", ")
+        ),
+    }
+}
+
+/// Try the component types that carry no type prefix, in order.
+fn trial_components(bytes: &[u8]) -> Option<Decoded> {
+    let detection = Detection::Trial;
+    read_body::<ContractInfo>(bytes, "contract-info", "ContractInfo", detection)
+        .or_else(|_| read_body::<OracleInfo>(bytes, "oracle-info", "OracleInfo", detection))
+        .or_else(|_| read_body::<OracleEvent>(bytes, "oracle-event", "OracleEvent", detection))
+        .ok()
+}
+
+/// node-dlc TLV records, tried after the oracle TLVs.
+fn detect_node_tlv(bytes: &[u8]) -> Option<Decoded> {
+    let detection = Detection::Tlv;
+    read_node::<BatchFundingGroup>(bytes, "batch-funding-group", detection)
+        .or_else(|_| read_node::<OrderMetadata>(bytes, "order-metadata", detection))
+        .or_else(|_| read_node::<OrderIrcInfo>(bytes, "order-irc-info", detection))
+        .or_else(|_| read_node::<OrderPositionInfo>(bytes, "order-position-info", detection))
+        .or_else(|_| read_node::<NegotiationFieldsV0>(bytes, "negotiation-fields-v0", detection))
+        .or_else(|_| read_node::<NegotiationFieldsV1>(bytes, "negotiation-fields-v1", detection))
+        .or_else(|_| read_node::<NegotiationFieldsV2>(bytes, "negotiation-fields-v2", detection))
+        .or_else(|_| read_node::<OracleEventContainer>(bytes, "oracle-event-container", detection))
+        .or_else(|_| read_node::<OracleIdentifier>(bytes, "oracle-identifier", detection))
+        .ok()
+}
+
+/// Detect the message type: oracle TLVs first, then the 2-byte wire type,
+/// then untyped components by trial.
+pub fn detect(bytes: &[u8]) -> Result<Decoded> {
+    if let Ok(decoded) = read_tlv::<OracleAnnouncement>(
+        bytes,
+        "oracle-announcement",
+        "OracleAnnouncement",
+        Detection::Tlv,
+    ) {
+        return Ok(decoded);
+    }
+    if let Ok(decoded) = read_tlv::<OracleAttestation>(
+        bytes,
+        "oracle-attestation",
+        "OracleAttestation",
+        Detection::Tlv,
+    ) {
+        return Ok(decoded);
+    }
+    if let Some(decoded) = detect_node_tlv(bytes) {
+        return Ok(decoded);
+    }
+
+    if bytes.len() >= 2 {
+        let msg_type = u16::from_be_bytes([bytes[0], bytes[1]]);
+        let detection = Detection::WireType;
+
+        let result = match msg_type {
+            OFFER_TYPE => {
+                read_current_or_legacy::<OfferDlc>(bytes, msg_type, "offer", "OfferDlc", detection)
+            }
+            ACCEPT_TYPE => read_current_or_legacy::<AcceptDlc>(
+                bytes,
+                msg_type,
+                "accept",
+                "AcceptDlc",
+                detection,
+            ),
+            SIGN_TYPE => {
+                read_current_or_legacy::<SignDlc>(bytes, msg_type, "sign", "SignDlc", detection)
+            }
+            ORACLE_ANNOUNCEMENT_TYPE => read_tlv_or_wire::<OracleAnnouncement>(
+                bytes,
+                msg_type,
+                "oracle-announcement",
+                "OracleAnnouncement",
+                detection,
+            ),
+            ORACLE_ATTESTATION_TYPE => read_tlv_or_wire::<OracleAttestation>(
+                bytes,
+                msg_type,
+                "oracle-attestation",
+                "OracleAttestation",
+                detection,
+            ),
+            ORDER_OFFER_TYPE => read_order_offer(bytes, detection),
+            ORDER_ACCEPT_TYPE => read_node::<OrderAccept>(bytes, "order-accept", detection),
+            DLC_TRANSACTIONS_TYPE => {
+                read_node::<DlcTransactionsRecord>(bytes, "dlc-transactions", detection)
+            }
+            DLC_IDS_TYPE => read_node::<DlcIds>(bytes, "dlc-ids", detection),
+            DLC_INFO_TYPE => read_node::<DlcInfo>(bytes, "dlc-info", detection),
+            _ => {
+                // Try parsing as types without message type prefix (for components like ContractInfo)
+                return trial_components(bytes)
+                    .ok_or_else(|| anyhow!("Failed to deserialize as any known DLC message type"));
+            }
+        };
+
+        return result
+            .map_err(|e| anyhow!("Failed to deserialize as any known DLC message type: {}", e));
+    }
+
+    // For data without type prefix, try parsing as different component types
+    trial_components(bytes)
+        .ok_or_else(|| anyhow!("Failed to deserialize as any known DLC message type"))
+}
@@ -0,0 +1,196 @@
+//! Structural and byte-level comparison of two messages.
+//!
+//! Each side is given as hex or as JSON (which is serialized first), decoded
+//! through the same readers as `deserialize`, and the decoded JSON trees are
+//! compared path by path. The first differing byte of the wire encodings is
+//! mapped back to the field that contains it using the `explain` walker.
+
+use crate::codec::decode_hex;
+use crate::decode::{decode_as, detect};
+use crate::explain::walk;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+struct Side {
+    message_type: &'static str,
+    bytes: Vec<u8>,
+    data: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Changed,
+    Removed,
+    Added,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Difference {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub left: Value,
+    pub right: Value,
+}
+
+/// Read one side of the diff: JSON if it looks like an object, hex otherwise.
+fn read_side(input: &str, type_hint: Option<&str>, label: &str) -> Result<Side> {
+    let trimmed = input.trim();
+    let bytes = if trimmed.starts_with('{') {
+        let json: Value = serde_json::from_str(trimmed)
+            .with_context(|| format!("Failed to parse {} input as JSON", label))?;
+        let Some(type_hint) = type_hint else {
+            bail!("--type is required when the {} input is JSON", label);
+        };
+        crate::serialize_json(type_hint, &json)
+            .with_context(|| format!("Failed to serialize {} input", label))?
+    } else {
+        decode_hex(trimmed).with_context(|| format!("Failed to read {} input", label))?
+    };
+
+    let decoded = match type_hint {
+        Some(type_hint) => decode_as(&bytes, type_hint),
+        None => detect(&bytes),
+    }
+    .with_context(|| format!("Failed to decode {} input", label))?;
+
+    Ok(Side {
+        message_type: decoded.message_type,
+        bytes,
+        data: decoded.data,
+    })
+}
+
+pub fn compare(path: &str, left: &Value, right: &Value, differences: &mut Vec<Difference>) {
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            for (key, lv) in l {
+                let child = format!("{}.{}", path, key);
+                match r.get(key) {
+                    Some(rv) => compare(&child, lv, rv, differences),
+                    None => differences.push(Difference {
+                        path: child,
+                        kind: ChangeKind::Removed,
+                        left: lv.clone(),
+                        right: Value::Null,
+                    }),
+                }
+            }
+            for (key, rv) in r.iter().filter(|(key, _)| !l.contains_key(*key)) {
+                differences.push(Difference {
+                    path: format!("{}.{}", path, key),
+                    kind: ChangeKind::Added,
+                    left: Value::Null,
+                    right: rv.clone(),
+                });
+            }
+        }
+        (Value::Array(l), Value::Array(r)) => {
+            for i in 0..l.len().max(r.len()) {
+                let child = format!("{}[{}]", path, i);
+                match (l.get(i), r.get(i)) {
+                    (Some(lv), Some(rv)) => compare(&child, lv, rv, differences),
+                    (Some(lv), None) => differences.push(Difference {
+                        path: child,
+                        kind: ChangeKind::Removed,
+                        left: lv.clone(),
+                        right: Value::Null,
+                    }),
+                    (None, Some(rv)) => differences.push(Difference {
+                        path: child,
+                        kind: ChangeKind::Added,
+                        left: Value::Null,
+                        right: rv.clone(),
+                    }),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        _ if left != right => differences.push(Difference {
+            path: path.to_string(),
+            kind: ChangeKind::Changed,
+            left: left.clone(),
+            right: right.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Offset of the first byte where the encodings differ, including the point
+/// where the shorter one ends.
+pub fn first_differing_byte(left: &[u8], right: &[u8]) -> Option<usize> {
+    left.iter()
+        .zip(right)
+        .position(|(l, r)| l != r)
+        .or_else(|| (left.len() != right.len()).then_some(left.len().min(right.len())))
+}
+
+/// Name of the innermost field of `side` that covers `offset`.
+fn field_at(side: &Side, offset: usize) -> Option<String> {
+    let fields = walk(&side.bytes, side.message_type).ok()?;
+    fields
+        .iter()
+        .filter(|field| field.kind != "group")
+        .find(|field| field.offset <= offset && offset < field.offset + field.length)
+        .map(|field| field.name.clone())
+}
+
+pub fn diff_messages(left: &str, right: &str, type_hint: Option<&str>) -> Result<()> {
+    let (left, right) = match (
+        read_side(left, type_hint, "left"),
+        read_side(right, type_hint, "right"),
+    ) {
+        (Ok(left), Ok(right)) => (left, right),
+        (Err(e), _) | (_, Err(e)) => return crate::output_error(&format!("{:#}", e)),
+    };
+
+    let mut differences = Vec::new();
+    if left.message_type != right.message_type {
+        differences.push(Difference {
+            path: "$".to_string(),
+            kind: ChangeKind::Changed,
+            left: json!(left.message_type),
+            right: json!(right.message_type),
+        });
+    } else {
+        compare("$", &left.data, &right.data, &mut differences);
+    }
+
+    let first_byte = first_differing_byte(&left.bytes, &right.bytes).map(|offset| {
+        json!({
+            "offset": offset,
+            "left": left.bytes.get(offset).map(|b| format!("{:02x}", b)),
+            "right": right.bytes.get(offset).map(|b| format!("{:02x}", b)),
+            "leftField": field_at(&left, offset),
+            "rightField": field_at(&right, offset),
+        })
+    });
+
+    let identical = differences.is_empty() && first_byte.is_none();
+    let message = match &first_byte {
+        None if identical => "Messages are identical".to_string(),
+        None => format!(
+            "{} structural differences, wire encodings identical",
+            differences.len()
+        ),
+        Some(first) => format!(
+            "{} structural differences, first differing byte at offset {}",
+            differences.len(),
+            first["offset"]
+        ),
+    };
+
+    let data = json!({
+        "identical": identical,
+        "leftType": left.message_type,
+        "rightType": right.message_type,
+        "leftLength": left.bytes.len(),
+        "rightLength": right.bytes.len(),
+        "firstDifferingByte": first_byte,
+        "differences": differences,
+    });
+
+    crate::output_success_with_data("diff", &data, &message)
+}
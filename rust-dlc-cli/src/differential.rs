@@ -0,0 +1,344 @@
+//! Differential testing of an external decoder against rust-dlc.
+//!
+//! Each case is a generated message of a random type. The external command
+//! is run through `sh -c` once per case and receives a single JSON object on
+//! stdin:
+//!
+//! ```json
+//! {"type": "offer", "hex": "<rust-dlc encoding>", "json": <rust-dlc JSON>}
+//! ```
+//!
+//! It must print a JSON object with `hex` (its encoding of `json`) and/or
+//! `json` (its decoding of `hex`), or `{"status": "error", "message": ...}`
+//! if it rejects the message. Whatever it returns is compared with rust-dlc,
+//! and failing cases are shrunk by dropping array elements and lowering
+//! numbers while the disagreement persists.
+
+use crate::codec::decode_hex;
+use crate::diff::{compare, first_differing_byte, Difference};
+use crate::generate::{encode, Generator, GENERATED_TYPES};
+use crate::rng::Rng;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Mismatch {
+    reason: String,
+    first_differing_byte: Option<usize>,
+    differences: Vec<Difference>,
+}
+
+impl Mismatch {
+    fn new(reason: String) -> Self {
+        Mismatch {
+            reason,
+            first_differing_byte: None,
+            differences: Vec::new(),
+        }
+    }
+}
+
+/// Run the decoder on one request, returning its response and, when it
+/// exited successfully without reading all of its input, a warning.
+fn run_external(command: &str, request: &Value) -> Result<(Value, Option<String>)> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start decoder command: {}", command))?;
+
+    // Feed stdin from its own thread so a decoder that writes output while
+    // still reading a large request cannot fill the stdout pipe and block
+    // both sides.
+    let mut stdin = child.stdin.take().context("Decoder stdin is not piped")?;
+    let input = request.to_string();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for decoder command")?;
+    if !output.status.success() {
+        bail!(
+            "decoder exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    // A decoder may answer and exit before reading the whole request; its
+    // output is still compared, and the closed pipe is only worth noting.
+    let warning = match writer
+        .join()
+        .map_err(|_| anyhow!("Decoder stdin writer panicked"))?
+    {
+        Ok(()) => None,
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Some(format!(
+            "decoder exited before reading the whole request: {}",
+            e
+        )),
+        Err(e) => return Err(e).context("Failed to write to decoder stdin"),
+    };
+    let response = serde_json::from_slice(&output.stdout).context("Decoder output is not JSON")?;
+    Ok((response, warning))
+}
+
+/// Compare the external decoder with rust-dlc on one message. Errors mean
+/// rust-dlc itself cannot encode the message, so there is nothing to compare.
+fn check(
+    command: &str,
+    msg_type: &str,
+    json: &Value,
+    warnings: &mut Vec<String>,
+) -> Result<Option<Mismatch>> {
+    let (bytes, canonical) = encode(msg_type, json)?;
+    let request = json!({
+        "type": msg_type,
+        "hex": hex::encode(&bytes),
+        "json": canonical,
+    });
+
+    let response = match run_external(command, &request) {
+        Ok((response, warning)) => {
+            warnings.extend(warning);
+            response
+        }
+        Err(e) => return Ok(Some(Mismatch::new(format!("{:#}", e)))),
+    };
+    if response["status"] == "error" {
+        return Ok(Some(Mismatch::new(format!(
+            "Decoder rejected the message: {}",
+            response["message"]
+        ))));
+    }
+
+    let mut mismatch = Mismatch::new(String::new());
+    let mut reasons = Vec::new();
+    let their_hex = response.get("hex").and_then(Value::as_str);
+    let their_json = response.get("json");
+    if their_hex.is_none() && their_json.is_none() {
+        reasons.push("decoder returned neither hex nor json".to_string());
+    }
+
+    if let Some(their_hex) = their_hex {
+        match decode_hex(their_hex) {
+            Ok(theirs) => {
+                mismatch.first_differing_byte = first_differing_byte(&bytes, &theirs);
+                if let Some(offset) = mismatch.first_differing_byte {
+                    reasons.push(format!("encodings differ from byte {}", offset));
+                }
+            }
+            Err(e) => reasons.push(format!("decoder returned invalid hex: {}", e)),
+        }
+    }
+    if let Some(their_json) = their_json {
+        compare("$", &canonical, their_json, &mut mismatch.differences);
+        if !mismatch.differences.is_empty() {
+            reasons.push(format!("{} JSON differences", mismatch.differences.len()));
+        }
+    }
+
+    if reasons.is_empty() {
+        return Ok(None);
+    }
+    mismatch.reason = reasons.join("; ");
+    Ok(Some(mismatch))
+}
+
+enum Edit {
+    /// Keep only the first half of an array.
+    Halve,
+    Remove(usize),
+    SetNumber(u64),
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Candidate edits that make `value` smaller, coarsest first.
+fn edits(value: &Value, pointer: &str, out: &mut Vec<(String, Edit)>) {
+    match value {
+        Value::Array(items) => {
+            if items.len() > 1 {
+                out.push((pointer.to_string(), Edit::Halve));
+            }
+            for i in (0..items.len()).rev() {
+                out.push((pointer.to_string(), Edit::Remove(i)));
+            }
+            for (i, item) in items.iter().enumerate() {
+                edits(item, &format!("{}/{}", pointer, i), out);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                edits(item, &format!("{}/{}", pointer, escape_pointer(key)), out);
+            }
+        }
+        Value::Number(n) => {
+            if let Some(n) = n.as_u64().filter(|n| *n > 0) {
+                out.push((pointer.to_string(), Edit::SetNumber(0)));
+                if n > 1 {
+                    out.push((pointer.to_string(), Edit::SetNumber(n / 2)));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn apply(value: &Value, pointer: &str, edit: &Edit) -> Option<Value> {
+    let mut candidate = value.clone();
+    let target = candidate.pointer_mut(pointer)?;
+    match edit {
+        Edit::Halve => {
+            let items = target.as_array_mut()?;
+            items.truncate(items.len() / 2);
+        }
+        Edit::Remove(i) => {
+            target.as_array_mut()?.remove(*i);
+        }
+        Edit::SetNumber(n) => *target = json!(n),
+    }
+    Some(candidate)
+}
+
+/// Greedily apply edits while the decoder still disagrees, spending at most
+/// `budget` checks. Returns the smallest failing message found.
+fn shrink(
+    command: &str,
+    msg_type: &str,
+    json: &Value,
+    mismatch: Mismatch,
+    budget: usize,
+) -> (Value, Mismatch, usize) {
+    let mut current = json.clone();
+    let mut current_mismatch = mismatch;
+    let mut runs = 0;
+    let mut steps = 0;
+
+    'outer: while runs < budget {
+        let mut candidates = Vec::new();
+        edits(&current, "", &mut candidates);
+        for (pointer, edit) in &candidates {
+            if runs >= budget {
+                break 'outer;
+            }
+            let Some(candidate) = apply(&current, pointer, edit) else {
+                continue;
+            };
+            runs += 1;
+            // Warnings while shrinking repeat those of the original case.
+            if let Ok(Some(mismatch)) = check(command, msg_type, &candidate, &mut Vec::new()) {
+                current = candidate;
+                current_mismatch = mismatch;
+                steps += 1;
+                continue 'outer;
+            }
+        }
+        break;
+    }
+
+    (current, current_mismatch, steps)
+}
+
+pub fn run_differential(
+    command: &str,
+    seed: u64,
+    iterations: u64,
+    types: &[&str],
+    shrink_budget: usize,
+    max_failures: usize,
+) -> Result<()> {
+    if let Some(unknown) = types.iter().find(|t| !GENERATED_TYPES.contains(*t)) {
+        return crate::output_error(&format!(
+            "Unsupported message type: {} (expected one of: {})",
+            unknown,
+            GENERATED_TYPES.join(", ")
+        ));
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut passed = 0u64;
+    let mut generator_errors = Vec::new();
+    let mut failures = Vec::new();
+    let mut warnings = Vec::new();
+
+    for iteration in 0..iterations {
+        if failures.len() >= max_failures {
+            warnings.push(format!(
+                "Stopped after {} failures, {} of {} cases run",
+                failures.len(),
+                iteration,
+                iterations
+            ));
+            break;
+        }
+
+        let case_seed = rng.next_u64();
+        let msg_type = types[rng.index(types.len())];
+        let mut case_warnings = Vec::new();
+        let case = Generator::new(case_seed)
+            .message(msg_type)
+            .and_then(|json| {
+                check(command, msg_type, &json, &mut case_warnings).map(|result| (json, result))
+            });
+        warnings.extend(
+            case_warnings
+                .into_iter()
+                .map(|warning| format!("Case {} ({}): {}", iteration, msg_type, warning)),
+        );
+
+        match case {
+            Ok((_, None)) => passed += 1,
+            Ok((json, Some(mismatch))) => {
+                let hex = encode(msg_type, &json).map(|(bytes, _)| hex::encode(bytes))?;
+                let (shrunk, shrunk_mismatch, steps) =
+                    shrink(command, msg_type, &json, mismatch, shrink_budget);
+                let shrunk_hex = encode(msg_type, &shrunk).map(|(bytes, _)| hex::encode(bytes))?;
+                failures.push(json!({
+                    "iteration": iteration,
+                    "caseSeed": case_seed,
+                    "messageType": msg_type,
+                    "hex": hex,
+                    "json": json,
+                    "shrunk": {
+                        "steps": steps,
+                        "hex": shrunk_hex,
+                        "json": shrunk,
+                        "mismatch": shrunk_mismatch,
+                    },
+                }));
+            }
+            Err(e) => generator_errors.push(json!({
+                "iteration": iteration,
+                "caseSeed": case_seed,
+                "messageType": msg_type,
+                "message": format!("{:#}", e),
+            })),
+        }
+    }
+
+    let message = format!(
+        "{} cases agreed, {} disagreed, {} could not be generated",
+        passed,
+        failures.len(),
+        generator_errors.len()
+    );
+    let data = json!({
+        "seed": seed,
+        "command": command,
+        "passed": passed,
+        "failed": failures.len(),
+        "failures": failures,
+        "generatorErrors": generator_errors,
+        "warnings": warnings,
+    });
+    crate::output_success_with_data("differential", &data, &message)
+}
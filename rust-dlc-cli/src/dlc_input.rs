@@ -0,0 +1,294 @@
+//! Funding inputs that spend the funding output of an earlier contract.
+//!
+//! A DLC input is a `FundingInput` whose `dlcInput` field names the prior
+//! contract and both of its funding pubkeys, so the counterparty can rebuild
+//! the 2-of-2 redeem script needed to co-sign the spend.
+
+use crate::codec::{decode_hex, read_accept, read_offer};
+use crate::flow::{CheckStatus, FlowCheck};
+use crate::output_error;
+use crate::transactions::{
+    build_dlc_transactions, compute_contract_id, fund_output_index, funding_input_prev_tx,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::Transaction;
+use dlc::{make_funding_redeemscript, DlcTransactions};
+use dlc_messages::{AcceptDlc, FundingInput, OfferDlc};
+use lightning::io::Cursor;
+use lightning::util::ser::{Readable, Writeable};
+use secp256k1_zkp::PublicKey;
+use serde_json::json;
+
+/// Witness size of a 2-of-2 funding output spend (empty element, two
+/// signatures and the redeem script).
+pub const DLC_INPUT_MAX_WITNESS_LEN: u16 = 220;
+
+/// Read a funding input given as JSON or as hex of its wire encoding.
+fn read_funding_input(input: &str) -> Result<FundingInput> {
+    let trimmed = input.trim();
+    if trimmed.starts_with('{') {
+        return serde_json::from_str(trimmed).context("Failed to parse JSON as FundingInput");
+    }
+    let bytes = decode_hex(trimmed)?;
+    FundingInput::read(&mut Cursor::new(&bytes))
+        .map_err(|e| anyhow!("Failed to parse FundingInput from hex: {:?}", e))
+}
+
+fn parse_pubkey(value: &str, name: &str) -> Result<PublicKey> {
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("Failed to parse {} as a public key", name))
+}
+
+/// Build a funding input spending the 2-of-2 output of `fund_tx_hex` that
+/// belongs to the given funding pubkeys.
+pub fn build_dlc_input(
+    fund_tx_hex: &str,
+    local_pubkey: &str,
+    remote_pubkey: &str,
+    contract_id: &str,
+    serial_id: u64,
+    sequence: u32,
+) -> Result<()> {
+    let fund_tx: Transaction = match decode_hex(fund_tx_hex).and_then(|bytes| {
+        bitcoin::consensus::deserialize(&bytes).context("Failed to parse funding transaction")
+    }) {
+        Ok(tx) => tx,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    let (local, remote) = match (
+        parse_pubkey(local_pubkey, "local funding pubkey"),
+        parse_pubkey(remote_pubkey, "remote funding pubkey"),
+    ) {
+        (Ok(local), Ok(remote)) => (local, remote),
+        (Err(e), _) | (_, Err(e)) => return output_error(&format!("{:#}", e)),
+    };
+    let contract_id: [u8; 32] = match decode_hex(contract_id).map(<[u8; 32]>::try_from) {
+        Ok(Ok(id)) => id,
+        _ => return output_error("Contract id must be 32 bytes of hex"),
+    };
+
+    let redeem_script = make_funding_redeemscript(&local, &remote);
+    let funding_spk = redeem_script.to_p2wsh();
+    let Some(vout) = fund_tx
+        .output
+        .iter()
+        .position(|output| output.script_pubkey == funding_spk)
+    else {
+        return output_error(&format!(
+            "Funding transaction {} has no output paying to the 2-of-2 of {} and {}",
+            fund_tx.compute_txid(),
+            local,
+            remote
+        ));
+    };
+
+    let input_json = json!({
+        "inputSerialId": serial_id,
+        "prevTx": bitcoin::consensus::encode::serialize_hex(&fund_tx),
+        "prevTxVout": vout,
+        "sequence": sequence,
+        "maxWitnessLen": DLC_INPUT_MAX_WITNESS_LEN,
+        "redeemScript": "",
+        "dlcInput": {
+            "localFundPubkey": local.to_string(),
+            "remoteFundPubkey": remote.to_string(),
+            "contractId": hex::encode(contract_id),
+        },
+    });
+    let input: FundingInput = serde_json::from_value(input_json)
+        .context("Failed to build FundingInput from the DLC input fields")?;
+
+    let data = json!({
+        "fundingInput": serde_json::to_value(&input).context("Failed to convert FundingInput to JSON")?,
+        "hex": hex::encode(input.encode()),
+        "outpoint": format!("{}:{}", fund_tx.compute_txid(), vout),
+        "value": fund_tx.output[vout].value.to_sat(),
+        "fundingScript": hex::encode(redeem_script.as_bytes()),
+    });
+    crate::output_success_with_data(
+        "funding-input",
+        &data,
+        &format!(
+            "Built DLC input spending {}:{}",
+            fund_tx.compute_txid(),
+            vout
+        ),
+    )
+}
+
+fn check_prev_tx(input: &FundingInput, transactions: &DlcTransactions) -> Result<String> {
+    let prev_tx = funding_input_prev_tx(input)?;
+    let fund_txid = transactions.fund.compute_txid();
+    if prev_tx.compute_txid() != fund_txid {
+        bail!(
+            "Input spends transaction {} but the prior contract was funded by {}",
+            prev_tx.compute_txid(),
+            fund_txid
+        );
+    }
+    Ok(format!(
+        "Previous transaction is funding transaction {}",
+        fund_txid
+    ))
+}
+
+fn check_outpoint(input: &FundingInput, transactions: &DlcTransactions) -> Result<String> {
+    let output_index = fund_output_index(transactions)?;
+    if input.prev_tx_vout != output_index as u32 {
+        bail!(
+            "Input spends output {} but the funding output is {}",
+            input.prev_tx_vout,
+            output_index
+        );
+    }
+    Ok(format!("Input spends funding output {}", output_index))
+}
+
+fn check_fund_pubkeys(
+    input: &FundingInput,
+    offer: &OfferDlc,
+    accept: &AcceptDlc,
+) -> Result<String> {
+    let Some(dlc_input) = &input.dlc_input else {
+        bail!("Funding input has no dlcInput");
+    };
+    let mut expected = [offer.funding_pubkey, accept.funding_pubkey];
+    let mut actual = [dlc_input.local_fund_pubkey, dlc_input.remote_fund_pubkey];
+    expected.sort_by_key(|pk| pk.serialize());
+    actual.sort_by_key(|pk| pk.serialize());
+    if expected != actual {
+        bail!(
+            "dlcInput pubkeys {} and {} do not match the prior contract's funding pubkeys {} and {}",
+            dlc_input.local_fund_pubkey,
+            dlc_input.remote_fund_pubkey,
+            offer.funding_pubkey,
+            accept.funding_pubkey
+        );
+    }
+    Ok(format!(
+        "Local {} and remote {} match the prior contract's funding pubkeys",
+        dlc_input.local_fund_pubkey, dlc_input.remote_fund_pubkey
+    ))
+}
+
+fn check_funding_script(input: &FundingInput, transactions: &DlcTransactions) -> Result<String> {
+    let Some(dlc_input) = &input.dlc_input else {
+        bail!("Funding input has no dlcInput");
+    };
+    let prev_tx = funding_input_prev_tx(input)?;
+    let prev_output = prev_tx
+        .output
+        .get(input.prev_tx_vout as usize)
+        .ok_or_else(|| anyhow!("Previous transaction has no output {}", input.prev_tx_vout))?;
+    let redeem_script =
+        make_funding_redeemscript(&dlc_input.local_fund_pubkey, &dlc_input.remote_fund_pubkey);
+    if redeem_script != transactions.funding_script_pubkey {
+        bail!("dlcInput pubkeys do not rebuild the prior contract's funding script");
+    }
+    if prev_output.script_pubkey != redeem_script.to_p2wsh() {
+        bail!("Spent output does not pay to the P2WSH of the funding script");
+    }
+    Ok("Spent output pays to the 2-of-2 funding script".to_string())
+}
+
+fn check_contract_id(
+    input: &FundingInput,
+    offer: &OfferDlc,
+    transactions: &DlcTransactions,
+) -> Result<String> {
+    let Some(dlc_input) = &input.dlc_input else {
+        bail!("Funding input has no dlcInput");
+    };
+    let expected = compute_contract_id(
+        transactions.fund.compute_txid(),
+        fund_output_index(transactions)?,
+        &offer.temporary_contract_id,
+    );
+    if dlc_input.contract_id != expected {
+        bail!(
+            "dlcInput contract id {} does not match the prior contract id {}",
+            hex::encode(dlc_input.contract_id),
+            hex::encode(expected)
+        );
+    }
+    Ok(format!("Contract id {} matches", hex::encode(expected)))
+}
+
+fn check_max_witness_len(input: &FundingInput) -> Result<String> {
+    if input.max_witness_len < DLC_INPUT_MAX_WITNESS_LEN {
+        bail!(
+            "maxWitnessLen {} is below the {} bytes a 2-of-2 spend needs",
+            input.max_witness_len,
+            DLC_INPUT_MAX_WITNESS_LEN
+        );
+    }
+    Ok(format!(
+        "maxWitnessLen {} covers a 2-of-2 spend",
+        input.max_witness_len
+    ))
+}
+
+/// Check a DLC input against the offer/accept pair of the contract it spends.
+pub fn validate_dlc_input(input: &str, prev_offer_hex: &str, prev_accept_hex: &str) -> Result<()> {
+    let input = match read_funding_input(input) {
+        Ok(input) => input,
+        Err(e) => return output_error(&format!("Failed to decode funding input: {:#}", e)),
+    };
+    let offer = match read_offer(prev_offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode prior offer: {}", e)),
+    };
+    let accept = match read_accept(prev_accept_hex) {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode prior accept: {}", e)),
+    };
+    let transactions = match build_dlc_transactions(&offer, &accept) {
+        Ok(transactions) => transactions,
+        Err(e) => return output_error(&e.to_string()),
+    };
+
+    let mut checks = vec![FlowCheck::new(
+        "prevTx",
+        check_prev_tx(&input, &transactions),
+    )];
+    checks.push(FlowCheck::new(
+        "outpoint",
+        check_outpoint(&input, &transactions),
+    ));
+    checks.push(FlowCheck::new(
+        "fundPubkeys",
+        check_fund_pubkeys(&input, &offer, &accept),
+    ));
+    checks.push(FlowCheck::new(
+        "fundingScript",
+        check_funding_script(&input, &transactions),
+    ));
+    checks.push(FlowCheck::new(
+        "contractId",
+        check_contract_id(&input, &offer, &transactions),
+    ));
+    checks.push(FlowCheck::new(
+        "maxWitnessLen",
+        check_max_witness_len(&input),
+    ));
+
+    let valid = checks.iter().all(|check| check.status != CheckStatus::Fail);
+    let output = json!({
+        "status": if valid { "success" } else { "error" },
+        "messageType": "dlc-input-validation",
+        "data": {
+            "valid": valid,
+            "checks": checks,
+        },
+        "message": if valid {
+            "DLC input spends the prior contract's funding output"
+        } else {
+            "One or more DLC input checks failed"
+        }
+    });
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
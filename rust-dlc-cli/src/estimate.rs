@@ -0,0 +1,158 @@
+//! CET count and accept/sign message sizes of a contract, computed from the
+//! wire layout without building or signing anything.
+
+use crate::cets::{contract_cets, sub_contract_ranges};
+use crate::codec::read_offer;
+use crate::{output_error, output_success_with_data};
+use anyhow::{bail, Context, Result};
+use dlc_messages::contract_msgs::ContractInfo;
+use serde_json::json;
+
+/// Largest message a single Lightning transport frame carries.
+pub const MAX_MESSAGE_SIZE: u64 = 65535;
+/// Size of an ECDSA adaptor signature with its DLEQ proof.
+const ADAPTOR_SIGNATURE_LEN: u64 = 162;
+/// Size of a previous transaction assumed for an input of unknown origin: one
+/// P2WPKH input and two P2WPKH outputs.
+const PREV_TX_LEN: u64 = 222;
+/// Script length of a P2WPKH payout or change script.
+const P2WPKH_SCRIPT_LEN: u64 = 22;
+/// Largest DER signature with its sighash byte, as pushed in a witness.
+const WITNESS_SIGNATURE_LEN: u64 = 73;
+const PUBKEY_LEN: u64 = 33;
+
+fn bigsize_len(value: u64) -> u64 {
+    match value {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x10000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+fn var_bytes_len(len: u64) -> u64 {
+    bigsize_len(len) + len
+}
+
+fn cet_adaptor_signatures_len(count: u64) -> u64 {
+    bigsize_len(count) + count * ADAPTOR_SIGNATURE_LEN
+}
+
+/// Size of a P2WPKH funding input without a DLC input.
+fn funding_input_len() -> u64 {
+    8 + var_bytes_len(PREV_TX_LEN) + 4 + 4 + 2 + 2 + 1
+}
+
+/// Size of an accept with P2WPKH scripts and inputs and no negotiation
+/// fields or TLVs.
+fn accept_len(adaptor_signatures: u64, funding_inputs: u64) -> u64 {
+    2 + 4
+        + 32
+        + 8
+        + PUBKEY_LEN
+        + 2
+        + P2WPKH_SCRIPT_LEN
+        + 8
+        + bigsize_len(funding_inputs)
+        + funding_inputs * funding_input_len()
+        + 2
+        + P2WPKH_SCRIPT_LEN
+        + 8
+        + cet_adaptor_signatures_len(adaptor_signatures)
+        + 64
+        + 1
+}
+
+/// Size of a sign with one P2WPKH witness per offer funding input and no
+/// TLVs.
+fn sign_len(adaptor_signatures: u64, funding_inputs: u64) -> u64 {
+    let witness_len =
+        bigsize_len(2) + var_bytes_len(WITNESS_SIGNATURE_LEN) + var_bytes_len(PUBKEY_LEN);
+    2 + 4
+        + 32
+        + cet_adaptor_signatures_len(adaptor_signatures)
+        + 64
+        + bigsize_len(funding_inputs)
+        + funding_inputs * witness_len
+}
+
+/// The contract info of an offer or given as JSON, with the offer's funding
+/// input count when read from an offer.
+pub fn read_contract_source(
+    offer_hex: Option<&str>,
+    contract_info_json: Option<&str>,
+) -> Result<(ContractInfo, Option<u64>)> {
+    match (offer_hex, contract_info_json) {
+        (Some(offer_hex), None) => {
+            let offer = read_offer(offer_hex).context("Failed to decode offer")?;
+            let inputs = offer.funding_inputs.len() as u64;
+            Ok((offer.contract_info, Some(inputs)))
+        }
+        (None, Some(json)) => Ok((
+            serde_json::from_str(json).context("Failed to parse JSON as ContractInfo")?,
+            None,
+        )),
+        _ => bail!("Pass exactly one of --offer-hex or --contract-info"),
+    }
+}
+
+/// Estimate the CETs, adaptor signatures and accept/sign sizes of the
+/// contract in an offer or a JSON contract info.
+pub fn estimate(
+    offer_hex: Option<&str>,
+    contract_info_json: Option<&str>,
+    funding_inputs: Option<u64>,
+    max_message_size: u64,
+) -> Result<()> {
+    let (contract_info, offer_inputs) = match read_contract_source(offer_hex, contract_info_json) {
+        Ok(source) => source,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    let cets = match contract_cets(&contract_info) {
+        Ok(cets) => cets,
+        Err(e) => return output_error(&format!("Failed to enumerate CETs: {}", e)),
+    };
+
+    let cet_count: u64 = cets.iter().map(|cets| cets.len() as u64).sum();
+    let adaptor_signatures: u64 = cets.iter().flatten().map(|cet| cet.adaptor_points).sum();
+    let accept_inputs = funding_inputs.unwrap_or(1);
+    let sign_inputs = offer_inputs.or(funding_inputs).unwrap_or(1);
+    let accept_size = accept_len(adaptor_signatures, accept_inputs);
+    let sign_size = sign_len(adaptor_signatures, sign_inputs);
+
+    let mut warnings = Vec::new();
+    for (name, size) in [("Accept", accept_size), ("Sign", sign_size)] {
+        if size > max_message_size {
+            warnings.push(format!(
+                "{} message of {} bytes exceeds the {} byte limit",
+                name, size, max_message_size
+            ));
+        }
+    }
+    // Each additional adaptor signature costs the same in both messages, so
+    // the headroom of the larger one bounds how many more the contract fits.
+    let largest = accept_size.max(sign_size);
+    let spare_signatures = max_message_size.saturating_sub(largest) / ADAPTOR_SIGNATURE_LEN;
+
+    let data = json!({
+        "subContracts": sub_contract_ranges(&contract_info, &cets),
+        "cetCount": cet_count,
+        "adaptorSignatureCount": adaptor_signatures,
+        "acceptSize": accept_size,
+        "signSize": sign_size,
+        "acceptFundingInputs": accept_inputs,
+        "signFundingInputs": sign_inputs,
+        "maxMessageSize": max_message_size,
+        "spareAdaptorSignatures": spare_signatures,
+        "warnings": warnings,
+    });
+
+    output_success_with_data(
+        "estimate",
+        &data,
+        &format!(
+            "{} CETs with {} adaptor signatures",
+            cet_count, adaptor_signatures
+        ),
+    )
+}
@@ -0,0 +1,704 @@
+//! Field-by-field annotation of the wire encoding of DLC messages.
+//!
+//! The walker follows the rust-dlc encoding rules (u16-prefixed scripts,
+//! BigSize-prefixed vectors and strings, TLV-wrapped oracle messages) and
+//! records the offset, length and decoded value of every field, so that
+//! encodings produced by node-dlc and rust-dlc can be compared byte by byte.
+
+use crate::decode::{detect, ORACLE_ANNOUNCEMENT_TYPE, ORACLE_ATTESTATION_TYPE};
+use crate::input::Encoding;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+const ORACLE_EVENT_TYPE: u64 = 55330;
+const ENUM_EVENT_DESCRIPTOR_TYPE: u64 = 55302;
+const DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE: u64 = 55306;
+
+/// Raw bytes longer than this are truncated in human output.
+const HUMAN_RAW_LIMIT: usize = 32;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Field {
+    pub offset: usize,
+    pub length: usize,
+    pub name: String,
+    pub kind: &'static str,
+    pub depth: usize,
+    pub raw: String,
+    pub value: Value,
+}
+
+pub struct Walker<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    path: Vec<String>,
+    fields: Vec<Field>,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Walker {
+            bytes,
+            pos: 0,
+            path: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    pub fn into_fields(self) -> Vec<Field> {
+        self.fields
+    }
+
+    fn full_name(&self, name: &str) -> String {
+        if self.path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.path.join("."), name)
+        }
+    }
+
+    fn take(&mut self, n: usize, name: &str) -> Result<&'a [u8]> {
+        if self.remaining() < n {
+            bail!(
+                "Unexpected end of input reading {} at offset {}: need {} bytes, {} left",
+                self.full_name(name),
+                self.pos,
+                n,
+                self.remaining()
+            );
+        }
+        let bytes = self.bytes;
+        let slice = &bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn record(&mut self, offset: usize, name: &str, kind: &'static str, value: Value) {
+        self.fields.push(Field {
+            offset,
+            length: self.pos - offset,
+            name: self.full_name(name),
+            kind,
+            depth: self.path.len(),
+            raw: hex::encode(&self.bytes[offset..self.pos]),
+            value,
+        });
+    }
+
+    pub fn u8(&mut self, name: &str) -> Result<u8> {
+        let offset = self.pos;
+        let value = self.take(1, name)?[0];
+        self.record(offset, name, "u8", json!(value));
+        Ok(value)
+    }
+
+    pub fn bool(&mut self, name: &str) -> Result<bool> {
+        let offset = self.pos;
+        let value = match self.take(1, name)?[0] {
+            0 => false,
+            1 => true,
+            other => bail!("Invalid boolean {} for {}", other, self.full_name(name)),
+        };
+        self.record(offset, name, "bool", json!(value));
+        Ok(value)
+    }
+
+    pub fn u16(&mut self, name: &str) -> Result<u16> {
+        let offset = self.pos;
+        let bytes = self.take(2, name)?;
+        let value = u16::from_be_bytes([bytes[0], bytes[1]]);
+        self.record(offset, name, "u16", json!(value));
+        Ok(value)
+    }
+
+    pub fn u32(&mut self, name: &str) -> Result<u32> {
+        let offset = self.pos;
+        let bytes = self.take(4, name)?;
+        let value = u32::from_be_bytes(bytes.try_into().unwrap());
+        self.record(offset, name, "u32", json!(value));
+        Ok(value)
+    }
+
+    pub fn i32(&mut self, name: &str) -> Result<i32> {
+        let offset = self.pos;
+        let bytes = self.take(4, name)?;
+        let value = i32::from_be_bytes(bytes.try_into().unwrap());
+        self.record(offset, name, "i32", json!(value));
+        Ok(value)
+    }
+
+    pub fn u64(&mut self, name: &str) -> Result<u64> {
+        let offset = self.pos;
+        let bytes = self.take(8, name)?;
+        let value = u64::from_be_bytes(bytes.try_into().unwrap());
+        self.record(offset, name, "u64", json!(value));
+        Ok(value)
+    }
+
+    pub fn f64(&mut self, name: &str) -> Result<f64> {
+        let offset = self.pos;
+        let bytes = self.take(8, name)?;
+        let value = f64::from_be_bytes(bytes.try_into().unwrap());
+        self.record(offset, name, "f64", json!(value));
+        Ok(value)
+    }
+
+    /// Lightning BigSize: 1, 3, 5 or 9 bytes, big-endian, minimally encoded.
+    pub fn bigsize(&mut self, name: &str) -> Result<u64> {
+        let offset = self.pos;
+        let prefix = self.take(1, name)?[0];
+        let (value, minimum) = match prefix {
+            0xfd => {
+                let b = self.take(2, name)?;
+                (u16::from_be_bytes([b[0], b[1]]) as u64, 0xfd)
+            }
+            0xfe => {
+                let b = self.take(4, name)?;
+                (u32::from_be_bytes(b.try_into().unwrap()) as u64, 0x1_0000)
+            }
+            0xff => {
+                let b = self.take(8, name)?;
+                (u64::from_be_bytes(b.try_into().unwrap()), 0x1_0000_0000)
+            }
+            small => (small as u64, 0),
+        };
+        if value < minimum {
+            bail!(
+                "Non-canonical BigSize {} for {} at offset {}",
+                value,
+                self.full_name(name),
+                offset
+            );
+        }
+        self.record(offset, name, "bigsize", json!(value));
+        Ok(value)
+    }
+
+    pub fn bytes(&mut self, n: usize, name: &str, kind: &'static str) -> Result<&'a [u8]> {
+        let offset = self.pos;
+        let bytes = self.take(n, name)?;
+        self.record(offset, name, kind, json!(hex::encode(bytes)));
+        Ok(bytes)
+    }
+
+    /// BigSize-prefixed UTF-8 string.
+    pub fn string(&mut self, name: &str) -> Result<String> {
+        let len = self.bigsize(&format!("{}Length", name))? as usize;
+        let offset = self.pos;
+        let bytes = self.take(len, name)?;
+        let value = String::from_utf8(bytes.to_vec())
+            .with_context(|| format!("{} is not valid UTF-8", self.full_name(name)))?;
+        self.record(offset, name, "string", json!(value));
+        Ok(value)
+    }
+
+    /// u16-prefixed script.
+    pub fn script(&mut self, name: &str) -> Result<&'a [u8]> {
+        let len = self.u16(&format!("{}Length", name))? as usize;
+        self.bytes(len, name, "script")
+    }
+
+    /// BigSize-prefixed byte vector.
+    pub fn var_bytes(&mut self, name: &str, kind: &'static str) -> Result<&'a [u8]> {
+        let len = self.bigsize(&format!("{}Length", name))? as usize;
+        self.bytes(len, name, kind)
+    }
+
+    /// Record a named group spanning whatever `f` reads.
+    pub fn group<T>(&mut self, name: &str, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let offset = self.pos;
+        let index = self.fields.len();
+        let depth = self.path.len();
+        let full_name = self.full_name(name);
+        self.path.push(name.to_string());
+        let result = f(self);
+        self.path.pop();
+        let result = result?;
+        self.fields.insert(
+            index,
+            Field {
+                offset,
+                length: self.pos - offset,
+                name: full_name,
+                kind: "group",
+                depth,
+                raw: String::new(),
+                value: Value::Null,
+            },
+        );
+        Ok(result)
+    }
+
+    /// BigSize-prefixed vector, each element recorded as `name[i]`.
+    pub fn vec(
+        &mut self,
+        name: &str,
+        mut f: impl FnMut(&mut Self, usize) -> Result<()>,
+    ) -> Result<usize> {
+        let count = self.bigsize(&format!("{}Count", name))? as usize;
+        for i in 0..count {
+            self.group(&format!("{}[{}]", name, i), |w| f(w, i))?;
+        }
+        Ok(count)
+    }
+
+    /// u16-prefixed vector, each element recorded as `name[i]`.
+    pub fn vec_u16(
+        &mut self,
+        name: &str,
+        mut f: impl FnMut(&mut Self, usize) -> Result<()>,
+    ) -> Result<usize> {
+        let count = self.u16(&format!("{}Count", name))? as usize;
+        for i in 0..count {
+            self.group(&format!("{}[{}]", name, i), |w| f(w, i))?;
+        }
+        Ok(count)
+    }
+
+    /// One-byte presence flag followed by the value when present.
+    pub fn option(&mut self, name: &str, f: impl FnOnce(&mut Self) -> Result<()>) -> Result<bool> {
+        let present = match self.u8(&format!("{}Present", name))? {
+            0 => false,
+            1 => true,
+            other => bail!("Invalid option flag {} for {}", other, self.full_name(name)),
+        };
+        if present {
+            self.group(name, f)?;
+        }
+        Ok(present)
+    }
+
+    /// TLV record: BigSize type, BigSize length and a body that must consume
+    /// exactly `length` bytes.
+    pub fn tlv(
+        &mut self,
+        name: &str,
+        expected_type: Option<u64>,
+        f: impl FnOnce(&mut Self, u64, usize) -> Result<()>,
+    ) -> Result<u64> {
+        self.group(name, |w| {
+            let tlv_type = w.bigsize("type")?;
+            if let Some(expected) = expected_type {
+                if tlv_type != expected {
+                    bail!(
+                        "Expected TLV type {} for {}, found {}",
+                        expected,
+                        w.full_name("type"),
+                        tlv_type
+                    );
+                }
+            }
+            let length = w.bigsize("length")? as usize;
+            if length > w.remaining() {
+                bail!(
+                    "TLV {} declares {} bytes but only {} remain",
+                    w.path.join("."),
+                    length,
+                    w.remaining()
+                );
+            }
+            let start = w.pos;
+            f(w, tlv_type, length)?;
+            let consumed = w.pos - start;
+            if consumed != length {
+                bail!(
+                    "TLV {} declares {} bytes but its fields use {}",
+                    w.path.join("."),
+                    length,
+                    consumed
+                );
+            }
+            Ok(tlv_type)
+        })
+    }
+
+    /// Unknown TLV records until the end of input.
+    pub fn trailing_tlvs(&mut self, name: &str) -> Result<()> {
+        let mut i = 0;
+        while self.remaining() > 0 {
+            self.tlv(&format!("{}[{}]", name, i), None, |w, _, length| {
+                w.bytes(length, "value", "bytes").map(|_| ())
+            })?;
+            i += 1;
+        }
+        Ok(())
+    }
+}
+
+fn payout_point(w: &mut Walker, name: &str) -> Result<()> {
+    w.group(name, |w| {
+        w.u64("eventOutcome")?;
+        w.u64("outcomePayout")?;
+        w.u16("extraPrecision")?;
+        Ok(())
+    })
+}
+
+pub fn rounding_intervals(w: &mut Walker) -> Result<()> {
+    w.vec("intervals", |w, _| {
+        w.u64("beginInterval")?;
+        w.u64("roundingMod")?;
+        Ok(())
+    })
+    .map(|_| ())
+}
+
+pub fn payout_function(w: &mut Walker) -> Result<()> {
+    w.vec("payoutFunctionPieces", |w, _| {
+        payout_point(w, "endPoint")?;
+        w.group("payoutCurvePiece", |w| {
+            match w.bigsize("variant")? {
+                0 => {
+                    w.vec("payoutPoints", |w, i| {
+                        payout_point(w, &format!("point{}", i))
+                    })?;
+                }
+                1 => {
+                    w.bool("usePositivePiece")?;
+                    w.f64("translateOutcome")?;
+                    w.f64("translatePayout")?;
+                    w.f64("a")?;
+                    w.f64("b")?;
+                    w.f64("c")?;
+                    w.f64("d")?;
+                }
+                other => bail!("Unknown payout curve piece variant {}", other),
+            }
+            Ok(())
+        })
+    })?;
+    payout_point(w, "lastEndpoint")
+}
+
+pub fn contract_descriptor(w: &mut Walker) -> Result<()> {
+    match w.bigsize("variant")? {
+        0 => {
+            w.vec("payouts", |w, _| {
+                w.string("outcome")?;
+                w.u64("offerPayout")?;
+                Ok(())
+            })?;
+        }
+        1 => {
+            w.u16("numDigits")?;
+            w.group("payoutFunction", payout_function)?;
+            w.group("roundingIntervals", rounding_intervals)?;
+        }
+        other => bail!("Unknown contract descriptor variant {}", other),
+    }
+    Ok(())
+}
+
+fn event_descriptor(w: &mut Walker) -> Result<()> {
+    w.tlv("eventDescriptor", None, |w, tlv_type, _| {
+        match tlv_type {
+            ENUM_EVENT_DESCRIPTOR_TYPE => {
+                w.vec_u16("outcomes", |w, _| w.string("outcome").map(|_| ()))?;
+            }
+            DIGIT_DECOMPOSITION_EVENT_DESCRIPTOR_TYPE => {
+                w.u16("base")?;
+                w.bool("isSigned")?;
+                w.string("unit")?;
+                w.i32("precision")?;
+                w.u16("nbDigits")?;
+            }
+            other => bail!("Unknown event descriptor type {}", other),
+        }
+        Ok(())
+    })
+    .map(|_| ())
+}
+
+pub fn oracle_event(w: &mut Walker) -> Result<()> {
+    w.vec_u16("oracleNonces", |w, _| {
+        w.bytes(32, "nonce", "xonly-pubkey").map(|_| ())
+    })?;
+    w.u32("eventMaturityEpoch")?;
+    event_descriptor(w)?;
+    w.string("eventId")?;
+    Ok(())
+}
+
+pub fn oracle_announcement(w: &mut Walker) -> Result<()> {
+    w.tlv(
+        "oracleAnnouncement",
+        Some(ORACLE_ANNOUNCEMENT_TYPE as u64),
+        |w, _, _| {
+            w.bytes(64, "announcementSignature", "schnorr-signature")?;
+            w.bytes(32, "oraclePublicKey", "xonly-pubkey")?;
+            w.tlv("oracleEvent", Some(ORACLE_EVENT_TYPE), |w, _, _| {
+                oracle_event(w)
+            })?;
+            Ok(())
+        },
+    )
+    .map(|_| ())
+}
+
+pub fn oracle_attestation(w: &mut Walker) -> Result<()> {
+    w.tlv(
+        "oracleAttestation",
+        Some(ORACLE_ATTESTATION_TYPE as u64),
+        |w, _, _| {
+            w.string("eventId")?;
+            w.bytes(32, "oraclePublicKey", "xonly-pubkey")?;
+            w.vec_u16("signatures", |w, _| {
+                w.bytes(64, "signature", "schnorr-signature").map(|_| ())
+            })?;
+            w.vec_u16("outcomes", |w, _| w.string("outcome").map(|_| ()))?;
+            Ok(())
+        },
+    )
+    .map(|_| ())
+}
+
+pub fn oracle_info(w: &mut Walker) -> Result<()> {
+    match w.bigsize("variant")? {
+        0 => oracle_announcement(w)?,
+        1 => {
+            w.u16("threshold")?;
+            w.vec("oracleAnnouncements", |w, _| oracle_announcement(w))?;
+            w.option("oracleParams", |w| {
+                w.u16("maxErrorExp")?;
+                w.u16("minFailExp")?;
+                w.bool("maximizeCoverage")?;
+                Ok(())
+            })?;
+        }
+        other => bail!("Unknown oracle info variant {}", other),
+    }
+    Ok(())
+}
+
+fn contract_info_inner(w: &mut Walker) -> Result<()> {
+    w.group("contractDescriptor", contract_descriptor)?;
+    w.group("oracleInfo", oracle_info)
+}
+
+pub fn contract_info(w: &mut Walker) -> Result<()> {
+    match w.bigsize("variant")? {
+        0 => {
+            w.u64("totalCollateral")?;
+            contract_info_inner(w)?;
+        }
+        1 => {
+            w.u64("totalCollateral")?;
+            w.vec("contractInfos", |w, _| contract_info_inner(w))?;
+        }
+        other => bail!("Unknown contract info variant {}", other),
+    }
+    Ok(())
+}
+
+pub fn funding_input(w: &mut Walker) -> Result<()> {
+    w.u64("inputSerialId")?;
+    w.var_bytes("prevTx", "transaction")?;
+    w.u32("prevTxVout")?;
+    w.u32("sequence")?;
+    w.u16("maxWitnessLen")?;
+    w.script("redeemScript")?;
+    w.option("dlcInput", |w| {
+        w.bytes(33, "localFundPubkey", "pubkey")?;
+        w.bytes(33, "remoteFundPubkey", "pubkey")?;
+        w.bytes(32, "contractId", "bytes32")?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn funding_inputs(w: &mut Walker) -> Result<()> {
+    w.vec("fundingInputs", |w, _| funding_input(w)).map(|_| ())
+}
+
+pub fn cet_adaptor_signatures(w: &mut Walker) -> Result<()> {
+    w.vec("ecdsaAdaptorSignatures", |w, _| {
+        w.bytes(162, "signature", "ecdsa-adaptor-signature")
+            .map(|_| ())
+    })
+    .map(|_| ())
+}
+
+pub fn funding_signatures(w: &mut Walker) -> Result<()> {
+    w.vec("fundingSignatures", |w, _| {
+        w.vec("witnessElements", |w, _| {
+            w.var_bytes("witness", "bytes").map(|_| ())
+        })
+        .map(|_| ())
+    })
+    .map(|_| ())
+}
+
+pub fn negotiation_fields(w: &mut Walker) -> Result<()> {
+    match w.bigsize("variant")? {
+        0 => w.group("roundingIntervals", rounding_intervals)?,
+        1 => {
+            w.vec("negotiationFields", |w, _| negotiation_fields(w))?;
+        }
+        other => bail!("Unknown negotiation fields variant {}", other),
+    }
+    Ok(())
+}
+
+pub fn offer(w: &mut Walker) -> Result<()> {
+    w.u16("type")?;
+    w.u32("protocolVersion")?;
+    w.u8("contractFlags")?;
+    w.bytes(32, "chainHash", "bytes32")?;
+    w.bytes(32, "temporaryContractId", "bytes32")?;
+    w.group("contractInfo", contract_info)?;
+    w.bytes(33, "fundingPubkey", "pubkey")?;
+    w.script("payoutSpk")?;
+    w.u64("payoutSerialId")?;
+    w.u64("offerCollateral")?;
+    funding_inputs(w)?;
+    w.script("changeSpk")?;
+    w.u64("changeSerialId")?;
+    w.u64("fundOutputSerialId")?;
+    w.u64("feeRatePerVb")?;
+    w.u32("cetLocktime")?;
+    w.u32("refundLocktime")?;
+    w.trailing_tlvs("tlvs")
+}
+
+pub fn accept(w: &mut Walker) -> Result<()> {
+    w.u16("type")?;
+    w.u32("protocolVersion")?;
+    w.bytes(32, "temporaryContractId", "bytes32")?;
+    w.u64("acceptCollateral")?;
+    w.bytes(33, "fundingPubkey", "pubkey")?;
+    w.script("payoutSpk")?;
+    w.u64("payoutSerialId")?;
+    funding_inputs(w)?;
+    w.script("changeSpk")?;
+    w.u64("changeSerialId")?;
+    w.group("cetAdaptorSignatures", cet_adaptor_signatures)?;
+    w.bytes(64, "refundSignature", "ecdsa-signature")?;
+    w.option("negotiationFields", negotiation_fields)?;
+    w.trailing_tlvs("tlvs")
+}
+
+pub fn sign(w: &mut Walker) -> Result<()> {
+    w.u16("type")?;
+    w.u32("protocolVersion")?;
+    w.bytes(32, "contractId", "bytes32")?;
+    w.group("cetAdaptorSignatures", cet_adaptor_signatures)?;
+    w.bytes(64, "refundSignature", "ecdsa-signature")?;
+    w.group("fundingSignatures", funding_signatures)?;
+    w.trailing_tlvs("tlvs")
+}
+
+/// Walk `bytes` as the given message type.
+pub fn walk(bytes: &[u8], message_type: &str) -> Result<Vec<Field>> {
+    let mut w = Walker::new(bytes);
+    match message_type {
+        "offer" => offer(&mut w)?,
+        "accept" => accept(&mut w)?,
+        "sign" => sign(&mut w)?,
+        "oracle-announcement" => oracle_announcement(&mut w)?,
+        "oracle-attestation" => oracle_attestation(&mut w)?,
+        "oracle-event" => oracle_event(&mut w)?,
+        "oracle-info" => oracle_info(&mut w)?,
+        "contract-info" => contract_info(&mut w)?,
+        "contract-descriptor" => contract_descriptor(&mut w)?,
+        "funding-input" => funding_input(&mut w)?,
+        "cet-adaptor-signatures" => cet_adaptor_signatures(&mut w)?,
+        "funding-signatures" => funding_signatures(&mut w)?,
+        "payout-function" => payout_function(&mut w)?,
+        "rounding-intervals" => rounding_intervals(&mut w)?,
+        "negotiation-fields" => negotiation_fields(&mut w)?,
+        _ => bail!("Unsupported message type for explain: {}", message_type),
+    }
+    if w.remaining() > 0 {
+        let remaining = w.remaining();
+        w.bytes(remaining, "trailingBytes", "bytes")?;
+    }
+    Ok(w.into_fields())
+}
+
+fn render_human(fields: &[Field]) -> String {
+    let mut out = format!(
+        "{:>8} {:>6}  {:<48} {}\n",
+        "OFFSET", "LEN", "FIELD", "VALUE"
+    );
+    for field in fields {
+        let indent = "  ".repeat(field.depth);
+        let label = field.name.rsplit('.').next().unwrap_or(&field.name);
+        let name = format!("{}{}", indent, label);
+        if field.kind == "group" {
+            out.push_str(&format!(
+                "{:>8} {:>6}  {}\n",
+                field.offset, field.length, name
+            ));
+            continue;
+        }
+
+        let raw = if field.raw.len() > HUMAN_RAW_LIMIT * 2 {
+            format!("{}…", &field.raw[..HUMAN_RAW_LIMIT * 2])
+        } else {
+            field.raw.clone()
+        };
+        let value = match &field.value {
+            Value::String(s) if s == &field.raw => String::new(),
+            Value::String(s) => format!(" = {:?}", s),
+            other => format!(" = {}", other),
+        };
+        out.push_str(&format!(
+            "{:>8} {:>6}  {:<48} {}{} ({})\n",
+            field.offset, field.length, name, raw, value, field.kind
+        ));
+    }
+    out
+}
+
+pub fn explain(
+    bytes: &[u8],
+    encoding: Encoding,
+    type_hint: Option<&str>,
+    format: &str,
+) -> Result<()> {
+    let message_type = match type_hint {
+        Some(type_hint) => type_hint.to_string(),
+        None => match detect(bytes) {
+            Ok(decoded) => decoded.message_type.to_string(),
+            Err(e) => return crate::output_error(&e.to_string()),
+        },
+    };
+
+    let fields = match walk(bytes, &message_type) {
+        Ok(fields) => fields,
+        Err(e) => {
+            return crate::output_error(&format!("Failed to explain {}: {}", message_type, e))
+        }
+    };
+
+    match format {
+        "human" => {
+            print!("{}", render_human(&fields));
+            Ok(())
+        }
+        "json" => {
+            let output = json!({
+                "status": "success",
+                "messageType": message_type,
+                "data": {
+                    "inputEncoding": encoding.to_string(),
+                    "length": bytes.len(),
+                    "fields": fields,
+                },
+                "message": format!("Explained {} fields of {}", fields.len(), message_type)
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+            Ok(())
+        }
+        _ => crate::output_error(&format!(
+            "Unsupported format: {} (expected human or json)",
+            format
+        )),
+    }
+}
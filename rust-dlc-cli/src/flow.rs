@@ -0,0 +1,346 @@
+//! Validation of accept and sign messages against the offer they answer.
+
+use crate::cets::adaptor_point_count;
+use crate::codec::{read_accept, read_offer, read_sign};
+use crate::output_error;
+use crate::transactions::{
+    build_dlc_transactions, compute_contract_id, fund_output_index, funding_input_outpoint,
+    funding_input_prev_tx,
+};
+use anyhow::{anyhow, bail, Result};
+use bitcoin::hashes::Hash;
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::{PublicKey, ScriptBuf};
+use dlc::DlcTransactions;
+use dlc_messages::{AcceptDlc, FundingSignature, OfferDlc, SignDlc};
+use secp256k1_zkp::{ecdsa::Signature, Message, SECP256K1};
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl FlowCheck {
+    pub fn new(name: &str, result: Result<String>) -> Self {
+        match result {
+            Ok(message) => FlowCheck {
+                name: name.to_string(),
+                status: CheckStatus::Pass,
+                message,
+            },
+            Err(e) => FlowCheck {
+                name: name.to_string(),
+                status: CheckStatus::Fail,
+                message: e.to_string(),
+            },
+        }
+    }
+
+    pub fn skip(name: &str, message: &str) -> Self {
+        FlowCheck {
+            name: name.to_string(),
+            status: CheckStatus::Skip,
+            message: message.to_string(),
+        }
+    }
+}
+
+pub fn validate_flow(offer_hex: &str, accept_hex: &str, sign_hex: Option<&str>) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let accept = match read_accept(accept_hex) {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode accept: {}", e)),
+    };
+    let sign = match sign_hex.map(read_sign).transpose() {
+        Ok(sign) => sign,
+        Err(e) => return output_error(&format!("Failed to decode sign: {}", e)),
+    };
+
+    let checks = flow_checks(&offer, &accept, sign.as_ref());
+    let valid = checks.iter().all(|check| check.status != CheckStatus::Fail);
+
+    let output = serde_json::json!({
+        "status": if valid { "success" } else { "error" },
+        "messageType": "flow-validation",
+        "data": {
+            "valid": valid,
+            "checks": checks,
+        },
+        "message": if valid {
+            "Accept and sign messages are consistent with the offer"
+        } else {
+            "One or more flow checks failed"
+        }
+    });
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+pub fn flow_checks(offer: &OfferDlc, accept: &AcceptDlc, sign: Option<&SignDlc>) -> Vec<FlowCheck> {
+    let mut checks = vec![FlowCheck::new(
+        "temporaryContractId",
+        check_temporary_contract_id(offer, accept),
+    )];
+
+    let expected_signatures = adaptor_point_count(&offer.contract_info);
+    checks.push(FlowCheck::new(
+        "acceptAdaptorSignatureCount",
+        check_adaptor_count(
+            &expected_signatures,
+            accept.cet_adaptor_signatures.ecdsa_adaptor_signatures.len(),
+        ),
+    ));
+
+    let transactions = build_dlc_transactions(offer, accept);
+    checks.push(FlowCheck::new(
+        "acceptRefundSignature",
+        transactions
+            .as_ref()
+            .map_err(|e| anyhow!("{}", e))
+            .and_then(|txs| {
+                check_refund_signature(txs, &accept.refund_signature, &accept.funding_pubkey)
+            }),
+    ));
+
+    let sign = match sign {
+        Some(sign) => sign,
+        None => {
+            for name in [
+                "contractId",
+                "signAdaptorSignatureCount",
+                "signRefundSignature",
+                "fundingSignatures",
+            ] {
+                checks.push(FlowCheck::skip(name, "No sign message provided"));
+            }
+            return checks;
+        }
+    };
+
+    checks.push(FlowCheck::new(
+        "contractId",
+        transactions
+            .as_ref()
+            .map_err(|e| anyhow!("{}", e))
+            .and_then(|txs| {
+                check_contract_id(txs, &offer.temporary_contract_id, &sign.contract_id)
+            }),
+    ));
+    checks.push(FlowCheck::new(
+        "signAdaptorSignatureCount",
+        check_adaptor_count(
+            &expected_signatures,
+            sign.cet_adaptor_signatures.ecdsa_adaptor_signatures.len(),
+        ),
+    ));
+    checks.push(FlowCheck::new(
+        "signRefundSignature",
+        transactions
+            .as_ref()
+            .map_err(|e| anyhow!("{}", e))
+            .and_then(|txs| {
+                check_refund_signature(txs, &sign.refund_signature, &offer.funding_pubkey)
+            }),
+    ));
+    checks.push(FlowCheck::new(
+        "fundingSignatures",
+        transactions
+            .as_ref()
+            .map_err(|e| anyhow!("{}", e))
+            .and_then(|txs| check_funding_signatures(txs, offer, sign)),
+    ));
+
+    checks
+}
+
+fn check_temporary_contract_id(offer: &OfferDlc, accept: &AcceptDlc) -> Result<String> {
+    if offer.temporary_contract_id != accept.temporary_contract_id {
+        bail!(
+            "Accept references temporary contract id {} but offer has {}",
+            hex::encode(accept.temporary_contract_id),
+            hex::encode(offer.temporary_contract_id)
+        );
+    }
+    Ok(format!(
+        "Temporary contract id {} matches",
+        hex::encode(offer.temporary_contract_id)
+    ))
+}
+
+fn check_adaptor_count(expected: &Result<u64>, actual: usize) -> Result<String> {
+    let expected = match expected {
+        Ok(expected) => *expected,
+        Err(e) => bail!("Could not compute expected CET count: {}", e),
+    };
+    if expected != actual as u64 {
+        bail!(
+            "Expected {} adaptor signatures from the contract info, found {}",
+            expected,
+            actual
+        );
+    }
+    Ok(format!(
+        "{} adaptor signatures match the CET outcomes",
+        actual
+    ))
+}
+
+fn check_contract_id(
+    transactions: &DlcTransactions,
+    temporary_id: &[u8; 32],
+    contract_id: &[u8; 32],
+) -> Result<String> {
+    let fund_txid = transactions.fund.compute_txid();
+    let output_index = fund_output_index(transactions)?;
+    let expected = compute_contract_id(fund_txid, output_index, temporary_id);
+    if &expected != contract_id {
+        bail!(
+            "Sign contract id {} does not match {} derived from funding txid {} output {}",
+            hex::encode(contract_id),
+            hex::encode(expected),
+            fund_txid,
+            output_index
+        );
+    }
+    Ok(format!(
+        "Contract id {} matches funding txid {} output {}",
+        hex::encode(contract_id),
+        fund_txid,
+        output_index
+    ))
+}
+
+pub fn check_refund_signature(
+    transactions: &DlcTransactions,
+    signature: &Signature,
+    pubkey: &secp256k1_zkp::PublicKey,
+) -> Result<String> {
+    let output_index = fund_output_index(transactions)? as usize;
+    let fund_value = transactions.fund.output[output_index].value;
+    dlc::verify_tx_input_sig(
+        SECP256K1,
+        signature,
+        &transactions.refund,
+        0,
+        &transactions.funding_script_pubkey,
+        fund_value,
+        pubkey,
+    )
+    .map_err(|e| {
+        anyhow!(
+            "Refund signature does not verify against {}: {:?}",
+            pubkey,
+            e
+        )
+    })?;
+    Ok(format!("Refund signature verifies against {}", pubkey))
+}
+
+fn check_funding_signatures(
+    transactions: &DlcTransactions,
+    offer: &OfferDlc,
+    sign: &SignDlc,
+) -> Result<String> {
+    let signatures = &sign.funding_signatures.funding_signatures;
+    if signatures.len() != offer.funding_inputs.len() {
+        bail!(
+            "Offer has {} funding inputs but sign carries {} funding signatures",
+            offer.funding_inputs.len(),
+            signatures.len()
+        );
+    }
+
+    let mut verified = 0;
+    for (i, (input, signature)) in offer.funding_inputs.iter().zip(signatures).enumerate() {
+        if signature.witness_elements.is_empty() {
+            bail!("Funding signature {} has no witness elements", i);
+        }
+
+        let outpoint = funding_input_outpoint(input)?;
+        let input_index = transactions
+            .fund
+            .input
+            .iter()
+            .position(|txin| txin.previous_output == outpoint)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Offer input {} is not spent by the funding transaction",
+                    outpoint
+                )
+            })?;
+
+        let prev_tx = funding_input_prev_tx(input)?;
+        let prev_output = &prev_tx.output[input.prev_tx_vout as usize];
+        if input.redeem_script.is_empty() && prev_output.script_pubkey.is_p2wpkh() {
+            verify_p2wpkh_signature(transactions, input_index, prev_output, signature).map_err(
+                |e| anyhow!("Funding signature {} for {} is invalid: {}", i, outpoint, e),
+            )?;
+            verified += 1;
+        }
+    }
+
+    Ok(format!(
+        "{} funding signatures cover all offer inputs ({} P2WPKH signatures verified)",
+        signatures.len(),
+        verified
+    ))
+}
+
+fn verify_p2wpkh_signature(
+    transactions: &DlcTransactions,
+    input_index: usize,
+    prev_output: &bitcoin::TxOut,
+    signature: &FundingSignature,
+) -> Result<()> {
+    if signature.witness_elements.len() != 2 {
+        bail!(
+            "expected 2 witness elements, found {}",
+            signature.witness_elements.len()
+        );
+    }
+
+    let sig = bitcoin::ecdsa::Signature::from_slice(&signature.witness_elements[0].witness)
+        .map_err(|e| anyhow!("invalid signature encoding: {}", e))?;
+    let pubkey = PublicKey::from_slice(&signature.witness_elements[1].witness)
+        .map_err(|e| anyhow!("invalid public key: {}", e))?;
+
+    let wpkh = pubkey
+        .wpubkey_hash()
+        .map_err(|e| anyhow!("public key is not compressed: {}", e))?;
+    if ScriptBuf::new_p2wpkh(&wpkh) != prev_output.script_pubkey {
+        bail!("public key does not match the spent output");
+    }
+
+    if sig.sighash_type != EcdsaSighashType::All {
+        bail!("unexpected sighash type {}", sig.sighash_type);
+    }
+    let sighash = SighashCache::new(&transactions.fund)
+        .p2wpkh_signature_hash(
+            input_index,
+            &prev_output.script_pubkey,
+            prev_output.value,
+            sig.sighash_type,
+        )
+        .map_err(|e| anyhow!("failed to compute sighash: {}", e))?;
+
+    let msg = Message::from_digest(sighash.to_byte_array());
+    SECP256K1
+        .verify_ecdsa(&msg, &sig.signature, &pubkey.inner)
+        .map_err(|e| anyhow!("signature does not verify: {}", e))
+}
@@ -0,0 +1,316 @@
+//! Deterministic mutation fuzzing of the message readers.
+//!
+//! Seeds are the hex encodings found in the test vectors. Each iteration
+//! mutates a seed and feeds it to every reader; an accepted input must
+//! re-encode to exactly the bytes that were consumed, and no reader may
+//! panic. This mirrors the cargo-fuzz targets under `fuzz/` for environments
+//! without a nightly toolchain.
+
+use crate::rng::Rng;
+use anyhow::{Context, Result};
+use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation};
+use dlc_messages::ser_impls::{read_as_tlv, write_as_tlv};
+use dlc_messages::{AcceptDlc, OfferDlc, SignDlc, ACCEPT_TYPE, OFFER_TYPE, SIGN_TYPE};
+use lightning::io::Cursor;
+use lightning::ln::wire::Type;
+use lightning::util::ser::{Readable, Writeable};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+/// Hex strings shorter than this many bytes (keys, signatures) are not seeds.
+const MIN_SEED_LEN: usize = 16;
+/// Byte values that tend to hit length-prefix and BigSize edge cases.
+const INTERESTING_BYTES: &[u8] = &[0x00, 0x01, 0x7f, 0x80, 0xfc, 0xfd, 0xfe, 0xff];
+
+enum Outcome {
+    Rejected,
+    Accepted,
+    /// Accepted, but re-encoding gave different bytes.
+    Mismatch(Vec<u8>),
+}
+
+type Reader = fn(&[u8]) -> Outcome;
+
+const READERS: &[(&str, Reader)] = &[
+    ("offer", |bytes| check_wire::<OfferDlc>(bytes, OFFER_TYPE)),
+    ("accept", |bytes| {
+        check_wire::<AcceptDlc>(bytes, ACCEPT_TYPE)
+    }),
+    ("sign", |bytes| check_wire::<SignDlc>(bytes, SIGN_TYPE)),
+    ("oracle-announcement", check_tlv::<OracleAnnouncement>),
+    ("oracle-attestation", check_tlv::<OracleAttestation>),
+];
+
+fn round_trip(bytes: &[u8], consumed: usize, encoded: Vec<u8>) -> Outcome {
+    if encoded == bytes[..consumed] {
+        Outcome::Accepted
+    } else {
+        Outcome::Mismatch(encoded)
+    }
+}
+
+fn check_wire<T: Readable + Writeable>(bytes: &[u8], msg_type: u16) -> Outcome {
+    if bytes.len() < 2 || u16::from_be_bytes([bytes[0], bytes[1]]) != msg_type {
+        return Outcome::Rejected;
+    }
+    let mut cursor = Cursor::new(&bytes[2..]);
+    let Ok(value) = T::read(&mut cursor) else {
+        return Outcome::Rejected;
+    };
+    let consumed = 2 + cursor.position() as usize;
+
+    let mut encoded = msg_type.to_be_bytes().to_vec();
+    encoded.extend(value.encode());
+    round_trip(bytes, consumed, encoded)
+}
+
+fn check_tlv<T: Readable + Writeable + Type>(bytes: &[u8]) -> Outcome {
+    let mut cursor = Cursor::new(bytes);
+    let Ok(value) = read_as_tlv::<T, _>(&mut cursor) else {
+        return Outcome::Rejected;
+    };
+    let consumed = cursor.position() as usize;
+
+    let mut encoded = Vec::new();
+    if write_as_tlv(&value, &mut encoded).is_err() {
+        return Outcome::Mismatch(encoded);
+    }
+    round_trip(bytes, consumed, encoded)
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReaderStats {
+    accepted: u64,
+    rejected: u64,
+    mismatches: u64,
+    panics: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Finding {
+    reader: &'static str,
+    kind: &'static str,
+    /// Iteration that produced the input, or `None` for an unmutated seed.
+    iteration: Option<u64>,
+    input: String,
+    detail: String,
+}
+
+fn collect_hex(value: &Value, seeds: &mut Vec<Vec<u8>>) {
+    match value {
+        Value::String(s) if s.len() >= MIN_SEED_LEN * 2 => {
+            if let Ok(bytes) = hex::decode(s) {
+                seeds.push(bytes);
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_hex(item, seeds)),
+        Value::Object(map) => map.values().for_each(|item| collect_hex(item, seeds)),
+        _ => {}
+    }
+}
+
+/// Collect every hex string from the `.json` and `.hex` files under `dir`,
+/// in sorted path order so the corpus is the same on every run. Other files,
+/// such as the `.bin` findings of an earlier run, are skipped.
+fn load_seeds(dir: &Path, seeds: &mut Vec<Vec<u8>>, warnings: &mut Vec<String>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read corpus directory {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            load_seeds(&path, seeds, warnings)?;
+            continue;
+        }
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        if !matches!(extension, Some("json" | "hex")) {
+            continue;
+        }
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                warnings.push(format!("Skipped {}: {}", path.display(), e));
+                continue;
+            }
+        };
+        if extension == Some("hex") {
+            collect_hex(&Value::String(contents.trim().to_string()), seeds);
+            continue;
+        }
+        match serde_json::from_str::<Value>(&contents) {
+            Ok(json) => collect_hex(&json, seeds),
+            Err(e) => warnings.push(format!("Skipped {}: {}", path.display(), e)),
+        }
+    }
+    Ok(())
+}
+
+fn mutate(rng: &mut Rng, seed: &[u8]) -> Vec<u8> {
+    let mut bytes = seed.to_vec();
+    for _ in 0..1 + rng.below(4) {
+        if bytes.is_empty() {
+            bytes.push(rng.byte());
+            continue;
+        }
+        let i = rng.index(bytes.len());
+        match rng.below(7) {
+            0 => bytes[i] ^= 1 << rng.below(8),
+            1 => bytes[i] = rng.byte(),
+            2 => bytes[i] = INTERESTING_BYTES[rng.index(INTERESTING_BYTES.len())],
+            3 => bytes.insert(i, rng.byte()),
+            4 => {
+                bytes.remove(i);
+            }
+            5 => bytes.truncate(i),
+            _ => {
+                let end = (i + 1 + rng.index(16)).min(bytes.len());
+                let chunk = bytes[i..end].to_vec();
+                bytes.splice(end..end, chunk);
+            }
+        }
+    }
+    bytes
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+struct Run {
+    stats: BTreeMap<&'static str, ReaderStats>,
+    findings: Vec<Finding>,
+    finding_count: u64,
+    max_findings: usize,
+}
+
+impl Run {
+    /// Feed `input` to every reader; returns whether any reader accepted it.
+    fn check(&mut self, input: &[u8], iteration: Option<u64>) -> bool {
+        let mut accepted = false;
+        for (name, reader) in READERS {
+            let stats = self.stats.entry(*name).or_default();
+            let (kind, detail) = match panic::catch_unwind(AssertUnwindSafe(|| reader(input))) {
+                Ok(Outcome::Rejected) => {
+                    stats.rejected += 1;
+                    continue;
+                }
+                Ok(Outcome::Accepted) => {
+                    stats.accepted += 1;
+                    accepted = true;
+                    continue;
+                }
+                Ok(Outcome::Mismatch(encoded)) => {
+                    stats.mismatches += 1;
+                    (
+                        "roundTripMismatch",
+                        format!("re-encoded as {}", hex::encode(encoded)),
+                    )
+                }
+                Err(payload) => {
+                    stats.panics += 1;
+                    ("panic", panic_message(payload.as_ref()))
+                }
+            };
+
+            self.finding_count += 1;
+            if self.findings.len() < self.max_findings {
+                self.findings.push(Finding {
+                    reader: *name,
+                    kind,
+                    iteration,
+                    input: hex::encode(input),
+                    detail,
+                });
+            }
+        }
+        accepted
+    }
+}
+
+pub fn fuzz_corpus(
+    corpus_dir: &str,
+    seed: u64,
+    iterations: u64,
+    max_findings: usize,
+    output_dir: Option<&str>,
+) -> Result<()> {
+    let mut seeds = Vec::new();
+    let mut warnings = Vec::new();
+    if let Err(e) = load_seeds(Path::new(corpus_dir), &mut seeds, &mut warnings) {
+        return crate::output_error(&format!("{:#}", e));
+    }
+    seeds.sort();
+    seeds.dedup();
+
+    let mut run = Run {
+        stats: BTreeMap::new(),
+        findings: Vec::new(),
+        finding_count: 0,
+        max_findings,
+    };
+
+    // Silence the default hook: panics are recorded as findings instead.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    // Only seeds that some reader accepts are worth mutating.
+    seeds.retain(|seed| run.check(seed, None));
+    if !seeds.is_empty() {
+        let mut rng = Rng::new(seed);
+        for iteration in 0..iterations {
+            let input = mutate(&mut rng, &seeds[rng.index(seeds.len())]);
+            run.check(&input, Some(iteration));
+        }
+    }
+
+    panic::set_hook(hook);
+
+    if seeds.is_empty() {
+        return crate::output_error(&format!("No decodable messages found under {}", corpus_dir));
+    }
+
+    if let Some(output_dir) = output_dir {
+        write_findings(Path::new(output_dir), &run.findings)?;
+    }
+
+    let message = format!(
+        "{} iterations over {} seeds: {} findings",
+        iterations,
+        seeds.len(),
+        run.finding_count
+    );
+    let data = json!({
+        "seed": seed,
+        "iterations": iterations,
+        "seeds": seeds.len(),
+        "readers": run.stats,
+        "findingCount": run.finding_count,
+        "findings": run.findings,
+        "warnings": warnings,
+    });
+    crate::output_success_with_data("fuzz-corpus", &data, &message)
+}
+
+/// Write each finding as a raw input file, usable as a cargo-fuzz artifact.
+fn write_findings(dir: &Path, findings: &[Finding]) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create output directory {}", dir.display()))?;
+    for (i, finding) in findings.iter().enumerate() {
+        let bytes = hex::decode(&finding.input)?;
+        let path = dir.join(format!("{}-{}-{}.bin", finding.reader, finding.kind, i));
+        fs::write(&path, bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
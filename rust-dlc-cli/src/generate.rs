@@ -0,0 +1,948 @@
+//! Seeded generators for random but protocol-valid DLC messages.
+//!
+//! Messages are built as JSON in the serde layout used by the test vectors
+//! and parsed back into rust-dlc types where later steps need them. Generated
+//! accept and sign messages carry adaptor signatures matching the offer's CET
+//! count, real refund and funding signatures, and the contract id derived from
+//! the funding transaction, so a generated offer/accept/sign triple passes
+//! `validate-flow`.
+
+use crate::cets::{adaptor_point_count, contract_infos};
+use crate::decode::decode_as;
+use crate::dlc_input::DLC_INPUT_MAX_WITNESS_LEN;
+use crate::rng::Rng;
+use crate::transactions::{
+    build_dlc_transactions, compute_contract_id, fund_output_index, funding_input_outpoint,
+    funding_input_value,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use bitcoin::{
+    absolute, transaction, Amount, CompressedPublicKey, Network, OutPoint, ScriptBuf, Sequence,
+    Transaction, TxIn, TxOut, Txid, Witness,
+};
+use dlc::{make_funding_redeemscript, DlcTransactions};
+use dlc_messages::oracle_msgs::{
+    DigitDecompositionEventDescriptor, EnumEventDescriptor, EventDescriptor, OracleAnnouncement,
+    OracleAttestation, OracleEvent,
+};
+use dlc_messages::{AcceptDlc, OfferDlc};
+use lightning::util::ser::Writeable;
+use secp256k1_zkp::ecdsa::Signature;
+use secp256k1_zkp::{EcdsaAdaptorSignature, Keypair, Message, PublicKey, SecretKey, SECP256K1};
+use serde_json::{json, Value};
+
+/// Every type accepted by the generators.
+pub const GENERATED_TYPES: &[&str] = &[
+    "offer",
+    "accept",
+    "sign",
+    "oracle-announcement",
+    "oracle-attestation",
+    "oracle-event",
+    "oracle-info",
+    "contract-info",
+    "contract-descriptor",
+];
+
+/// Witness size of a P2WPKH spend (signature and compressed public key).
+const P2WPKH_MAX_WITNESS_LEN: u16 = 107;
+/// Amount added to each party's collateral in its funding inputs, enough to
+/// cover fees at any generated fee rate.
+const FUNDING_SURPLUS: u64 = 500_000;
+const ROUNDING_MODS: &[u64] = &[1, 10, 100, 1000];
+
+#[derive(Clone, Debug)]
+enum EventKind {
+    Enum(Vec<String>),
+    Digits { base: u16, nb_digits: u16 },
+}
+
+/// A generated offer/accept/sign triple.
+pub struct Dlc {
+    pub offer: Value,
+    pub accept: Value,
+    pub sign: Value,
+}
+
+/// Keys that can spend a generated funding input.
+enum InputKey {
+    P2wpkh(SecretKey),
+    /// Both fund keys of the earlier contract whose funding output a DLC
+    /// input spends.
+    Dlc {
+        local: SecretKey,
+        remote: SecretKey,
+    },
+}
+
+/// Keys behind a generated offer, needed to produce the sign message.
+struct OfferKeys {
+    fund_sk: SecretKey,
+    input_keys: Vec<InputKey>,
+}
+
+/// Shape of the generated contract; each field is random when `None`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    pub contract: Option<ContractKind>,
+    pub oracles: Option<u64>,
+    pub threshold: Option<u64>,
+    /// Give binary numeric events with several oracles random `oracleParams`
+    /// (a bounded difference between oracles).
+    pub bounded_difference: bool,
+    /// Number of sub-contracts of a disjoint contract; two when `None`.
+    pub sub_contracts: Option<u64>,
+    /// Fund the offer's first input from an earlier contract's funding output.
+    pub dlc_inputs: bool,
+    /// Chain named by the offer's chain hash; regtest, as in the dlcspecs
+    /// test vectors, when `None`.
+    pub network: Option<Network>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContractKind {
+    Enum,
+    Numeric,
+    /// A numeric contract paying out along a single hyperbola piece.
+    Hyperbola,
+    /// A disjoint contract info with sub-contracts of random kinds, each on
+    /// its own event.
+    Disjoint,
+}
+
+impl ContractKind {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "enum" => Ok(ContractKind::Enum),
+            "numeric" => Ok(ContractKind::Numeric),
+            "hyperbola" => Ok(ContractKind::Hyperbola),
+            "disjoint" => Ok(ContractKind::Disjoint),
+            _ => bail!(
+                "Unsupported contract kind: {} (expected enum, numeric, hyperbola or disjoint)",
+                name
+            ),
+        }
+    }
+}
+
+impl Options {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(oracles) = self.oracles {
+            if oracles == 0 {
+                bail!("At least one oracle is required");
+            }
+        }
+        if let Some(threshold) = self.threshold {
+            if threshold == 0 || threshold > self.oracles.unwrap_or(threshold) {
+                bail!(
+                    "Invalid threshold {} for {} oracles",
+                    threshold,
+                    self.oracles.unwrap_or(threshold)
+                );
+            }
+        }
+        if self.bounded_difference {
+            if self.oracles == Some(1) {
+                bail!("Bounded difference requires more than one oracle");
+            }
+            if self.contract == Some(ContractKind::Enum) {
+                bail!("Bounded difference requires a numeric contract");
+            }
+        }
+        if let Some(sub_contracts) = self.sub_contracts {
+            if sub_contracts < 2 {
+                bail!("A disjoint contract needs at least two sub-contracts");
+            }
+            if matches!(self.contract, Some(kind) if kind != ContractKind::Disjoint) {
+                bail!("Sub-contracts require a disjoint contract");
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Generator {
+    rng: Rng,
+    options: Options,
+}
+
+fn p2wpkh(sk: &SecretKey) -> ScriptBuf {
+    let pubkey = CompressedPublicKey(PublicKey::from_secret_key(SECP256K1, sk));
+    ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash())
+}
+
+fn payout_point(outcome: u64, payout: u64) -> Value {
+    json!({ "eventOutcome": outcome, "outcomePayout": payout, "extraPrecision": 0 })
+}
+
+fn funding_signature(sighash: [u8; 32], sk: &SecretKey) -> Vec<u8> {
+    bitcoin::ecdsa::Signature {
+        signature: SECP256K1.sign_ecdsa(&Message::from_digest(sighash), sk),
+        sighash_type: EcdsaSighashType::All,
+    }
+    .to_vec()
+}
+
+fn to_json<T: serde::Serialize>(value: &T, name: &str) -> Result<Value> {
+    serde_json::to_value(value).with_context(|| format!("Failed to convert {} to JSON", name))
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Generator::with_options(seed, Options::default())
+    }
+
+    pub fn with_options(seed: u64, options: Options) -> Self {
+        Generator {
+            rng: Rng::new(seed),
+            options,
+        }
+    }
+
+    fn numeric(&mut self) -> bool {
+        match self.options.contract {
+            Some(ContractKind::Enum) => false,
+            Some(ContractKind::Numeric | ContractKind::Hyperbola) => true,
+            _ => self.rng.bool(),
+        }
+    }
+
+    fn bytes32(&mut self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        self.rng.fill(&mut bytes);
+        bytes
+    }
+
+    fn secret_key(&mut self) -> SecretKey {
+        loop {
+            if let Ok(sk) = SecretKey::from_slice(&self.bytes32()) {
+                return sk;
+            }
+        }
+    }
+
+    fn keypair(&mut self) -> Keypair {
+        Keypair::from_secret_key(SECP256K1, &self.secret_key())
+    }
+
+    fn digest(&mut self) -> Message {
+        Message::from_digest(self.bytes32())
+    }
+
+    fn spk_hex(&mut self) -> String {
+        hex::encode(p2wpkh(&self.secret_key()).as_bytes())
+    }
+
+    fn word(&mut self, len: usize) -> String {
+        (0..len)
+            .map(|_| (b'a' + self.rng.below(26) as u8) as char)
+            .collect()
+    }
+
+    fn maturity(&mut self) -> u32 {
+        self.rng.range(1_600_000_000, 1_700_000_000) as u32
+    }
+
+    fn event_kind(&mut self, numeric: bool) -> EventKind {
+        if !numeric {
+            let count = self.rng.range(2, 5);
+            let outcomes = (0..count)
+                .map(|i| format!("{}-{}", self.word(5), i))
+                .collect();
+            return EventKind::Enum(outcomes);
+        }
+        if self.rng.below(4) == 0 {
+            EventKind::Digits {
+                base: 10,
+                nb_digits: self.rng.range(1, 3) as u16,
+            }
+        } else {
+            EventKind::Digits {
+                base: 2,
+                nb_digits: self.rng.range(4, 10) as u16,
+            }
+        }
+    }
+
+    fn oracle_event(&mut self, kind: &EventKind, event_id: &str, maturity: u32) -> OracleEvent {
+        let (event_descriptor, nonce_count) = match kind {
+            EventKind::Enum(outcomes) => (
+                EventDescriptor::EnumEvent(EnumEventDescriptor {
+                    outcomes: outcomes.clone(),
+                }),
+                1,
+            ),
+            EventKind::Digits { base, nb_digits } => (
+                EventDescriptor::DigitDecompositionEvent(DigitDecompositionEventDescriptor {
+                    base: *base,
+                    is_signed: false,
+                    unit: "sats/sec".to_string(),
+                    precision: 0,
+                    nb_digits: *nb_digits,
+                }),
+                *nb_digits as usize,
+            ),
+        };
+
+        OracleEvent {
+            oracle_nonces: (0..nonce_count)
+                .map(|_| self.keypair().x_only_public_key().0)
+                .collect(),
+            event_maturity_epoch: maturity,
+            event_descriptor,
+            event_id: event_id.to_string(),
+        }
+    }
+
+    /// An announcement signed by a fresh oracle key, like
+    /// `create-oracle-announcement`.
+    fn announcement(
+        &mut self,
+        kind: &EventKind,
+        event_id: &str,
+        maturity: u32,
+    ) -> Result<OracleAnnouncement> {
+        let keypair = self.keypair();
+        let oracle_event = self.oracle_event(kind, event_id, maturity);
+
+        let mut event_bytes = Vec::new();
+        oracle_event
+            .write(&mut event_bytes)
+            .context("Failed to serialize oracle event")?;
+        let hash = sha256::Hash::hash(&event_bytes);
+        let msg = Message::from_digest(hash.to_byte_array());
+
+        Ok(OracleAnnouncement {
+            announcement_signature: SECP256K1.sign_schnorr_no_aux_rand(&msg, &keypair),
+            oracle_public_key: keypair.x_only_public_key().0,
+            oracle_event,
+        })
+    }
+
+    fn attestation(&mut self) -> OracleAttestation {
+        let numeric = self.numeric();
+        let outcomes = match self.event_kind(numeric) {
+            EventKind::Enum(outcomes) => vec![outcomes[self.rng.index(outcomes.len())].clone()],
+            EventKind::Digits { base, nb_digits } => (0..nb_digits)
+                .map(|_| self.rng.below(base as u64).to_string())
+                .collect(),
+        };
+
+        let keypair = self.keypair();
+        let signatures = outcomes
+            .iter()
+            .map(|outcome| {
+                let hash = sha256::Hash::hash(outcome.as_bytes());
+                let msg = Message::from_digest(hash.to_byte_array());
+                SECP256K1.sign_schnorr_no_aux_rand(&msg, &keypair)
+            })
+            .collect();
+
+        OracleAttestation {
+            event_id: self.word(8),
+            oracle_public_key: keypair.x_only_public_key().0,
+            signatures,
+            outcomes,
+        }
+    }
+
+    fn oracle_info(&mut self, kind: &EventKind, maturity: u32) -> Result<Value> {
+        let event_id = self.word(8);
+        let single = match (self.options.oracles, self.options.threshold) {
+            (Some(oracles), _) => oracles == 1,
+            (None, Some(threshold)) => threshold == 1 && self.rng.below(3) != 0,
+            (None, None) => self.rng.below(3) != 0,
+        };
+        if single {
+            let announcement = self.announcement(kind, &event_id, maturity)?;
+            return Ok(json!({
+                "single": {
+                    "oracleAnnouncement": to_json(&announcement, "OracleAnnouncement")?,
+                }
+            }));
+        }
+
+        let count = match (self.options.oracles, self.options.threshold) {
+            (Some(oracles), _) => oracles,
+            (None, Some(threshold)) => self.rng.range(threshold.max(2), threshold.max(5)),
+            (None, None) => self.rng.range(2, 5),
+        };
+        let threshold = match self.options.threshold {
+            Some(threshold) => threshold,
+            None => self.rng.range(1, count),
+        };
+        let announcements = (0..count)
+            .map(|_| {
+                let announcement = self.announcement(kind, &event_id, maturity)?;
+                to_json(&announcement, "OracleAnnouncement")
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Bounded difference applies to binary numeric events only, and only
+        // draws from the RNG when asked for so existing seeds are unchanged.
+        let oracle_params = match kind {
+            EventKind::Digits { base: 2, nb_digits } if self.options.bounded_difference => {
+                let max_error_exp = self.rng.range(1, *nb_digits as u64 - 1);
+                json!({
+                    "maxErrorExp": max_error_exp,
+                    "minFailExp": self.rng.range(0, max_error_exp - 1),
+                    "maximizeCoverage": self.rng.bool(),
+                })
+            }
+            _ => Value::Null,
+        };
+
+        Ok(json!({
+            "multi": {
+                "threshold": threshold,
+                "oracleAnnouncements": announcements,
+                "oracleParams": oracle_params,
+            }
+        }))
+    }
+
+    fn contract_descriptor(&mut self, kind: &EventKind, total_collateral: u64) -> Value {
+        let (base, nb_digits) = match kind {
+            EventKind::Enum(outcomes) => {
+                let payouts: Vec<Value> = outcomes
+                    .iter()
+                    .map(|outcome| {
+                        json!({
+                            "outcome": outcome,
+                            "offerPayout": self.rng.range(0, total_collateral),
+                        })
+                    })
+                    .collect();
+                return json!({ "enumeratedContractDescriptor": { "payouts": payouts } });
+            }
+            EventKind::Digits { base, nb_digits } => (*base as u64, *nb_digits),
+        };
+
+        let max_outcome = base.pow(nb_digits as u32) - 1;
+        let (pieces, last_endpoint) = if self.options.contract == Some(ContractKind::Hyperbola) {
+            self.hyperbola_pieces(max_outcome, total_collateral)
+        } else {
+            self.polynomial_pieces(max_outcome, total_collateral)
+        };
+
+        json!({
+            "numericOutcomeContractDescriptor": {
+                "numDigits": nb_digits,
+                "payoutFunction": {
+                    "payoutFunctionPieces": pieces,
+                    "lastEndpoint": last_endpoint,
+                },
+                "roundingIntervals": {
+                    "intervals": [{
+                        "beginInterval": 0,
+                        "roundingMod": ROUNDING_MODS[self.rng.index(ROUNDING_MODS.len())],
+                    }]
+                }
+            }
+        })
+    }
+
+    /// Split the outcome domain at distinct interior points into 1 to 3
+    /// polynomial pieces.
+    fn polynomial_pieces(
+        &mut self,
+        max_outcome: u64,
+        total_collateral: u64,
+    ) -> (Vec<Value>, Value) {
+        let piece_count = self.rng.range(1, max_outcome.min(3));
+        let mut outcomes = std::collections::BTreeSet::new();
+        while (outcomes.len() as u64) < piece_count - 1 {
+            outcomes.insert(self.rng.range(1, max_outcome - 1));
+        }
+        let outcomes: Vec<u64> = std::iter::once(0)
+            .chain(outcomes)
+            .chain(std::iter::once(max_outcome))
+            .collect();
+        let payouts: Vec<u64> = outcomes
+            .iter()
+            .map(|_| self.rng.range(0, total_collateral))
+            .collect();
+
+        let pieces = outcomes
+            .windows(2)
+            .zip(payouts.windows(2))
+            .map(|(x, y)| {
+                // An optional interior point on the line between the
+                // endpoints keeps the piece within [0, total collateral].
+                let mut payout_points = Vec::new();
+                if x[1] - x[0] >= 2 && self.rng.bool() {
+                    let mid = x[0] + (x[1] - x[0]) / 2;
+                    let slope = (y[1] as f64 - y[0] as f64) / (x[1] - x[0]) as f64;
+                    let payout = (y[0] as f64 + slope * (mid - x[0]) as f64).round() as u64;
+                    payout_points.push(payout_point(mid, payout));
+                }
+                json!({
+                    "endPoint": payout_point(x[0], y[0]),
+                    "payoutCurvePiece": {
+                        "polynomialPayoutCurvePiece": { "payoutPoints": payout_points }
+                    }
+                })
+            })
+            .collect();
+        (
+            pieces,
+            payout_point(max_outcome, payouts[payouts.len() - 1]),
+        )
+    }
+
+    /// A single decreasing piece `d / x` with `x` the positive root of
+    /// `x^2 - outcome * x - 1`, so the offer receives `d` at outcome 0.
+    fn hyperbola_pieces(&mut self, max_outcome: u64, total_collateral: u64) -> (Vec<Value>, Value) {
+        let d = self.rng.range(total_collateral / 2, total_collateral);
+        let payout_at = |outcome: u64| {
+            let t = outcome as f64;
+            (2.0 * d as f64 / (t + (t * t + 4.0).sqrt())).round() as u64
+        };
+        let piece = json!({
+            "endPoint": payout_point(0, payout_at(0)),
+            "payoutCurvePiece": {
+                "hyperbolaPayoutCurvePiece": {
+                    "usePositivePiece": true,
+                    "translateOutcome": 0.0,
+                    "translatePayout": 0.0,
+                    "a": 1.0,
+                    "b": -1.0,
+                    "c": 0.0,
+                    "d": d as f64,
+                }
+            }
+        });
+        (
+            vec![piece],
+            payout_point(max_outcome, payout_at(max_outcome)),
+        )
+    }
+
+    fn contract_info_inner(&mut self, total_collateral: u64, maturity: u32) -> Result<Value> {
+        let numeric = self.numeric();
+        let kind = self.event_kind(numeric);
+        Ok(json!({
+            "contractDescriptor": self.contract_descriptor(&kind, total_collateral),
+            "oracleInfo": self.oracle_info(&kind, maturity)?,
+        }))
+    }
+
+    fn contract_info(&mut self, total_collateral: u64, maturity: u32) -> Result<Value> {
+        let disjoint = match (self.options.contract, self.options.sub_contracts) {
+            (Some(kind), _) => kind == ContractKind::Disjoint,
+            (None, Some(_)) => true,
+            (None, None) => self.rng.below(4) == 0,
+        };
+        if !disjoint {
+            return Ok(json!({
+                "singleContractInfo": {
+                    "totalCollateral": total_collateral,
+                    "contractInfo": self.contract_info_inner(total_collateral, maturity)?,
+                }
+            }));
+        }
+
+        let infos = (0..self.options.sub_contracts.unwrap_or(2))
+            .map(|_| self.contract_info_inner(total_collateral, maturity))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(json!({
+            "disjointContractInfo": {
+                "totalCollateral": total_collateral,
+                "contractInfos": infos,
+            }
+        }))
+    }
+
+    /// A fresh previous transaction paying `value` to `script_pubkey`.
+    fn prev_tx(&mut self, value: u64, script_pubkey: ScriptBuf) -> String {
+        let prev_tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint {
+                    txid: Txid::from_byte_array(self.bytes32()),
+                    vout: 0,
+                },
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey,
+            }],
+        };
+        bitcoin::consensus::encode::serialize_hex(&prev_tx)
+    }
+
+    /// A P2WPKH funding input spending a fresh previous transaction.
+    fn funding_input(&mut self, value: u64) -> (Value, InputKey) {
+        let sk = self.secret_key();
+        let input = json!({
+            "prevTx": self.prev_tx(value, p2wpkh(&sk)),
+            "inputSerialId": self.rng.next_u64(),
+            "prevTxVout": 0,
+            "sequence": Sequence::MAX.0,
+            "maxWitnessLen": P2WPKH_MAX_WITNESS_LEN,
+            "redeemScript": "",
+            "dlcInput": null,
+        });
+        (input, InputKey::P2wpkh(sk))
+    }
+
+    /// A DLC input spending the 2-of-2 funding output of an earlier contract.
+    fn dlc_funding_input(&mut self, value: u64) -> (Value, InputKey) {
+        let local = self.secret_key();
+        let remote = self.secret_key();
+        let local_pubkey = PublicKey::from_secret_key(SECP256K1, &local);
+        let remote_pubkey = PublicKey::from_secret_key(SECP256K1, &remote);
+        let script_pubkey = make_funding_redeemscript(&local_pubkey, &remote_pubkey).to_p2wsh();
+        let input = json!({
+            "prevTx": self.prev_tx(value, script_pubkey),
+            "inputSerialId": self.rng.next_u64(),
+            "prevTxVout": 0,
+            "sequence": Sequence::MAX.0,
+            "maxWitnessLen": DLC_INPUT_MAX_WITNESS_LEN,
+            "redeemScript": "",
+            "dlcInput": {
+                "localFundPubkey": local_pubkey.to_string(),
+                "remoteFundPubkey": remote_pubkey.to_string(),
+                "contractId": hex::encode(self.bytes32()),
+            },
+        });
+        (input, InputKey::Dlc { local, remote })
+    }
+
+    fn funding_inputs(&mut self, collateral: u64, dlc_input: bool) -> (Vec<Value>, Vec<InputKey>) {
+        let count = self.rng.range(1, 2);
+        (0..count)
+            .map(|i| {
+                let value = collateral / count + FUNDING_SURPLUS;
+                if dlc_input && i == 0 {
+                    self.dlc_funding_input(value)
+                } else {
+                    self.funding_input(value)
+                }
+            })
+            .unzip()
+    }
+
+    fn adaptor_signatures(&mut self, count: u64, sk: &SecretKey) -> Result<Value> {
+        let signatures = (0..count)
+            .map(|_| {
+                let msg = self.digest();
+                let adaptor_point = PublicKey::from_secret_key(SECP256K1, &self.secret_key());
+                let signature =
+                    EcdsaAdaptorSignature::encrypt_no_aux_rand(SECP256K1, &msg, sk, &adaptor_point);
+                Ok(json!({ "signature": to_json(&signature, "adaptor signature")? }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(json!({ "ecdsaAdaptorSignatures": signatures }))
+    }
+
+    fn offer(&mut self) -> Result<(Value, OfferKeys)> {
+        let offer_collateral = self.rng.range(10_000, 100_000_000);
+        let accept_collateral = self.rng.range(10_000, 100_000_000);
+        let cet_locktime = self.maturity();
+        let refund_locktime = cet_locktime + self.rng.range(86_400, 30 * 86_400) as u32;
+
+        let contract_info =
+            self.contract_info(offer_collateral + accept_collateral, cet_locktime)?;
+        let fund_sk = self.secret_key();
+        let (funding_inputs, input_keys) =
+            self.funding_inputs(offer_collateral, self.options.dlc_inputs);
+
+        let offer = json!({
+            "protocolVersion": 1,
+            "contractFlags": 0,
+            "chainHash": to_json(
+                &self.options.network.unwrap_or(Network::Regtest).chain_hash(),
+                "chain hash",
+            )?,
+            "temporaryContractId": hex::encode(self.bytes32()),
+            "contractInfo": contract_info,
+            "fundingPubkey": PublicKey::from_secret_key(SECP256K1, &fund_sk).to_string(),
+            "payoutSpk": self.spk_hex(),
+            "payoutSerialId": self.rng.next_u64(),
+            "offerCollateral": offer_collateral,
+            "fundingInputs": funding_inputs,
+            "changeSpk": self.spk_hex(),
+            "changeSerialId": self.rng.next_u64(),
+            "fundOutputSerialId": self.rng.next_u64(),
+            "feeRatePerVb": self.rng.range(1, 10),
+            "cetLocktime": cet_locktime,
+            "refundLocktime": refund_locktime,
+        });
+        Ok((
+            offer,
+            OfferKeys {
+                fund_sk,
+                input_keys,
+            },
+        ))
+    }
+
+    fn accept(&mut self, offer: &OfferDlc) -> Result<(Value, DlcTransactions)> {
+        let (total_collateral, _) = contract_infos(&offer.contract_info);
+        let accept_collateral = total_collateral - offer.offer_collateral;
+        let adaptor_count = adaptor_point_count(&offer.contract_info)?;
+
+        let fund_sk = self.secret_key();
+        let (funding_inputs, _) = self.funding_inputs(accept_collateral, false);
+        // Placeholder until the refund transaction exists to be signed.
+        let placeholder = SECP256K1.sign_ecdsa(&self.digest(), &fund_sk);
+
+        let mut accept = json!({
+            "protocolVersion": 1,
+            "temporaryContractId": hex::encode(offer.temporary_contract_id),
+            "acceptCollateral": accept_collateral,
+            "fundingPubkey": PublicKey::from_secret_key(SECP256K1, &fund_sk).to_string(),
+            "payoutSpk": self.spk_hex(),
+            "payoutSerialId": self.rng.next_u64(),
+            "fundingInputs": funding_inputs,
+            "changeSpk": self.spk_hex(),
+            "changeSerialId": self.rng.next_u64(),
+            "cetAdaptorSignatures": self.adaptor_signatures(adaptor_count, &fund_sk)?,
+            "refundSignature": to_json(&placeholder, "refund signature")?,
+            "negotiationFields": null,
+        });
+
+        let parsed: AcceptDlc = serde_json::from_value(accept.clone())
+            .context("Generated accept does not parse as AcceptDlc")?;
+        let transactions = build_dlc_transactions(offer, &parsed)?;
+        accept["refundSignature"] = to_json(
+            &refund_signature(&transactions, &fund_sk)?,
+            "refund signature",
+        )?;
+        Ok((accept, transactions))
+    }
+
+    fn sign(
+        &mut self,
+        offer: &OfferDlc,
+        keys: &OfferKeys,
+        transactions: &DlcTransactions,
+    ) -> Result<Value> {
+        let contract_id = compute_contract_id(
+            transactions.fund.compute_txid(),
+            fund_output_index(transactions)?,
+            &offer.temporary_contract_id,
+        );
+        let adaptor_count = adaptor_point_count(&offer.contract_info)?;
+
+        let funding_signatures = offer
+            .funding_inputs
+            .iter()
+            .zip(&keys.input_keys)
+            .map(|(input, key)| {
+                let outpoint = funding_input_outpoint(input)?;
+                let input_index = transactions
+                    .fund
+                    .input
+                    .iter()
+                    .position(|txin| txin.previous_output == outpoint)
+                    .ok_or_else(|| anyhow!("Funding transaction does not spend {}", outpoint))?;
+                let value = funding_input_value(input)?;
+                let mut cache = SighashCache::new(&transactions.fund);
+                let witness = match key {
+                    InputKey::P2wpkh(sk) => {
+                        let sighash = cache
+                            .p2wpkh_signature_hash(
+                                input_index,
+                                &p2wpkh(sk),
+                                value,
+                                EcdsaSighashType::All,
+                            )
+                            .map_err(|e| anyhow!("Failed to compute funding sighash: {}", e))?;
+                        let pubkey = PublicKey::from_secret_key(SECP256K1, sk);
+                        vec![
+                            funding_signature(sighash.to_byte_array(), sk),
+                            pubkey.serialize().to_vec(),
+                        ]
+                    }
+                    InputKey::Dlc { local, remote } => {
+                        let local_pubkey = PublicKey::from_secret_key(SECP256K1, local);
+                        let remote_pubkey = PublicKey::from_secret_key(SECP256K1, remote);
+                        let script = make_funding_redeemscript(&local_pubkey, &remote_pubkey);
+                        let sighash = cache
+                            .p2wsh_signature_hash(
+                                input_index,
+                                &script,
+                                value,
+                                EcdsaSighashType::All,
+                            )
+                            .map_err(|e| anyhow!("Failed to compute funding sighash: {}", e))?;
+                        // CHECKMULTISIG takes signatures in redeem script key order.
+                        let mut sks = [local, remote];
+                        sks.sort_by_key(|sk| PublicKey::from_secret_key(SECP256K1, sk).serialize());
+                        vec![
+                            Vec::new(),
+                            funding_signature(sighash.to_byte_array(), sks[0]),
+                            funding_signature(sighash.to_byte_array(), sks[1]),
+                            script.to_bytes(),
+                        ]
+                    }
+                };
+                let witness_elements: Vec<Value> = witness
+                    .iter()
+                    .map(|element| json!({ "witness": hex::encode(element) }))
+                    .collect();
+                Ok(json!({ "witnessElements": witness_elements }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(json!({
+            "protocolVersion": 1,
+            "contractId": hex::encode(contract_id),
+            "cetAdaptorSignatures": self.adaptor_signatures(adaptor_count, &keys.fund_sk)?,
+            "refundSignature": to_json(
+                &refund_signature(transactions, &keys.fund_sk)?,
+                "refund signature",
+            )?,
+            "fundingSignatures": { "fundingSignatures": funding_signatures },
+        }))
+    }
+
+    /// A consistent offer/accept/sign triple.
+    pub fn dlc(&mut self) -> Result<Dlc> {
+        let (offer_json, keys) = self.offer()?;
+        let offer: OfferDlc = serde_json::from_value(offer_json.clone())
+            .context("Generated offer does not parse as OfferDlc")?;
+        let (accept, transactions) = self.accept(&offer)?;
+        let sign = self.sign(&offer, &keys, &transactions)?;
+        Ok(Dlc {
+            offer: offer_json,
+            accept,
+            sign,
+        })
+    }
+
+    /// A random instance of the named type, as JSON.
+    pub fn message(&mut self, msg_type: &str) -> Result<Value> {
+        let numeric = self.numeric();
+        match msg_type {
+            "offer" => Ok(self.offer()?.0),
+            "accept" => Ok(self.dlc()?.accept),
+            "sign" => Ok(self.dlc()?.sign),
+            "oracle-announcement" => {
+                let kind = self.event_kind(numeric);
+                let (event_id, maturity) = (self.word(8), self.maturity());
+                to_json(
+                    &self.announcement(&kind, &event_id, maturity)?,
+                    "OracleAnnouncement",
+                )
+            }
+            "oracle-attestation" => to_json(&self.attestation(), "OracleAttestation"),
+            "oracle-event" => {
+                let kind = self.event_kind(numeric);
+                let (event_id, maturity) = (self.word(8), self.maturity());
+                to_json(
+                    &self.oracle_event(&kind, &event_id, maturity),
+                    "OracleEvent",
+                )
+            }
+            "oracle-info" => {
+                let kind = self.event_kind(numeric);
+                let maturity = self.maturity();
+                self.oracle_info(&kind, maturity)
+            }
+            "contract-info" => {
+                let total_collateral = self.rng.range(20_000, 200_000_000);
+                let maturity = self.maturity();
+                self.contract_info(total_collateral, maturity)
+            }
+            "contract-descriptor" => {
+                let kind = self.event_kind(numeric);
+                let total_collateral = self.rng.range(20_000, 200_000_000);
+                Ok(self.contract_descriptor(&kind, total_collateral))
+            }
+            _ => bail!(
+                "Unsupported message type for generation: {} (expected one of: {})",
+                msg_type,
+                GENERATED_TYPES.join(", ")
+            ),
+        }
+    }
+}
+
+/// Sign the refund transaction's funding input with one of the fund keys.
+fn refund_signature(transactions: &DlcTransactions, sk: &SecretKey) -> Result<Signature> {
+    let output_index = fund_output_index(transactions)? as usize;
+    let fund_value = transactions.fund.output[output_index].value;
+    let sighash = SighashCache::new(&transactions.refund)
+        .p2wsh_signature_hash(
+            0,
+            &transactions.funding_script_pubkey,
+            fund_value,
+            EcdsaSighashType::All,
+        )
+        .map_err(|e| anyhow!("Failed to compute refund sighash: {}", e))?;
+    Ok(SECP256K1.sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), sk))
+}
+
+/// rust-dlc's encoding of a message and its canonical JSON.
+pub fn encode(msg_type: &str, json: &Value) -> Result<(Vec<u8>, Value)> {
+    let bytes = crate::serialize_json(msg_type, json)?;
+    let canonical = decode_as(&bytes, msg_type)?.data;
+    Ok((bytes, canonical))
+}
+
+fn encoded_case(msg_type: &str, json: &Value) -> Result<Value> {
+    let (bytes, canonical) = encode(msg_type, json)?;
+    Ok(json!({ "hex": hex::encode(bytes), "json": canonical }))
+}
+
+/// Generate `count` messages of `msg_type` (or `dlc` for offer/accept/sign
+/// triples) from consecutive seeds starting at `seed`.
+pub fn generate(msg_type: &str, seed: u64, count: u64, options: Options) -> Result<()> {
+    if let Err(e) = options.validate() {
+        return crate::output_error(&e.to_string());
+    }
+
+    let mut cases = Vec::new();
+    for i in 0..count {
+        let case_seed = seed.wrapping_add(i);
+        let mut generator = Generator::with_options(case_seed, options);
+        let case = if msg_type == "dlc" {
+            generator.dlc().and_then(|dlc| {
+                Ok(json!({
+                    "seed": case_seed,
+                    "offer": encoded_case("offer", &dlc.offer)?,
+                    "accept": encoded_case("accept", &dlc.accept)?,
+                    "sign": encoded_case("sign", &dlc.sign)?,
+                }))
+            })
+        } else {
+            generator
+                .message(msg_type)
+                .and_then(|json| encoded_case(msg_type, &json))
+                .map(|mut case| {
+                    case["seed"] = json!(case_seed);
+                    case
+                })
+        };
+
+        match case {
+            Ok(case) => cases.push(case),
+            Err(e) => {
+                return crate::output_error(&format!(
+                    "Failed to generate {} from seed {}: {:#}",
+                    msg_type, case_seed, e
+                ))
+            }
+        }
+    }
+
+    let data = json!({
+        "seed": seed,
+        "count": count,
+        "cases": cases,
+    });
+    crate::output_success_with_data(
+        msg_type,
+        &data,
+        &format!("Generated {} {} message(s)", count, msg_type),
+    )
+}
@@ -0,0 +1,145 @@
+//! Reading message bytes from files or stdin in hex, base64 or raw binary,
+//! and writing them back out in any of those encodings.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Hex,
+    Base64,
+    Binary,
+}
+
+impl Encoding {
+    /// Parse an encoding name, `auto` meaning "detect from the input".
+    pub fn parse(name: &str) -> Result<Option<Encoding>> {
+        match name {
+            "auto" => Ok(None),
+            "hex" => Ok(Some(Encoding::Hex)),
+            "base64" => Ok(Some(Encoding::Base64)),
+            "binary" | "raw" => Ok(Some(Encoding::Binary)),
+            _ => bail!(
+                "Unsupported encoding: {} (expected auto, hex, base64 or binary)",
+                name
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Hex => write!(f, "hex"),
+            Encoding::Base64 => write!(f, "base64"),
+            Encoding::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// Read raw input from a file path, or from stdin when the path is `-` or absent.
+pub fn read_source(path: Option<&str>) -> Result<Vec<u8>> {
+    match path {
+        None | Some("-") => {
+            let mut input = Vec::new();
+            io::stdin()
+                .read_to_end(&mut input)
+                .context("Failed to read input from stdin")?;
+            Ok(input)
+        }
+        Some(path) => fs::read(path).with_context(|| format!("Failed to read input file {}", path)),
+    }
+}
+
+fn is_hex_text(text: &str) -> bool {
+    let text = text.strip_prefix("0x").unwrap_or(text);
+    !text.is_empty() && text.len() % 2 == 0 && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_base64_text(text: &str) -> bool {
+    !text.is_empty()
+        && text.len() % 4 == 0
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=')
+}
+
+/// Decode input bytes using the given encoding, or detect it when `None`.
+///
+/// Text inputs are checked for hex first (hex is also valid base64), then
+/// base64; anything else is treated as a raw binary message.
+pub fn decode_input(raw: &[u8], encoding: Option<Encoding>) -> Result<(Vec<u8>, Encoding)> {
+    let text = std::str::from_utf8(raw).ok().map(|text| {
+        text.chars()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect::<String>()
+    });
+
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None => match text.as_deref() {
+            Some(text) if is_hex_text(text) => Encoding::Hex,
+            Some(text) if is_base64_text(text) && BASE64.decode(text).is_ok() => Encoding::Base64,
+            _ => Encoding::Binary,
+        },
+    };
+
+    let bytes = match encoding {
+        Encoding::Binary => raw.to_vec(),
+        Encoding::Hex => {
+            let text = text.context("Hex input is not valid UTF-8")?;
+            hex::decode(text.strip_prefix("0x").unwrap_or(&text))
+                .context("Failed to decode hex input")?
+        }
+        Encoding::Base64 => {
+            let text = text.context("Base64 input is not valid UTF-8")?;
+            BASE64
+                .decode(text)
+                .context("Failed to decode base64 input")?
+        }
+    };
+
+    if bytes.is_empty() {
+        bail!("Input is empty");
+    }
+
+    Ok((bytes, encoding))
+}
+
+/// Encode bytes as text for the JSON envelope.
+pub fn encode_text(bytes: &[u8], encoding: Encoding) -> Result<String> {
+    match encoding {
+        Encoding::Hex => Ok(hex::encode(bytes)),
+        Encoding::Base64 => Ok(BASE64.encode(bytes)),
+        Encoding::Binary => bail!("Binary output cannot be embedded as text"),
+    }
+}
+
+/// Write raw bytes to stdout, bypassing the JSON envelope.
+pub fn write_binary(bytes: &[u8]) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(bytes)
+        .context("Failed to write binary output")?;
+    stdout.flush().context("Failed to flush binary output")
+}
+
+/// Message bytes and their encoding from a `--hex` argument, or from a
+/// file/stdin in the given encoding (`auto` to detect it).
+pub fn message_bytes(
+    hex_arg: Option<&str>,
+    path: Option<&str>,
+    encoding: &str,
+) -> Result<(Vec<u8>, Encoding)> {
+    if let Some(hex_str) = hex_arg {
+        let bytes = hex::decode(hex_str.trim()).context("Failed to decode hex string")?;
+        return Ok((bytes, Encoding::Hex));
+    }
+
+    let raw = read_source(path)?;
+    decode_input(&raw, Encoding::parse(encoding)?)
+}
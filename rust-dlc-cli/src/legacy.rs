@@ -0,0 +1,751 @@
+//! Pre-1.0 (legacy v0) encodings of the offer, accept and sign messages.
+//!
+//! Before dlcspecs PR #163 the sub-objects of these messages were TLV records
+//! with their own types (`ContractInfoV0`, `OracleInfoV1`, `FundingInputV0`
+//! and so on) where rust-dlc now writes a BigSize variant, or nothing at all.
+//! Messages had no protocol version, offers had no temporary contract id (it
+//! was the SHA256 of the offer), funding input, oracle and witness counts
+//! were 2 bytes, and messages ended without a TLV stream. Leaf encodings such
+//! as payout points, rounding intervals and oracle announcements did not
+//! change.
+//!
+//! Both directions share one description of each layout: upgrading reads
+//! the legacy form and writes the current one, downgrading the reverse.
+
+use crate::codec::{decode_hex, read_wire_message_with_trailing};
+use crate::decode::ORACLE_ANNOUNCEMENT_TYPE;
+use crate::negotiation::{
+    Negotiation, NegotiationFieldsV0, NegotiationFieldsV1, NegotiationFieldsV2,
+};
+use crate::orders::PROTOCOL_VERSION;
+use crate::output_error;
+use crate::wire::{NodeMessage, Reader, Writer};
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::hashes::{sha256, Hash};
+use dlc_messages::{AcceptDlc, OfferDlc, SignDlc, ACCEPT_TYPE, OFFER_TYPE, SIGN_TYPE};
+use lightning::util::ser::Readable;
+use serde::Serialize;
+use serde_json::Value;
+
+const CONTRACT_INFO_V0: u64 = 55342;
+const CONTRACT_INFO_V1: u64 = 55344;
+const CONTRACT_DESCRIPTOR_V0: u64 = 42768;
+const CONTRACT_DESCRIPTOR_V1: u64 = 42784;
+const ORACLE_INFO_V0: u64 = 42770;
+const ORACLE_INFO_V1: u64 = 42786;
+const ORACLE_INFO_V2: u64 = 55340;
+const ORACLE_PARAMS_V0: u64 = 55338;
+const FUNDING_INPUT_V0: u64 = 42772;
+const CET_ADAPTOR_SIGNATURES_V0: u64 = 42774;
+const FUNDING_SIGNATURES_V0: u64 = 42776;
+const ROUNDING_INTERVALS_V0: u64 = 42788;
+const PAYOUT_FUNCTION_V0: u64 = 42790;
+const POLYNOMIAL_PAYOUT_CURVE_PIECE: u64 = 42792;
+const HYPERBOLA_PAYOUT_CURVE_PIECE: u64 = 42794;
+const OLD_HYPERBOLA_PAYOUT_CURVE_PIECE: u64 = 42796;
+
+/// Size of an ECDSA adaptor signature with its DLEQ proof.
+const ADAPTOR_SIGNATURE_LEN: usize = 162;
+/// Size of an `OracleParams` body: two u16 exponents and a bool.
+const ORACLE_PARAMS_LEN: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// Legacy v0 to the current encoding.
+    Upgrade,
+    /// Current encoding to legacy v0.
+    Downgrade,
+}
+
+struct Transcoder<'a> {
+    r: Reader<'a>,
+    w: Writer,
+    direction: Direction,
+    /// Legacy type of the enclosing TLV: read when upgrading, and chosen by
+    /// the body when downgrading.
+    legacy_type: u64,
+}
+
+impl<'a> Transcoder<'a> {
+    fn new(bytes: &'a [u8], direction: Direction) -> Self {
+        Transcoder {
+            r: Reader::new(bytes),
+            w: Writer::default(),
+            direction,
+            legacy_type: 0,
+        }
+    }
+
+    fn upgrading(&self) -> bool {
+        self.direction == Direction::Upgrade
+    }
+
+    /// A fixed-size field, the same in both encodings.
+    fn copy(&mut self, n: usize, name: &str) -> Result<()> {
+        let bytes = self.r.take(n, name)?;
+        self.w.bytes(bytes);
+        Ok(())
+    }
+
+    fn bigsize(&mut self, name: &str) -> Result<u64> {
+        let value = self.r.bigsize(name)?;
+        self.w.bigsize(value);
+        Ok(value)
+    }
+
+    /// A script with its 2-byte length, the same in both encodings.
+    fn script(&mut self, name: &str) -> Result<()> {
+        let len = self.r.u16(name)?;
+        self.w.u16(len);
+        self.copy(len as usize, name)
+    }
+
+    /// A count or length: 2 bytes in the legacy encoding, a BigSize now.
+    fn count(&mut self, name: &str) -> Result<u64> {
+        if self.upgrading() {
+            let value = self.r.u16(name)? as u64;
+            self.w.bigsize(value);
+            Ok(value)
+        } else {
+            let value = self.r.bigsize(name)?;
+            let legacy = u16::try_from(value)
+                .map_err(|_| anyhow!("{} {} does not fit a legacy 2-byte count", name, value))?;
+            self.w.u16(legacy);
+            Ok(value)
+        }
+    }
+
+    /// Bytes prefixed with their length as a [`count`](Self::count).
+    fn counted_bytes(&mut self, name: &str) -> Result<()> {
+        let len = self.count(name)?;
+        self.copy(usize::try_from(len)?, name)
+    }
+
+    /// The optional-field marker of the current encoding.
+    fn flag(&mut self, name: &str) -> Result<bool> {
+        match self.r.u8(name)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => bail!("Invalid {} marker {}", name, other),
+        }
+    }
+
+    /// An embedded TLV record, the same in both encodings.
+    fn tlv(&mut self, name: &str, expected_type: u64) -> Result<()> {
+        let found = self.r.bigsize(name)?;
+        if found != expected_type {
+            bail!("Expected {} type {}, got {}", name, expected_type, found);
+        }
+        self.w.bigsize(found);
+        self.w.var_bytes(self.r.var_bytes(name)?);
+        Ok(())
+    }
+
+    /// Index of the next entry of `variants` (legacy TLV type, current
+    /// variant), without consuming it.
+    fn peek_variant(&self, name: &str, variants: &[(u64, u64)]) -> Result<usize> {
+        let found = Reader::new(self.r.remaining()).bigsize(name)?;
+        variants
+            .iter()
+            .position(|(legacy, current)| {
+                found == if self.upgrading() { *legacy } else { *current }
+            })
+            .ok_or_else(|| {
+                if self.upgrading() {
+                    anyhow!("Unknown legacy {} type {}", name, found)
+                } else {
+                    anyhow!("Unknown {} variant {}", name, found)
+                }
+            })
+    }
+
+    /// A sub-object that the legacy encoding wraps in a TLV record of one of
+    /// `legacy_types` (the first unless the body picks another), and that is
+    /// preceded by the BigSize `variant`, if any, in the current encoding.
+    fn nested(
+        &mut self,
+        name: &str,
+        legacy_types: &[u64],
+        variant: Option<u64>,
+        body: impl FnOnce(&mut Transcoder<'a>) -> Result<()>,
+    ) -> Result<()> {
+        match self.direction {
+            Direction::Upgrade => {
+                let found = self.r.bigsize(name)?;
+                if !legacy_types.contains(&found) {
+                    bail!(
+                        "Expected legacy {} type {:?}, got {}",
+                        name,
+                        legacy_types,
+                        found
+                    );
+                }
+                let value = self.r.var_bytes(name)?;
+                let mut inner = Transcoder::new(value, self.direction);
+                inner.legacy_type = found;
+                body(&mut inner).with_context(|| format!("Failed to upgrade {}", name))?;
+                if !inner.r.is_empty() {
+                    bail!(
+                        "{} unread bytes in legacy {}",
+                        value.len() - inner.r.position(),
+                        name
+                    );
+                }
+                if let Some(variant) = variant {
+                    self.w.bigsize(variant);
+                }
+                self.w.bytes(&inner.w.into_bytes());
+            }
+            Direction::Downgrade => {
+                if let Some(variant) = variant {
+                    let found = self.r.bigsize(name)?;
+                    if found != variant {
+                        bail!("Expected {} variant {}, got {}", name, variant, found);
+                    }
+                }
+                let mut inner = Transcoder::new(self.r.remaining(), self.direction);
+                inner.legacy_type = legacy_types[0];
+                body(&mut inner).with_context(|| format!("Failed to downgrade {}", name))?;
+                self.r.take(inner.r.position(), name)?;
+                self.w.bigsize(inner.legacy_type);
+                self.w.var_bytes(&inner.w.into_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn rounding_intervals(t: &mut Transcoder) -> Result<()> {
+    for _ in 0..t.bigsize("roundingIntervals")? {
+        t.copy(16, "roundingInterval")?;
+    }
+    Ok(())
+}
+
+fn payout_curve_piece(t: &mut Transcoder) -> Result<()> {
+    if t.upgrading()
+        && Reader::new(t.r.remaining()).bigsize("payoutCurvePiece")?
+            == OLD_HYPERBOLA_PAYOUT_CURVE_PIECE
+    {
+        bail!(
+            "Hyperbola pieces of type {} predate the f64 encoding and cannot be upgraded",
+            OLD_HYPERBOLA_PAYOUT_CURVE_PIECE
+        );
+    }
+    let variants = [
+        (POLYNOMIAL_PAYOUT_CURVE_PIECE, 0),
+        (HYPERBOLA_PAYOUT_CURVE_PIECE, 1),
+    ];
+    match t.peek_variant("payoutCurvePiece", &variants)? {
+        0 => t.nested(
+            "payoutCurvePiece",
+            &[POLYNOMIAL_PAYOUT_CURVE_PIECE],
+            Some(0),
+            |t| {
+                for _ in 0..t.bigsize("payoutPoints")? {
+                    t.copy(18, "payoutPoint")?;
+                }
+                Ok(())
+            },
+        ),
+        _ => t.nested(
+            "payoutCurvePiece",
+            &[HYPERBOLA_PAYOUT_CURVE_PIECE],
+            Some(1),
+            // usePositivePiece, then translateOutcome, translatePayout, a, b,
+            // c and d as f64.
+            |t| t.copy(1 + 6 * 8, "hyperbola"),
+        ),
+    }
+}
+
+fn payout_function(t: &mut Transcoder) -> Result<()> {
+    for _ in 0..t.bigsize("payoutFunctionPieces")? {
+        t.copy(18, "endPoint")?;
+        payout_curve_piece(t)?;
+    }
+    t.copy(18, "lastEndpoint")
+}
+
+fn contract_descriptor(t: &mut Transcoder) -> Result<()> {
+    let variants = [(CONTRACT_DESCRIPTOR_V0, 0), (CONTRACT_DESCRIPTOR_V1, 1)];
+    match t.peek_variant("contractDescriptor", &variants)? {
+        0 => t.nested(
+            "contractDescriptor",
+            &[CONTRACT_DESCRIPTOR_V0],
+            Some(0),
+            |t| {
+                for _ in 0..t.bigsize("payouts")? {
+                    let len = t.bigsize("outcome")?;
+                    t.copy(usize::try_from(len)?, "outcome")?;
+                    t.copy(8, "offerPayout")?;
+                }
+                Ok(())
+            },
+        ),
+        _ => t.nested(
+            "contractDescriptor",
+            &[CONTRACT_DESCRIPTOR_V1],
+            Some(1),
+            |t| {
+                t.copy(2, "numDigits")?;
+                t.nested(
+                    "payoutFunction",
+                    &[PAYOUT_FUNCTION_V0],
+                    None,
+                    payout_function,
+                )?;
+                t.nested(
+                    "roundingIntervals",
+                    &[ROUNDING_INTERVALS_V0],
+                    None,
+                    rounding_intervals,
+                )
+            },
+        ),
+    }
+}
+
+/// Multi-oracle info. Legacy `OracleInfoV2` is `OracleInfoV1` followed by
+/// an `OracleParamsV0` record; the current encoding has an optional field.
+fn multi_oracle_info(t: &mut Transcoder) -> Result<()> {
+    t.copy(2, "threshold")?;
+    for _ in 0..t.count("oracleAnnouncements")? {
+        t.tlv("oracleAnnouncement", ORACLE_ANNOUNCEMENT_TYPE as u64)?;
+    }
+    let has_params = if t.upgrading() {
+        let has_params = t.legacy_type == ORACLE_INFO_V2;
+        t.w.u8(has_params as u8);
+        has_params
+    } else {
+        let has_params = t.flag("oracleParams")?;
+        if has_params {
+            t.legacy_type = ORACLE_INFO_V2;
+        }
+        has_params
+    };
+    if has_params {
+        t.nested("oracleParams", &[ORACLE_PARAMS_V0], None, |t| {
+            t.copy(ORACLE_PARAMS_LEN, "oracleParams")
+        })?;
+    }
+    Ok(())
+}
+
+fn oracle_info(t: &mut Transcoder) -> Result<()> {
+    let variants = [
+        (ORACLE_INFO_V0, 0),
+        (ORACLE_INFO_V1, 1),
+        (ORACLE_INFO_V2, 1),
+    ];
+    match t.peek_variant("oracleInfo", &variants)? {
+        0 => t.nested("oracleInfo", &[ORACLE_INFO_V0], Some(0), |t| {
+            t.tlv("oracleAnnouncement", ORACLE_ANNOUNCEMENT_TYPE as u64)
+        }),
+        _ => t.nested(
+            "oracleInfo",
+            &[ORACLE_INFO_V1, ORACLE_INFO_V2],
+            Some(1),
+            multi_oracle_info,
+        ),
+    }
+}
+
+fn contract_info(t: &mut Transcoder) -> Result<()> {
+    let variants = [(CONTRACT_INFO_V0, 0), (CONTRACT_INFO_V1, 1)];
+    match t.peek_variant("contractInfo", &variants)? {
+        0 => t.nested("contractInfo", &[CONTRACT_INFO_V0], Some(0), |t| {
+            t.copy(8, "totalCollateral")?;
+            contract_descriptor(t)?;
+            oracle_info(t)
+        }),
+        _ => t.nested("contractInfo", &[CONTRACT_INFO_V1], Some(1), |t| {
+            t.copy(8, "totalCollateral")?;
+            for _ in 0..t.bigsize("contractInfos")? {
+                contract_descriptor(t)?;
+                oracle_info(t)?;
+            }
+            Ok(())
+        }),
+    }
+}
+
+fn funding_input(t: &mut Transcoder) -> Result<()> {
+    t.nested("fundingInput", &[FUNDING_INPUT_V0], None, |t| {
+        t.copy(8, "inputSerialId")?;
+        t.counted_bytes("prevTx")?;
+        t.copy(4, "prevTxVout")?;
+        t.copy(4, "sequence")?;
+        t.copy(2, "maxWitnessLen")?;
+        t.script("redeemScript")?;
+        if t.upgrading() {
+            t.w.u8(0);
+        } else if t.flag("dlcInput")? {
+            bail!("DLC inputs have no legacy encoding");
+        }
+        Ok(())
+    })
+}
+
+fn funding_inputs(t: &mut Transcoder) -> Result<()> {
+    for index in 0..t.count("fundingInputs")? {
+        funding_input(t).with_context(|| format!("Funding input {}", index))?;
+    }
+    Ok(())
+}
+
+fn cet_adaptor_signatures(t: &mut Transcoder) -> Result<()> {
+    t.nested(
+        "cetAdaptorSignatures",
+        &[CET_ADAPTOR_SIGNATURES_V0],
+        None,
+        |t| {
+            for _ in 0..t.bigsize("ecdsaAdaptorSignatures")? {
+                t.copy(ADAPTOR_SIGNATURE_LEN, "ecdsaAdaptorSignature")?;
+            }
+            Ok(())
+        },
+    )
+}
+
+fn funding_signatures(t: &mut Transcoder) -> Result<()> {
+    t.nested("fundingSignatures", &[FUNDING_SIGNATURES_V0], None, |t| {
+        for _ in 0..t.count("fundingSignatures")? {
+            for _ in 0..t.count("witnessElements")? {
+                t.counted_bytes("witness")?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Legacy accepts end after the refund signature or carry a
+/// `NegotiationFieldsV0/V1/V2` record; current ones have an optional field.
+fn negotiation_fields(t: &mut Transcoder) -> Result<()> {
+    if t.upgrading() {
+        if t.r.is_empty() {
+            t.w.u8(0);
+            return Ok(());
+        }
+        let legacy_type = Reader::new(t.r.remaining()).bigsize("negotiationFields")?;
+        let negotiation: Option<Negotiation> = if legacy_type == NegotiationFieldsV0::TYPE {
+            NegotiationFieldsV0::read(&mut t.r)?;
+            None
+        } else if legacy_type == NegotiationFieldsV1::TYPE {
+            Some(NegotiationFieldsV1::read(&mut t.r)?.into())
+        } else if legacy_type == NegotiationFieldsV2::TYPE {
+            Some(NegotiationFieldsV2::read(&mut t.r)?.into())
+        } else {
+            bail!("Unknown legacy negotiation fields type {}", legacy_type);
+        };
+        match negotiation {
+            Some(negotiation) => {
+                t.w.u8(1);
+                negotiation.write(&mut t.w);
+            }
+            None => t.w.u8(0),
+        }
+        return Ok(());
+    }
+
+    if !t.flag("negotiationFields")? {
+        return Ok(());
+    }
+    match Negotiation::read(&mut t.r)? {
+        Negotiation::Single { rounding_intervals } => {
+            t.w.bytes(&NegotiationFieldsV1 { rounding_intervals }.encode())
+        }
+        Negotiation::Disjoint { negotiation_fields } => {
+            let negotiation_fields = negotiation_fields
+                .into_iter()
+                .map(|fields| match fields {
+                    Negotiation::Single { rounding_intervals } => {
+                        Ok(NegotiationFieldsV1 { rounding_intervals })
+                    }
+                    Negotiation::Disjoint { .. } => {
+                        bail!("Nested disjoint negotiation fields have no legacy encoding")
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
+            t.w.bytes(&NegotiationFieldsV2 { negotiation_fields }.encode());
+        }
+    }
+    Ok(())
+}
+
+fn offer_body(t: &mut Transcoder) -> Result<()> {
+    contract_info(t)?;
+    t.copy(33, "fundingPubkey")?;
+    t.script("payoutSpk")?;
+    t.copy(8, "payoutSerialId")?;
+    t.copy(8, "offerCollateral")?;
+    funding_inputs(t)?;
+    t.script("changeSpk")?;
+    t.copy(8, "changeSerialId")?;
+    t.copy(8, "fundOutputSerialId")?;
+    t.copy(8, "feeRatePerVb")?;
+    t.copy(4, "cetLocktime")?;
+    t.copy(4, "refundLocktime")
+}
+
+fn accept_body(t: &mut Transcoder) -> Result<()> {
+    t.copy(8, "acceptCollateral")?;
+    t.copy(33, "fundingPubkey")?;
+    t.script("payoutSpk")?;
+    t.copy(8, "payoutSerialId")?;
+    funding_inputs(t)?;
+    t.script("changeSpk")?;
+    t.copy(8, "changeSerialId")?;
+    cet_adaptor_signatures(t)?;
+    t.copy(64, "refundSignature")?;
+    negotiation_fields(t)
+}
+
+fn sign_body(t: &mut Transcoder) -> Result<()> {
+    cet_adaptor_signatures(t)?;
+    t.copy(64, "refundSignature")?;
+    funding_signatures(t)
+}
+
+/// The result of converting a message between the two encodings.
+pub struct Transcoded {
+    /// `offer`, `accept` or `sign`.
+    pub message_type: &'static str,
+    pub bytes: Vec<u8>,
+    /// Bytes of the input the transcoder read, message type included.
+    pub consumed: usize,
+    pub warnings: Vec<String>,
+}
+
+fn message_type(bytes: &[u8]) -> Result<(u16, &'static str)> {
+    if bytes.len() < 2 {
+        bail!("Input is too short to contain a message type");
+    }
+    let msg_type = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let name = match msg_type {
+        OFFER_TYPE => "offer",
+        ACCEPT_TYPE => "accept",
+        SIGN_TYPE => "sign",
+        _ => bail!(
+            "Message type {} is not an offer, accept or sign; only those have a legacy encoding",
+            msg_type
+        ),
+    };
+    Ok((msg_type, name))
+}
+
+/// Whether an offer, accept or sign has the shape of the legacy encoding:
+/// an offer whose contract info is a TLV record after the chain hash, or an
+/// accept or sign without a protocol version in front of its contract id.
+pub fn looks_legacy(bytes: &[u8]) -> bool {
+    let Ok((msg_type, _)) = message_type(bytes) else {
+        return false;
+    };
+    let mut peek = Reader::new(&bytes[2..]);
+    if msg_type == OFFER_TYPE {
+        return peek.take(33, "contractFlags and chainHash").is_ok()
+            && matches!(
+                peek.bigsize("contractInfo"),
+                Ok(CONTRACT_INFO_V0 | CONTRACT_INFO_V1)
+            );
+    }
+    !matches!(peek.u32("protocolVersion"), Ok(1..=10))
+}
+
+/// Convert a legacy v0 offer, accept or sign to the current encoding.
+pub fn upgrade(bytes: &[u8]) -> Result<Transcoded> {
+    let (msg_type, name) = message_type(bytes)?;
+    let mut t = Transcoder::new(&bytes[2..], Direction::Upgrade);
+    t.w.u16(msg_type);
+    t.w.u32(PROTOCOL_VERSION);
+    let mut warnings = Vec::new();
+    match msg_type {
+        OFFER_TYPE => {
+            // Same heuristic as node-dlc: a current offer starts with a small
+            // protocol version, a legacy one with the flags and chain hash.
+            let mut peek = Reader::new(t.r.remaining());
+            if matches!(
+                (peek.u32("protocolVersion"), peek.u8("contractFlags")),
+                (Ok(1..=10), Ok(0))
+            ) {
+                bail!("Offer starts with a protocol version; it is not in the legacy format");
+            }
+            t.copy(1, "contractFlags")?;
+            t.copy(32, "chainHash")?;
+            let temporary_contract_id = sha256::Hash::hash(bytes).to_byte_array();
+            t.w.bytes(&temporary_contract_id);
+            warnings.push(format!(
+                "Legacy offers carry no temporaryContractId; using the SHA256 of the offer, {}",
+                hex::encode(temporary_contract_id)
+            ));
+            offer_body(&mut t)
+        }
+        ACCEPT_TYPE => t
+            .copy(32, "temporaryContractId")
+            .and_then(|_| accept_body(&mut t)),
+        _ => t.copy(32, "contractId").and_then(|_| sign_body(&mut t)),
+    }
+    .with_context(|| format!("Failed to upgrade legacy {}", name))?;
+    if !t.r.is_empty() {
+        bail!(
+            "{} unread bytes after the legacy {}",
+            bytes.len() - 2 - t.r.position(),
+            name
+        );
+    }
+    Ok(Transcoded {
+        message_type: name,
+        consumed: 2 + t.r.position(),
+        bytes: t.w.into_bytes(),
+        warnings,
+    })
+}
+
+/// Convert a current offer, accept or sign to the legacy v0 encoding.
+pub fn downgrade(bytes: &[u8]) -> Result<Transcoded> {
+    let (msg_type, name) = message_type(bytes)?;
+    let mut t = Transcoder::new(&bytes[2..], Direction::Downgrade);
+    t.w.u16(msg_type);
+    let mut warnings = Vec::new();
+    let protocol_version = t.r.u32("protocolVersion")?;
+    if protocol_version != PROTOCOL_VERSION {
+        warnings.push(format!(
+            "Dropped protocolVersion {}; legacy messages predate versioning",
+            protocol_version
+        ));
+    }
+    let mut temporary_contract_id = None;
+    match msg_type {
+        OFFER_TYPE => t
+            .copy(1, "contractFlags")
+            .and_then(|_| t.copy(32, "chainHash"))
+            .and_then(|_| {
+                temporary_contract_id = Some(t.r.array::<32>("temporaryContractId")?);
+                offer_body(&mut t)
+            }),
+        ACCEPT_TYPE => t
+            .copy(32, "temporaryContractId")
+            .and_then(|_| accept_body(&mut t)),
+        _ => t.copy(32, "contractId").and_then(|_| sign_body(&mut t)),
+    }
+    .with_context(|| format!("Failed to downgrade {}", name))?;
+    let consumed = 2 + t.r.position();
+    if !t.r.is_empty() {
+        warnings.push(format!(
+            "Dropped {} bytes of TLV stream after the {}; legacy messages cannot carry one",
+            t.r.remaining().len(),
+            name
+        ));
+    }
+
+    let legacy = t.w.into_bytes();
+    if let Some(temporary_contract_id) = temporary_contract_id {
+        let derived = sha256::Hash::hash(&legacy).to_byte_array();
+        if derived != temporary_contract_id {
+            warnings.push(format!(
+                "Dropped temporaryContractId {}; legacy readers derive {}",
+                hex::encode(temporary_contract_id),
+                hex::encode(derived)
+            ));
+        }
+    }
+    Ok(Transcoded {
+        message_type: name,
+        bytes: legacy,
+        consumed,
+        warnings,
+    })
+}
+
+fn parse_current<T: Readable + Serialize>(
+    bytes: &[u8],
+    msg_type: u16,
+    name: &str,
+) -> Result<Value> {
+    let (message, trailing) = read_wire_message_with_trailing::<T>(bytes, msg_type, name)?;
+    if !trailing.is_empty() {
+        bail!(
+            "{} unread bytes after the upgraded {}",
+            trailing.len(),
+            name
+        );
+    }
+    serde_json::to_value(&message).with_context(|| format!("Failed to convert {} to JSON", name))
+}
+
+/// The JSON of an upgraded message, checking that rust-dlc reads all of it.
+pub fn upgraded_json(upgraded: &Transcoded) -> Result<Value> {
+    let (msg_type, _) = message_type(&upgraded.bytes)?;
+    match msg_type {
+        OFFER_TYPE => parse_current::<OfferDlc>(&upgraded.bytes, msg_type, "OfferDlc"),
+        ACCEPT_TYPE => parse_current::<AcceptDlc>(&upgraded.bytes, msg_type, "AcceptDlc"),
+        _ => parse_current::<SignDlc>(&upgraded.bytes, msg_type, "SignDlc"),
+    }
+}
+
+fn output(
+    direction: &str,
+    transcoded: &Transcoded,
+    message: Option<Value>,
+    summary: &str,
+) -> Result<()> {
+    let mut data = serde_json::json!({
+        "type": transcoded.message_type,
+        "hex": hex::encode(&transcoded.bytes),
+        "warnings": transcoded.warnings,
+    });
+    if let Some(message) = message {
+        data["message"] = message;
+    }
+    crate::output_success_with_data(direction, &data, summary)
+}
+
+/// Upgrade a legacy message and show it as rust-dlc reads it.
+pub fn upgrade_legacy(hex_str: &str) -> Result<()> {
+    let bytes = match decode_hex(hex_str) {
+        Ok(bytes) => bytes,
+        Err(e) => return output_error(&e.to_string()),
+    };
+    let upgraded = match upgrade(&bytes) {
+        Ok(upgraded) => upgraded,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    let message = match upgraded_json(&upgraded) {
+        Ok(message) => message,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    output(
+        "legacy-upgrade",
+        &upgraded,
+        Some(message),
+        &format!(
+            "Upgraded legacy {} ({} -> {} bytes)",
+            upgraded.message_type,
+            bytes.len(),
+            upgraded.bytes.len()
+        ),
+    )
+}
+
+/// Downgrade a current message to the legacy encoding.
+pub fn downgrade_legacy(hex_str: &str) -> Result<()> {
+    let bytes = match decode_hex(hex_str) {
+        Ok(bytes) => bytes,
+        Err(e) => return output_error(&e.to_string()),
+    };
+    let downgraded = match downgrade(&bytes) {
+        Ok(downgraded) => downgraded,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    output(
+        "legacy-downgrade",
+        &downgraded,
+        None,
+        &format!(
+            "Downgraded {} to the legacy format ({} -> {} bytes)",
+            downgraded.message_type,
+            bytes.len(),
+            downgraded.bytes.len()
+        ),
+    )
+}
@@ -0,0 +1,47 @@
+//! Locktimes and unix timestamps in human-readable form.
+
+use serde_json::{json, Value};
+
+/// Locktimes at or above this are unix timestamps, below are block heights.
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// Format a unix timestamp as an ISO 8601 UTC date and time.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+/// A locktime with its unit, and its date when it is a timestamp.
+pub fn locktime_json(locktime: u32) -> Value {
+    if locktime < LOCKTIME_THRESHOLD {
+        json!({
+            "locktime": locktime,
+            "unit": "blockHeight",
+        })
+    } else {
+        json!({
+            "locktime": locktime,
+            "unit": "timestamp",
+            "date": format_timestamp(u64::from(locktime)),
+        })
+    }
+}
@@ -1,5 +1,35 @@
-use anyhow::{Context, Result};
-use clap::{Arg, Command};
+mod batch;
+mod cets;
+mod codec;
+mod decode;
+mod diff;
+mod differential;
+mod dlc_input;
+mod estimate;
+mod explain;
+mod flow;
+mod fuzz;
+mod generate;
+mod input;
+mod legacy;
+mod locktime;
+mod multi_oracle;
+mod negotiation;
+mod network;
+mod oracles;
+mod orders;
+mod payout;
+mod psbt;
+mod refund;
+mod report;
+mod rng;
+mod store;
+mod transactions;
+mod vectors;
+mod wire;
+
+use anyhow::{bail, Context, Result};
+use clap::{Arg, ArgAction, Command};
 use dlc_messages::{AcceptDlc, OfferDlc, SignDlc};
 use dlc_messages::oracle_msgs::{OracleAnnouncement, OracleAttestation, OracleEvent, OracleInfo, EventDescriptor, EnumEventDescriptor, DigitDecompositionEventDescriptor};
 use dlc_messages::contract_msgs::{ContractInfo, ContractDescriptor};
@@ -27,19 +57,73 @@ fn main() -> Result<()> {
                         .short('t')
                         .long("type")
                         .value_name("MESSAGE_TYPE")
-                        .help("Message type: offer, accept, sign, oracle-announcement, oracle-attestation, contract-info, oracle-info, oracle-event")
+                        .help("Message type: offer, accept, sign, oracle-announcement, oracle-attestation, contract-info, oracle-info, oracle-event, batch-funding-group, order-offer, order-accept, order-metadata, order-irc-info, order-position-info, negotiation-fields-v0, negotiation-fields-v1, negotiation-fields-v2, dlc-transactions, dlc-ids, dlc-info, oracle-event-container, oracle-identifier")
                         .required(true),
+                )
+                .arg(
+                    Arg::new("encoding")
+                        .short('e')
+                        .long("encoding")
+                        .value_name("ENCODING")
+                        .help("Output encoding: hex, base64 or binary (raw bytes written to stdout without the JSON envelope)")
+                        .default_value("hex")
+                        .required(false),
                 ),
         )
         .subcommand(
             Command::new("deserialize")
-                .about("Deserialize hex to JSON using rust-dlc")
+                .about("Deserialize hex, base64 or binary to JSON using rust-dlc")
                 .arg(
                     Arg::new("hex")
                         .long("hex")
                         .value_name("HEX_STRING")
                         .help("Hex string to deserialize")
-                        .required(true),
+                        .conflicts_with("input")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("input")
+                        .short('f')
+                        .long("input")
+                        .value_name("PATH")
+                        .help("File to read the message from, '-' for stdin (the default when --hex is not given)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("encoding")
+                        .short('e')
+                        .long("encoding")
+                        .value_name("ENCODING")
+                        .help("Input encoding: auto, hex, base64 or binary")
+                        .default_value("auto")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("type")
+                        .short('t')
+                        .long("type")
+                        .value_name("MESSAGE_TYPE")
+                        .help("Decode as this type instead of detecting it: offer, accept, sign, oracle-announcement, oracle-attestation, oracle-event, oracle-info, contract-info, contract-descriptor, funding-input, cet-adaptor-signatures, funding-signatures, payout-function, rounding-intervals, negotiation-fields, batch-funding-group, order-offer, order-accept, order-metadata, order-irc-info, order-position-info, negotiation-fields-v0, negotiation-fields-v1, negotiation-fields-v2, dlc-transactions, dlc-ids, dlc-info, oracle-event-container, oracle-identifier, legacy-offer, legacy-accept, legacy-sign")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("strict")
+                        .long("strict")
+                        .help("Reject input with trailing bytes after the decoded message")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("strict-version")
+                        .long("strict-version")
+                        .help("Reject messages with an unknown protocol version or reserved contract flags set, instead of warning")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("network")
+                        .long("network")
+                        .value_name("NETWORK")
+                        .help("Network the message should be for (mainnet, testnet, signet or regtest); flags offers for another chain and renders accept addresses")
+                        .required(false),
                 ),
         )
         .subcommand(
@@ -50,8 +134,395 @@ fn main() -> Result<()> {
                         .short('t')
                         .long("type")
                         .value_name("MESSAGE_TYPE")
-                        .help("Message type: offer, accept, sign, oracle-announcement, oracle-attestation, contract-info, oracle-info, oracle-event")
+                        .help("Message type: offer, accept, sign, oracle-announcement, oracle-attestation, contract-info, oracle-info, oracle-event, batch-funding-group, order-offer, order-accept, order-metadata, order-irc-info, order-position-info, negotiation-fields-v0, negotiation-fields-v1, negotiation-fields-v2, dlc-transactions, dlc-ids, dlc-info, oracle-event-container, oracle-identifier")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("Print an annotated field-by-field breakdown of a message's wire encoding")
+                .arg(
+                    Arg::new("hex")
+                        .long("hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex string to explain")
+                        .conflicts_with("input")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("input")
+                        .short('f')
+                        .long("input")
+                        .value_name("PATH")
+                        .help("File to read the message from, '-' for stdin (the default when --hex is not given)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("encoding")
+                        .short('e')
+                        .long("encoding")
+                        .value_name("ENCODING")
+                        .help("Input encoding: auto, hex, base64 or binary")
+                        .default_value("auto")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("type")
+                        .short('t')
+                        .long("type")
+                        .value_name("MESSAGE_TYPE")
+                        .help("Walk the bytes as this type instead of detecting it (same types as deserialize --type)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Output format: json or human")
+                        .default_value("json")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare two messages structurally and locate the first differing wire byte")
+                .arg(
+                    Arg::new("left")
+                        .long("left")
+                        .value_name("HEX_OR_JSON")
+                        .help("First message, as hex or as JSON (requires --type)")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("right")
+                        .long("right")
+                        .value_name("HEX_OR_JSON")
+                        .help("Second message, as hex or as JSON (requires --type)")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("type")
+                        .short('t')
+                        .long("type")
+                        .value_name("MESSAGE_TYPE")
+                        .help("Message type of both sides (detected from hex when omitted)")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("fuzz-corpus")
+                .about("Mutate test-vector messages deterministically and check the readers for panics and round-trip mismatches")
+                .arg(
+                    Arg::new("corpus")
+                        .short('c')
+                        .long("corpus")
+                        .value_name("DIR")
+                        .help("Directory of JSON test vectors or .hex files to take seed messages from")
+                        .default_value("../packages/messaging/test_vectors")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .short('s')
+                        .long("seed")
+                        .value_name("SEED")
+                        .help("PRNG seed; the same seed and corpus always give the same inputs")
+                        .default_value("0")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("iterations")
+                        .short('n')
+                        .long("iterations")
+                        .value_name("COUNT")
+                        .help("Number of mutated inputs to generate")
+                        .default_value("10000")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("max-findings")
+                        .long("max-findings")
+                        .value_name("COUNT")
+                        .help("Maximum number of findings to include in the output")
+                        .default_value("50")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("DIR")
+                        .help("Directory to write failing inputs to as raw .bin files")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("differential")
+                .about("Compare an external decoder against rust-dlc on generated messages, shrinking disagreements")
+                .arg(
+                    Arg::new("command")
+                        .long("command")
+                        .value_name("SHELL_COMMAND")
+                        .help("Decoder command; reads {\"type\", \"hex\", \"json\"} on stdin and prints {\"hex\"} and/or {\"json\"}")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .short('s')
+                        .long("seed")
+                        .value_name("SEED")
+                        .help("PRNG seed; each case also reports its own seed for `generate`")
+                        .default_value("0")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("iterations")
+                        .short('n')
+                        .long("iterations")
+                        .value_name("COUNT")
+                        .help("Number of generated cases")
+                        .default_value("100")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("types")
+                        .long("types")
+                        .value_name("TYPES")
+                        .help("Comma-separated message types to generate (default: all)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("shrink-budget")
+                        .long("shrink-budget")
+                        .value_name("COUNT")
+                        .help("Maximum decoder runs spent shrinking each failing case")
+                        .default_value("200")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("max-failures")
+                        .long("max-failures")
+                        .value_name("COUNT")
+                        .help("Stop after this many failing cases")
+                        .default_value("10")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Generate random but protocol-valid messages from a seed, as hex and JSON")
+                .arg(
+                    Arg::new("type")
+                        .short('t')
+                        .long("type")
+                        .value_name("MESSAGE_TYPE")
+                        .help("offer, accept, sign, oracle-announcement, oracle-attestation, oracle-event, oracle-info, contract-info, contract-descriptor, or dlc for a consistent offer/accept/sign triple")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .short('s')
+                        .long("seed")
+                        .value_name("SEED")
+                        .help("Seed of the first message; message i uses seed + i")
+                        .default_value("0")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("count")
+                        .short('n')
+                        .long("count")
+                        .value_name("COUNT")
+                        .help("Number of messages to generate")
+                        .default_value("1")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("contract")
+                        .long("contract")
+                        .value_name("KIND")
+                        .help("Contract kind: enum, numeric, hyperbola or disjoint (random by default)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("oracles")
+                        .long("oracles")
+                        .value_name("COUNT")
+                        .help("Number of oracles (random by default)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("threshold")
+                        .long("threshold")
+                        .value_name("COUNT")
+                        .help("Number of oracles required to attest (random by default)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("sub-contracts")
+                        .long("sub-contracts")
+                        .value_name("COUNT")
+                        .help("Number of sub-contracts of a disjoint contract, each on its own event (2 by default)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("bounded-difference")
+                        .long("bounded-difference")
+                        .help("Give binary numeric multi-oracle events random oracleParams (allowed difference between oracles)")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("dlc-inputs")
+                        .long("dlc-inputs")
+                        .help("Fund the offer's first input from an earlier contract's funding output")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("network")
+                        .long("network")
+                        .value_name("NETWORK")
+                        .help("Network whose chain hash the offer carries: mainnet, testnet, signet or regtest")
+                        .default_value("regtest"),
+                ),
+        )
+        .subcommand(
+            Command::new("generate-vectors")
+                .about("Generate dlcspecs-format offer/accept/sign test vectors from scenario descriptions")
+                .arg(
+                    Arg::new("scenario")
+                        .long("scenario")
+                        .value_name("FILE")
+                        .help("JSON scenario or array of scenarios (name, seed, contract, oracles, threshold, dlcInputs, network), or - for stdin")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("DIR")
+                        .help("Write one <name>.json vector per scenario into this directory instead of printing them")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("build-dlc-input")
+                .about("Build a funding input that spends a prior contract's funding output (DLC input)")
+                .arg(
+                    Arg::new("fund-tx")
+                        .long("fund-tx")
+                        .value_name("HEX")
+                        .help("Funding transaction of the prior contract")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("local-pubkey")
+                        .long("local-pubkey")
+                        .value_name("PUBKEY")
+                        .help("Our funding pubkey in the prior contract")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("remote-pubkey")
+                        .long("remote-pubkey")
+                        .value_name("PUBKEY")
+                        .help("The counterparty's funding pubkey in the prior contract")
                         .required(true),
+                )
+                .arg(
+                    Arg::new("contract-id")
+                        .long("contract-id")
+                        .value_name("HEX")
+                        .help("Contract id of the prior contract")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("serial-id")
+                        .long("serial-id")
+                        .value_name("SERIAL_ID")
+                        .help("Input serial id")
+                        .default_value("0")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("sequence")
+                        .long("sequence")
+                        .value_name("SEQUENCE")
+                        .help("Input sequence number")
+                        .default_value("4294967295")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("validate-dlc-input")
+                .about("Check that a DLC input spends the funding output and keys of a prior contract")
+                .arg(
+                    Arg::new("input")
+                        .long("input")
+                        .value_name("HEX_OR_JSON")
+                        .help("Funding input as hex or JSON")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("prev-offer-hex")
+                        .long("prev-offer-hex")
+                        .value_name("HEX")
+                        .help("Offer message of the prior contract")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("prev-accept-hex")
+                        .long("prev-accept-hex")
+                        .value_name("HEX")
+                        .help("Accept message of the prior contract")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("build-batch-funding")
+                .about("Build one funding transaction for several offer/accept pairs and derive each contract's CETs from its own output")
+                .arg(
+                    Arg::new("input")
+                        .short('i')
+                        .long("input")
+                        .value_name("FILE")
+                        .help("JSON batch description {\"contracts\": [{\"offer\": HEX, \"accept\": HEX}], \"batchFundingGroups\": [HEX or JSON]}, or - for stdin")
+                        .default_value("-")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("apply-negotiation")
+                .about("Apply counter-proposed rounding intervals to an offer's numeric contracts and show how the payouts change")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX")
+                        .help("Offer message whose contract is being negotiated")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("negotiation")
+                        .long("negotiation")
+                        .value_name("HEX_OR_JSON")
+                        .help("Negotiation fields as JSON, or hex of an accept message, a NegotiationFieldsV0/V1/V2 TLV or the untyped encoding")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("inspect-store-record")
+                .about("Decode a value from node-dlc's LevelDB stores and check that it re-encodes to the same bytes")
+                .arg(
+                    Arg::new("value")
+                        .long("value")
+                        .value_name("HEX")
+                        .help("Stored value")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("key")
+                        .long("key")
+                        .value_name("HEX")
+                        .help("DLC store key; its prefix byte selects the record type instead of detecting it"),
                 ),
         )
         .subcommand(
@@ -71,7 +542,8 @@ fn main() -> Result<()> {
                         .short('i')
                         .long("event-id")
                         .value_name("EVENT_ID")
-                        .help("Event identifier")
+                        .help("Event identifier; repeat to announce one event per sub-contract of a disjoint contract")
+                        .action(ArgAction::Append)
                         .default_value("test-event-001")
                         .required(false),
                 )
@@ -105,11 +577,306 @@ fn main() -> Result<()> {
                         .required(true),
                 ),
         )
+        .subcommand(
+            Command::new("container-from")
+                .about("Bundle an oracle announcement and its attestation into an OracleEventContainer")
+                .arg(
+                    Arg::new("announcement")
+                        .long("announcement")
+                        .value_name("HEX")
+                        .help("Oracle announcement hex, e.g. from create-oracle-announcement")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("attestation")
+                        .long("attestation")
+                        .value_name("HEX")
+                        .help("Oracle attestation hex, e.g. from create-oracle-attestation")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("oracle-name")
+                        .long("oracle-name")
+                        .value_name("NAME")
+                        .help("Name of the oracle")
+                        .default_value(""),
+                )
+                .arg(
+                    Arg::new("uri")
+                        .long("uri")
+                        .value_name("URI")
+                        .help("Where the oracle publishes its events")
+                        .default_value(""),
+                )
+                .arg(
+                    Arg::new("outcome")
+                        .long("outcome")
+                        .value_name("OUTCOME")
+                        .help("Outcome to record instead of the one read from the attestation"),
+                ),
+        )
+        .subcommand(
+            Command::new("upgrade-legacy")
+                .about("Convert a pre-1.0 (legacy v0) offer, accept or sign to the current format")
+                .arg(
+                    Arg::new("hex")
+                        .long("hex")
+                        .value_name("HEX")
+                        .help("Legacy message hex")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("downgrade-legacy")
+                .about("Convert an offer, accept or sign to the pre-1.0 (legacy v0) format, for testing legacy readers")
+                .arg(
+                    Arg::new("hex")
+                        .long("hex")
+                        .value_name("HEX")
+                        .help("Current message hex")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("validate-flow")
+                .about("Validate accept and sign messages against the originating offer")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("accept-hex")
+                        .long("accept-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded AcceptDlc message answering the offer")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("sign-hex")
+                        .long("sign-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded SignDlc message answering the accept")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("contract-id")
+                .about("Compute the final contract id from the funding transaction of an offer/accept pair")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("accept-hex")
+                        .long("accept-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded AcceptDlc message answering the offer")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("build-transactions")
+                .about("Build the funding, refund and CET transactions of an offer/accept pair")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("accept-hex")
+                        .long("accept-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded AcceptDlc message answering the offer")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("funding-psbt")
+                .about("Build the funding transaction of an offer/accept pair as a BIP174 PSBT")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("accept-hex")
+                        .long("accept-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded AcceptDlc message answering the offer")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("key-origin")
+                        .long("key-origin")
+                        .value_name("PUBKEY=FINGERPRINT/PATH")
+                        .help("BIP32 origin of a signing key, added to the inputs and outputs it controls (repeatable)")
+                        .action(ArgAction::Append),
+                ),
+        )
+        .subcommand(
+            Command::new("finalize-funding")
+                .about("Combine signed funding PSBTs into the FundingSignatures of a SignDlc")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("accept-hex")
+                        .long("accept-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded AcceptDlc message answering the offer")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("psbt")
+                        .long("psbt")
+                        .value_name("PSBT")
+                        .help("Signed funding PSBT in base64 or hex (repeatable)")
+                        .required(true)
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("party")
+                        .long("party")
+                        .value_name("PARTY")
+                        .help("Party whose funding inputs to extract signatures for: offer or accept")
+                        .default_value("offer"),
+                ),
+        )
+        .subcommand(
+            Command::new("build-refund")
+                .about("Rebuild the refund transaction of an offer/accept pair")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("accept-hex")
+                        .long("accept-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded AcceptDlc message answering the offer")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("verify-refund-sig")
+                .about("Verify the refund signatures of accept and sign messages against the funding pubkeys")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("accept-hex")
+                        .long("accept-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded AcceptDlc message answering the offer")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("sign-hex")
+                        .long("sign-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded SignDlc message answering the accept")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("report")
+                .about("Summarize collateral, payouts, liquidation outcomes, fees and dates of an offer for each party")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("accept-hex")
+                        .long("accept-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded AcceptDlc message, for the accepting party's actual fees")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("estimate")
+                .about("Estimate CET and adaptor signature counts and AcceptDlc/SignDlc sizes without signing")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message whose contract info to estimate")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("contract-info")
+                        .long("contract-info")
+                        .value_name("JSON")
+                        .help("ContractInfo JSON to estimate instead of an offer")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("funding-inputs")
+                        .long("funding-inputs")
+                        .value_name("COUNT")
+                        .help("P2WPKH funding inputs per party (the offer's own count is used when given; default 1)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("max-message-size")
+                        .long("max-message-size")
+                        .value_name("BYTES")
+                        .help("Transport message size limit to check against (default 65535)")
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("multi-oracle-cets")
+                .about("Show the bounded-difference prefix combinations of a multi-oracle numeric contract per payout interval")
+                .arg(
+                    Arg::new("offer-hex")
+                        .long("offer-hex")
+                        .value_name("HEX_STRING")
+                        .help("Hex-encoded OfferDlc message")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("contract-info")
+                        .long("contract-info")
+                        .value_name("JSON")
+                        .help("ContractInfo JSON instead of an offer")
+                        .required(false),
+                ),
+        )
         .get_matches();
 
     match matches.subcommand() {
         Some(("serialize", sub_matches)) => {
             let msg_type = sub_matches.get_one::<String>("type").unwrap();
+            let encoding = match input::Encoding::parse(sub_matches.get_one::<String>("encoding").unwrap()) {
+                Ok(Some(encoding)) => encoding,
+                Ok(None) => return output_error("Output encoding must be hex, base64 or binary"),
+                Err(e) => return output_error(&e.to_string()),
+            };
 
             // Read JSON from stdin
             let mut input = String::new();
@@ -118,11 +885,30 @@ fn main() -> Result<()> {
             let json: Value = serde_json::from_str(&input)
                 .context("Failed to parse input as JSON")?;
 
-            serialize_message(msg_type, &json)
+            serialize_message(msg_type, &json, encoding)
         }
         Some(("deserialize", sub_matches)) => {
-            let hex_str = sub_matches.get_one::<String>("hex").unwrap();
-            deserialize_hex(hex_str)
+            let type_hint = sub_matches.get_one::<String>("type").map(String::as_str);
+            let strict = sub_matches.get_flag("strict");
+            let strict_version = sub_matches.get_flag("strict-version");
+            let network = match sub_matches.get_one::<String>("network").map(|name| network::parse_network(name)).transpose() {
+                Ok(network) => network,
+                Err(e) => return output_error(&e.to_string()),
+            };
+
+            if let Some(hex_str) = sub_matches.get_one::<String>("hex") {
+                return deserialize_hex(hex_str, type_hint, strict, strict_version, network);
+            }
+
+            let encoding = match input::Encoding::parse(sub_matches.get_one::<String>("encoding").unwrap()) {
+                Ok(encoding) => encoding,
+                Err(e) => return output_error(&e.to_string()),
+            };
+            let raw = input::read_source(sub_matches.get_one::<String>("input").map(String::as_str))?;
+            match input::decode_input(&raw, encoding) {
+                Ok((bytes, encoding)) => deserialize_bytes(&bytes, encoding, type_hint, strict, strict_version, network),
+                Err(e) => output_error(&format!("Failed to decode input: {}", e)),
+            }
         }
         Some(("validate", sub_matches)) => {
             let msg_type = sub_matches.get_one::<String>("type").unwrap();
@@ -136,14 +922,158 @@ fn main() -> Result<()> {
 
             validate_message(msg_type, &json)
         }
+        Some(("explain", sub_matches)) => {
+            let (bytes, encoding) = match input::message_bytes(
+                sub_matches.get_one::<String>("hex").map(String::as_str),
+                sub_matches.get_one::<String>("input").map(String::as_str),
+                sub_matches.get_one::<String>("encoding").unwrap(),
+            ) {
+                Ok(input) => input,
+                Err(e) => return output_error(&format!("Failed to read input: {}", e)),
+            };
+            let type_hint = sub_matches.get_one::<String>("type").map(String::as_str);
+            let format = sub_matches.get_one::<String>("format").unwrap();
+
+            explain::explain(&bytes, encoding, type_hint, format)
+        }
+        Some(("diff", sub_matches)) => {
+            let left = sub_matches.get_one::<String>("left").unwrap();
+            let right = sub_matches.get_one::<String>("right").unwrap();
+            let type_hint = sub_matches.get_one::<String>("type").map(String::as_str);
+
+            diff::diff_messages(left, right, type_hint)
+        }
+        Some(("fuzz-corpus", sub_matches)) => {
+            let corpus = sub_matches.get_one::<String>("corpus").unwrap();
+            let seed: u64 = sub_matches.get_one::<String>("seed").unwrap().parse()
+                .context("Failed to parse seed as u64")?;
+            let iterations: u64 = sub_matches.get_one::<String>("iterations").unwrap().parse()
+                .context("Failed to parse iterations as u64")?;
+            let max_findings: usize = sub_matches.get_one::<String>("max-findings").unwrap().parse()
+                .context("Failed to parse max-findings as a number")?;
+            let output = sub_matches.get_one::<String>("output").map(String::as_str);
+
+            fuzz::fuzz_corpus(corpus, seed, iterations, max_findings, output)
+        }
+        Some(("differential", sub_matches)) => {
+            let command = sub_matches.get_one::<String>("command").unwrap();
+            let seed: u64 = sub_matches.get_one::<String>("seed").unwrap().parse()
+                .context("Failed to parse seed as u64")?;
+            let iterations: u64 = sub_matches.get_one::<String>("iterations").unwrap().parse()
+                .context("Failed to parse iterations as u64")?;
+            let types: Vec<&str> = match sub_matches.get_one::<String>("types") {
+                Some(types) => types.split(',').map(str::trim).collect(),
+                None => generate::GENERATED_TYPES.to_vec(),
+            };
+            let shrink_budget: usize = sub_matches.get_one::<String>("shrink-budget").unwrap().parse()
+                .context("Failed to parse shrink-budget as a number")?;
+            let max_failures: usize = sub_matches.get_one::<String>("max-failures").unwrap().parse()
+                .context("Failed to parse max-failures as a number")?;
+
+            differential::run_differential(command, seed, iterations, &types, shrink_budget, max_failures)
+        }
+        Some(("generate", sub_matches)) => {
+            let msg_type = sub_matches.get_one::<String>("type").unwrap();
+            let seed: u64 = sub_matches.get_one::<String>("seed").unwrap().parse()
+                .context("Failed to parse seed as u64")?;
+            let count: u64 = sub_matches.get_one::<String>("count").unwrap().parse()
+                .context("Failed to parse count as u64")?;
+            let network = match network::parse_network(sub_matches.get_one::<String>("network").unwrap()) {
+                Ok(network) => network,
+                Err(e) => return output_error(&e.to_string()),
+            };
+            let options = generate::Options {
+                contract: sub_matches.get_one::<String>("contract")
+                    .map(|kind| generate::ContractKind::parse(kind))
+                    .transpose()?,
+                oracles: sub_matches.get_one::<String>("oracles")
+                    .map(|oracles| oracles.parse())
+                    .transpose()
+                    .context("Failed to parse oracles as a number")?,
+                threshold: sub_matches.get_one::<String>("threshold")
+                    .map(|threshold| threshold.parse())
+                    .transpose()
+                    .context("Failed to parse threshold as a number")?,
+                bounded_difference: sub_matches.get_flag("bounded-difference"),
+                sub_contracts: sub_matches.get_one::<String>("sub-contracts")
+                    .map(|sub_contracts| sub_contracts.parse())
+                    .transpose()
+                    .context("Failed to parse sub-contracts as a number")?,
+                dlc_inputs: sub_matches.get_flag("dlc-inputs"),
+                network: Some(network),
+            };
+
+            generate::generate(msg_type, seed, count, options)
+        }
+        Some(("generate-vectors", sub_matches)) => {
+            let scenario = sub_matches.get_one::<String>("scenario").unwrap();
+            let output = sub_matches.get_one::<String>("output");
+
+            vectors::generate_vectors(scenario, output.map(|s| s.as_str()))
+        }
+        Some(("build-dlc-input", sub_matches)) => {
+            let fund_tx = sub_matches.get_one::<String>("fund-tx").unwrap();
+            let local_pubkey = sub_matches.get_one::<String>("local-pubkey").unwrap();
+            let remote_pubkey = sub_matches.get_one::<String>("remote-pubkey").unwrap();
+            let contract_id = sub_matches.get_one::<String>("contract-id").unwrap();
+            let serial_id: u64 = sub_matches.get_one::<String>("serial-id").unwrap().parse()
+                .context("Failed to parse serial id as u64")?;
+            let sequence: u32 = sub_matches.get_one::<String>("sequence").unwrap().parse()
+                .context("Failed to parse sequence as u32")?;
+
+            dlc_input::build_dlc_input(fund_tx, local_pubkey, remote_pubkey, contract_id, serial_id, sequence)
+        }
+        Some(("validate-dlc-input", sub_matches)) => {
+            let input = sub_matches.get_one::<String>("input").unwrap();
+            let prev_offer_hex = sub_matches.get_one::<String>("prev-offer-hex").unwrap();
+            let prev_accept_hex = sub_matches.get_one::<String>("prev-accept-hex").unwrap();
+
+            dlc_input::validate_dlc_input(input, prev_offer_hex, prev_accept_hex)
+        }
+        Some(("build-batch-funding", sub_matches)) => {
+            let input = sub_matches.get_one::<String>("input").unwrap();
+
+            batch::build_batch_funding(input)
+        }
+        Some(("apply-negotiation", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let negotiation = sub_matches.get_one::<String>("negotiation").unwrap();
+
+            negotiation::apply_negotiation(offer_hex, negotiation)
+        }
+        Some(("inspect-store-record", sub_matches)) => {
+            let value = sub_matches.get_one::<String>("value").unwrap();
+            let key = sub_matches.get_one::<String>("key").map(String::as_str);
+
+            store::inspect_store_record(value, key)
+        }
+        Some(("container-from", sub_matches)) => {
+            let announcement = sub_matches.get_one::<String>("announcement").unwrap();
+            let attestation = sub_matches.get_one::<String>("attestation").unwrap();
+            let oracle_name = sub_matches.get_one::<String>("oracle-name").unwrap();
+            let uri = sub_matches.get_one::<String>("uri").unwrap();
+            let outcome = sub_matches.get_one::<String>("outcome").map(String::as_str);
+
+            oracles::container_from(announcement, attestation, oracle_name, uri, outcome)
+        }
+        Some(("upgrade-legacy", sub_matches)) => {
+            let hex_str = sub_matches.get_one::<String>("hex").unwrap();
+
+            legacy::upgrade_legacy(hex_str)
+        }
+        Some(("downgrade-legacy", sub_matches)) => {
+            let hex_str = sub_matches.get_one::<String>("hex").unwrap();
+
+            legacy::downgrade_legacy(hex_str)
+        }
         Some(("create-oracle-announcement", sub_matches)) => {
             let event_type = sub_matches.get_one::<String>("event-type").unwrap();
-            let event_id = sub_matches.get_one::<String>("event-id").unwrap();
+            let event_ids: Vec<String> = sub_matches.get_many::<String>("event-id").unwrap().cloned().collect();
             let maturity_str = sub_matches.get_one::<String>("maturity").unwrap();
             let maturity: u32 = maturity_str.parse()
                 .context("Failed to parse maturity as u32")?;
 
-            create_oracle_announcement(event_type, event_id, maturity)
+            create_oracle_announcement(event_type, &event_ids, maturity)
         }
         Some(("create-oracle-attestation", sub_matches)) => {
             let announcement_hex = sub_matches.get_one::<String>("announcement-hex").unwrap();
@@ -151,6 +1081,91 @@ fn main() -> Result<()> {
 
             create_oracle_attestation(announcement_hex, outcome)
         }
+        Some(("validate-flow", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let accept_hex = sub_matches.get_one::<String>("accept-hex").unwrap();
+            let sign_hex = sub_matches.get_one::<String>("sign-hex");
+
+            flow::validate_flow(offer_hex, accept_hex, sign_hex.map(String::as_str))
+        }
+        Some(("contract-id", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let accept_hex = sub_matches.get_one::<String>("accept-hex").unwrap();
+
+            transactions::show_contract_id(offer_hex, accept_hex)
+        }
+        Some(("build-transactions", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let accept_hex = sub_matches.get_one::<String>("accept-hex").unwrap();
+
+            transactions::show_transactions(offer_hex, accept_hex)
+        }
+        Some(("funding-psbt", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let accept_hex = sub_matches.get_one::<String>("accept-hex").unwrap();
+            let key_origins: Vec<String> = sub_matches
+                .get_many::<String>("key-origin")
+                .map(|values| values.cloned().collect())
+                .unwrap_or_default();
+
+            psbt::funding_psbt(offer_hex, accept_hex, &key_origins)
+        }
+        Some(("finalize-funding", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let accept_hex = sub_matches.get_one::<String>("accept-hex").unwrap();
+            let psbts: Vec<String> = sub_matches.get_many::<String>("psbt").unwrap().cloned().collect();
+            let party = sub_matches.get_one::<String>("party").unwrap();
+
+            psbt::finalize_funding(offer_hex, accept_hex, &psbts, party)
+        }
+        Some(("build-refund", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let accept_hex = sub_matches.get_one::<String>("accept-hex").unwrap();
+
+            refund::build_refund(offer_hex, accept_hex)
+        }
+        Some(("verify-refund-sig", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let accept_hex = sub_matches.get_one::<String>("accept-hex").unwrap();
+            let sign_hex = sub_matches.get_one::<String>("sign-hex");
+
+            refund::verify_refund_sig(offer_hex, accept_hex, sign_hex.map(String::as_str))
+        }
+        Some(("report", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex").unwrap();
+            let accept_hex = sub_matches.get_one::<String>("accept-hex");
+
+            report::report(offer_hex, accept_hex.map(String::as_str))
+        }
+        Some(("estimate", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex");
+            let contract_info = sub_matches.get_one::<String>("contract-info");
+            let funding_inputs: Option<u64> = sub_matches.get_one::<String>("funding-inputs")
+                .map(|count| count.parse())
+                .transpose()
+                .context("Failed to parse funding inputs as u64")?;
+            let max_message_size: u64 = sub_matches.get_one::<String>("max-message-size")
+                .map(|size| size.parse())
+                .transpose()
+                .context("Failed to parse max message size as u64")?
+                .unwrap_or(estimate::MAX_MESSAGE_SIZE);
+
+            estimate::estimate(
+                offer_hex.map(String::as_str),
+                contract_info.map(String::as_str),
+                funding_inputs,
+                max_message_size,
+            )
+        }
+        Some(("multi-oracle-cets", sub_matches)) => {
+            let offer_hex = sub_matches.get_one::<String>("offer-hex");
+            let contract_info = sub_matches.get_one::<String>("contract-info");
+
+            multi_oracle::multi_oracle_cets(
+                offer_hex.map(String::as_str),
+                contract_info.map(String::as_str),
+            )
+        }
         _ => {
             eprintln!("No subcommand provided. Use --help for usage.");
             std::process::exit(1);
@@ -158,8 +1173,19 @@ fn main() -> Result<()> {
     }
 }
 
-fn serialize_message(msg_type: &str, json: &Value) -> Result<()> {
-    let result = match msg_type {
+fn serialize_message(msg_type: &str, json: &Value, encoding: input::Encoding) -> Result<()> {
+    match serialize_json(msg_type, json) {
+        Ok(bytes) if encoding == input::Encoding::Binary => input::write_binary(&bytes),
+        Ok(bytes) => output_success(
+            &input::encode_text(&bytes, encoding)?,
+            &format!("Serialized {} message to {}", msg_type, encoding),
+        ),
+        Err(e) => output_error(&format!("Failed to serialize {}: {}", msg_type, e)),
+    }
+}
+
+fn serialize_json(msg_type: &str, json: &Value) -> Result<Vec<u8>> {
+    match msg_type {
         "offer" => serialize_offer(json),
         "accept" => serialize_accept(json),
         "sign" => serialize_sign(json),
@@ -169,18 +1195,25 @@ fn serialize_message(msg_type: &str, json: &Value) -> Result<()> {
         "oracle-info" => serialize_oracle_info(json),
         "contract-info" => serialize_contract_info(json),
         "contract-descriptor" => serialize_contract_descriptor(json),
-        _ => {
-            return output_error(&format!("Unsupported message type: {}", msg_type));
-        }
-    };
-
-    match result {
-        Ok(hex) => output_success(&hex, &format!("Serialized {} message to hex", msg_type)),
-        Err(e) => output_error(&format!("Failed to serialize {}: {}", msg_type, e)),
+        "batch-funding-group" => wire::serialize::<batch::BatchFundingGroup>(json),
+        "order-offer" => wire::serialize::<orders::OrderOffer>(json),
+        "order-accept" => wire::serialize::<orders::OrderAccept>(json),
+        "order-metadata" => wire::serialize::<orders::OrderMetadata>(json),
+        "order-irc-info" => wire::serialize::<orders::OrderIrcInfo>(json),
+        "order-position-info" => wire::serialize::<orders::OrderPositionInfo>(json),
+        "negotiation-fields-v0" => wire::serialize::<negotiation::NegotiationFieldsV0>(json),
+        "negotiation-fields-v1" => wire::serialize::<negotiation::NegotiationFieldsV1>(json),
+        "negotiation-fields-v2" => wire::serialize::<negotiation::NegotiationFieldsV2>(json),
+        "dlc-transactions" => wire::serialize::<store::DlcTransactionsRecord>(json),
+        "dlc-ids" => wire::serialize::<store::DlcIds>(json),
+        "dlc-info" => wire::serialize::<store::DlcInfo>(json),
+        "oracle-event-container" => wire::serialize::<oracles::OracleEventContainer>(json),
+        "oracle-identifier" => wire::serialize::<oracles::OracleIdentifier>(json),
+        _ => bail!("Unsupported message type: {}", msg_type),
     }
 }
 
-fn serialize_offer(json: &Value) -> Result<String> {
+fn serialize_offer(json: &Value) -> Result<Vec<u8>> {
     // Try to deserialize the JSON as an OfferDlc using serde
     let offer: OfferDlc = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as OfferDlc")?;
@@ -192,10 +1225,10 @@ fn serialize_offer(json: &Value) -> Result<String> {
     offer.write(&mut bytes)
         .context("Failed to serialize OfferDlc to bytes")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn serialize_accept(json: &Value) -> Result<String> {
+fn serialize_accept(json: &Value) -> Result<Vec<u8>> {
     let accept: AcceptDlc = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as AcceptDlc")?;
 
@@ -205,10 +1238,10 @@ fn serialize_accept(json: &Value) -> Result<String> {
     accept.write(&mut bytes)
         .context("Failed to serialize AcceptDlc to bytes")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn serialize_sign(json: &Value) -> Result<String> {
+fn serialize_sign(json: &Value) -> Result<Vec<u8>> {
     let sign: SignDlc = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as SignDlc")?;
 
@@ -218,10 +1251,10 @@ fn serialize_sign(json: &Value) -> Result<String> {
     sign.write(&mut bytes)
         .context("Failed to serialize SignDlc to bytes")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn serialize_oracle_announcement(json: &Value) -> Result<String> {
+fn serialize_oracle_announcement(json: &Value) -> Result<Vec<u8>> {
     let announcement: OracleAnnouncement = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as OracleAnnouncement")?;
 
@@ -229,10 +1262,10 @@ fn serialize_oracle_announcement(json: &Value) -> Result<String> {
     write_as_tlv(&announcement, &mut bytes)
         .context("Failed to serialize OracleAnnouncement as TLV")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn serialize_oracle_attestation(json: &Value) -> Result<String> {
+fn serialize_oracle_attestation(json: &Value) -> Result<Vec<u8>> {
     let attestation: OracleAttestation = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as OracleAttestation")?;
 
@@ -240,10 +1273,10 @@ fn serialize_oracle_attestation(json: &Value) -> Result<String> {
     write_as_tlv(&attestation, &mut bytes)
         .context("Failed to serialize OracleAttestation as TLV")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn serialize_oracle_event(json: &Value) -> Result<String> {
+fn serialize_oracle_event(json: &Value) -> Result<Vec<u8>> {
     let event: OracleEvent = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as OracleEvent")?;
 
@@ -252,10 +1285,10 @@ fn serialize_oracle_event(json: &Value) -> Result<String> {
     event.write(&mut bytes)
         .context("Failed to serialize OracleEvent to bytes")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn serialize_oracle_info(json: &Value) -> Result<String> {
+fn serialize_oracle_info(json: &Value) -> Result<Vec<u8>> {
     let info: OracleInfo = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as OracleInfo")?;
 
@@ -264,10 +1297,10 @@ fn serialize_oracle_info(json: &Value) -> Result<String> {
     info.write(&mut bytes)
         .context("Failed to serialize OracleInfo to bytes")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn serialize_contract_info(json: &Value) -> Result<String> {
+fn serialize_contract_info(json: &Value) -> Result<Vec<u8>> {
     let info: ContractInfo = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as ContractInfo")?;
 
@@ -276,10 +1309,10 @@ fn serialize_contract_info(json: &Value) -> Result<String> {
     info.write(&mut bytes)
         .context("Failed to serialize ContractInfo to bytes")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn serialize_contract_descriptor(json: &Value) -> Result<String> {
+fn serialize_contract_descriptor(json: &Value) -> Result<Vec<u8>> {
     let descriptor: ContractDescriptor = serde_json::from_value(json.clone())
         .context("Failed to parse JSON as ContractDescriptor")?;
 
@@ -288,195 +1321,64 @@ fn serialize_contract_descriptor(json: &Value) -> Result<String> {
     descriptor.write(&mut bytes)
         .context("Failed to serialize ContractDescriptor to bytes")?;
 
-    Ok(hex::encode(bytes))
+    Ok(bytes)
 }
 
-fn deserialize_hex(hex_str: &str) -> Result<()> {
+fn deserialize_hex(hex_str: &str, type_hint: Option<&str>, strict: bool, strict_version: bool, network: Option<Network>) -> Result<()> {
     let bytes = hex::decode(hex_str)
         .context("Failed to decode hex string")?;
 
-    // Debug: show message type
-    if bytes.len() >= 2 {
-        let msg_type = u16::from_be_bytes([bytes[0], bytes[1]]);
-        eprintln!("DEBUG: Message type detected: 0x{:04x} ({})", msg_type, msg_type);
-        eprintln!("DEBUG: Hex length: {} bytes", bytes.len());
-        eprintln!("DEBUG: First 20 bytes: {}", hex::encode(&bytes[..std::cmp::min(20, bytes.len())]));
-    }
-
-    // Try OracleAnnouncement TLV
-    let mut cursor_clone = Cursor::new(&bytes);
-    if let Ok(announcement) = read_as_tlv::<OracleAnnouncement, _>(&mut cursor_clone) {
-        if let Ok(json) = serde_json::to_value(&announcement) {
-            return output_success_with_data("oracle-announcement", &json, "Successfully deserialized OracleAnnouncement (TLV)");
-        }
-    }
-
-    // Try OracleAttestation TLV
-    let mut cursor_clone = Cursor::new(&bytes);
-    if let Ok(attestation) = read_as_tlv::<OracleAttestation, _>(&mut cursor_clone) {
-        if let Ok(json) = serde_json::to_value(&attestation) {
-            return output_success_with_data("oracle-attestation", &json, "Successfully deserialized OracleAttestation (TLV)");
-        }
-    }
-
-    if bytes.len() >= 2 {
-        let msg_type = u16::from_be_bytes([bytes[0], bytes[1]]);
-
-        // Skip message type prefix (2 bytes) - message.read() expects message body only
-        let message_body = &bytes[2..];
-        let mut cursor = Cursor::new(message_body);
-
-        match msg_type {
-            42778 => { // DlcOffer (0xa71a)
-                match OfferDlc::read(&mut cursor) {
-                    Ok(offer) => {
-                        match serde_json::to_value(&offer) {
-                            Ok(json) => return output_success_with_data("offer", &json, "Successfully deserialized OfferDlc"),
-                            Err(e) => return output_error(&format!("Failed to convert OfferDlc to JSON: {}", e)),
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("DEBUG: Failed to parse as OfferDlc: {:?}", e);
-                    }
-                }
-            }
-            42780 => { // DlcAccept (0xa71c)
-                match AcceptDlc::read(&mut cursor) {
-                    Ok(accept) => {
-                        match serde_json::to_value(&accept) {
-                            Ok(json) => return output_success_with_data("accept", &json, "Successfully deserialized AcceptDlc"),
-                            Err(e) => return output_error(&format!("Failed to convert AcceptDlc to JSON: {}", e)),
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("DEBUG: Failed to parse as AcceptDlc: {:?}", e);
-                    }
-                }
-            }
-            42782 => { // DlcSign (0xa71e)
-                match SignDlc::read(&mut cursor) {
-                    Ok(sign) => {
-                        match serde_json::to_value(&sign) {
-                            Ok(json) => return output_success_with_data("sign", &json, "Successfully deserialized SignDlc"),
-                            Err(e) => return output_error(&format!("Failed to convert SignDlc to JSON: {}", e)),
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("DEBUG: Failed to parse as SignDlc: {:?}", e);
-                    }
-                }
-            }
-            55332 => { // OracleAnnouncement (0xd824)
-                // Try TLV format first
-                let mut full_cursor = Cursor::new(&bytes);
-                match read_as_tlv::<OracleAnnouncement, _>(&mut full_cursor) {
-                    Ok(announcement) => {
-                        match serde_json::to_value(&announcement) {
-                            Ok(json) => return output_success_with_data("oracle-announcement", &json, "Successfully deserialized OracleAnnouncement (TLV)"),
-                            Err(e) => return output_error(&format!("Failed to convert OracleAnnouncement to JSON: {}", e)),
-                        }
-                    }
-                    Err(_) => {
-                        // Fallback to direct format
-                        match OracleAnnouncement::read(&mut cursor) {
-                            Ok(announcement) => {
-                                match serde_json::to_value(&announcement) {
-                                    Ok(json) => return output_success_with_data("oracle-announcement", &json, "Successfully deserialized OracleAnnouncement (direct)"),
-                                    Err(e) => return output_error(&format!("Failed to convert OracleAnnouncement to JSON: {}", e)),
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("DEBUG: Failed to parse as OracleAnnouncement: {:?}", e);
-                            }
-                        }
-                    }
-                }
-            }
-            55400 => { // OracleAttestation (0xd868)
-                // Try TLV format first
-                let mut full_cursor = Cursor::new(&bytes);
-                match read_as_tlv::<OracleAttestation, _>(&mut full_cursor) {
-                    Ok(attestation) => {
-                        match serde_json::to_value(&attestation) {
-                            Ok(json) => return output_success_with_data("oracle-attestation", &json, "Successfully deserialized OracleAttestation (TLV)"),
-                            Err(e) => return output_error(&format!("Failed to convert OracleAttestation to JSON: {}", e)),
-                        }
-                    }
-                    Err(_) => {
-                        // Fallback to direct format
-                        match OracleAttestation::read(&mut cursor) {
-                            Ok(attestation) => {
-                                match serde_json::to_value(&attestation) {
-                                    Ok(json) => return output_success_with_data("oracle-attestation", &json, "Successfully deserialized OracleAttestation (direct)"),
-                                    Err(e) => return output_error(&format!("Failed to convert OracleAttestation to JSON: {}", e)),
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("DEBUG: Failed to parse as OracleAttestation: {:?}", e);
-                            }
-                        }
-                    }
-                }
-            }
-            _ => {
-                eprintln!("DEBUG: Unknown message type: 0x{:04x} ({})", msg_type, msg_type);
-
-                // Try parsing as types without message type prefix (for components like ContractInfo)
-                let mut full_cursor = Cursor::new(&bytes);
-
-                // Try ContractInfo
-                if let Ok(contract_info) = ContractInfo::read(&mut full_cursor) {
-                    if let Ok(json) = serde_json::to_value(&contract_info) {
-                        return output_success_with_data("contract-info", &json, "Successfully deserialized ContractInfo");
-                    }
-                }
+    deserialize_bytes(&bytes, input::Encoding::Hex, type_hint, strict, strict_version, network)
+}
 
-                // Try OracleInfo
-                let mut full_cursor = Cursor::new(&bytes);
-                if let Ok(oracle_info) = OracleInfo::read(&mut full_cursor) {
-                    if let Ok(json) = serde_json::to_value(&oracle_info) {
-                        return output_success_with_data("oracle-info", &json, "Successfully deserialized OracleInfo");
-                    }
-                }
+fn deserialize_bytes(bytes: &[u8], encoding: input::Encoding, type_hint: Option<&str>, strict: bool, strict_version: bool, network: Option<Network>) -> Result<()> {
+    let result = match type_hint {
+        Some(type_hint) => decode::decode_as(bytes, type_hint),
+        None => decode::detect(bytes),
+    };
 
-                // Try OracleEvent
-                let mut full_cursor = Cursor::new(&bytes);
-                if let Ok(oracle_event) = OracleEvent::read(&mut full_cursor) {
-                    if let Ok(json) = serde_json::to_value(&oracle_event) {
-                        return output_success_with_data("oracle-event", &json, "Successfully deserialized OracleEvent");
-                    }
-                }
-            }
-        }
-    } else {
-        // For data without type prefix, try parsing as different component types
-        let mut cursor = Cursor::new(&bytes);
+    let decoded = match result {
+        Ok(decoded) => decoded,
+        Err(e) => return output_error(&e.to_string()),
+    };
 
-        // Try ContractInfo
-        if let Ok(contract_info) = ContractInfo::read(&mut cursor) {
-            if let Ok(json) = serde_json::to_value(&contract_info) {
-                return output_success_with_data("contract-info", &json, "Successfully deserialized ContractInfo");
-            }
-        }
+    let trailing_bytes = bytes.len() - decoded.consumed;
+    if trailing_bytes > 0 && strict {
+        return output_error(&format!(
+            "{} trailing bytes after {} (strict mode)",
+            trailing_bytes, decoded.message_type
+        ));
+    }
 
-        // Try OracleInfo
-        let mut cursor = Cursor::new(&bytes);
-        if let Ok(oracle_info) = OracleInfo::read(&mut cursor) {
-            if let Ok(json) = serde_json::to_value(&oracle_info) {
-                return output_success_with_data("oracle-info", &json, "Successfully deserialized OracleInfo");
-            }
+    let protocol = decode::protocol_info(&decoded);
+    if let Some(protocol) = &protocol {
+        if strict_version && !protocol.warnings.is_empty() {
+            return output_error(&format!(
+                "{} (strict version mode)",
+                protocol.warnings.join("; ")
+            ));
         }
+    }
 
-        // Try OracleEvent
-        let mut cursor = Cursor::new(&bytes);
-        if let Ok(oracle_event) = OracleEvent::read(&mut cursor) {
-            if let Ok(json) = serde_json::to_value(&oracle_event) {
-                return output_success_with_data("oracle-event", &json, "Successfully deserialized OracleEvent");
-            }
-        }
+    let mut output = serde_json::json!({
+        "status": "success",
+        "messageType": decoded.message_type,
+        "inputEncoding": encoding.to_string(),
+        "detection": decoded.detection,
+        "trailingBytes": trailing_bytes,
+        "data": decoded.data,
+        "warnings": decoded.warnings,
+        "message": decoded.message
+    });
+    if let Some(protocol) = protocol {
+        output["protocol"] = serde_json::to_value(protocol)?;
+    }
+    if let Some(report) = network::network_report(&decoded, network) {
+        output["network"] = report;
     }
 
-    output_error("Failed to deserialize as any known DLC message type")
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
 }
 
 fn validate_message(msg_type: &str, json: &Value) -> Result<()> {
@@ -517,6 +1419,69 @@ fn validate_message(msg_type: &str, json: &Value) -> Result<()> {
             serde_json::from_value::<ContractDescriptor>(json.clone())
                 .map(|_| "Valid ContractDescriptor structure".to_string())
         }
+        "batch-funding-group" => {
+            serde_json::from_value::<batch::BatchFundingGroup>(json.clone())
+                .map(|_| "Valid BatchFundingGroup structure".to_string())
+        }
+        "order-offer" => {
+            serde_json::from_value::<orders::OrderOffer>(json.clone())
+                .and_then(|offer| offer.validate().map_err(serde::de::Error::custom))
+                .map(|_| "Valid OrderOffer structure".to_string())
+        }
+        "order-accept" => {
+            serde_json::from_value::<orders::OrderAccept>(json.clone())
+                .and_then(|accept| match &accept.negotiation_fields {
+                    Some(orders::OrderNegotiationFields::Disjoint { order_offer }) => {
+                        order_offer.validate().map_err(serde::de::Error::custom)
+                    }
+                    _ => Ok(()),
+                })
+                .map(|_| "Valid OrderAccept structure".to_string())
+        }
+        "order-metadata" => {
+            serde_json::from_value::<orders::OrderMetadata>(json.clone())
+                .map(|_| "Valid OrderMetadata structure".to_string())
+        }
+        "order-irc-info" => {
+            serde_json::from_value::<orders::OrderIrcInfo>(json.clone())
+                .map(|_| "Valid OrderIrcInfo structure".to_string())
+        }
+        "order-position-info" => {
+            serde_json::from_value::<orders::OrderPositionInfo>(json.clone())
+                .map(|_| "Valid OrderPositionInfo structure".to_string())
+        }
+        "negotiation-fields-v0" => {
+            serde_json::from_value::<negotiation::NegotiationFieldsV0>(json.clone())
+                .map(|_| "Valid NegotiationFieldsV0 structure".to_string())
+        }
+        "negotiation-fields-v1" => {
+            serde_json::from_value::<negotiation::NegotiationFieldsV1>(json.clone())
+                .map(|_| "Valid NegotiationFieldsV1 structure".to_string())
+        }
+        "negotiation-fields-v2" => {
+            serde_json::from_value::<negotiation::NegotiationFieldsV2>(json.clone())
+                .map(|_| "Valid NegotiationFieldsV2 structure".to_string())
+        }
+        "dlc-transactions" => {
+            serde_json::from_value::<store::DlcTransactionsRecord>(json.clone())
+                .map(|_| "Valid DlcTransactions structure".to_string())
+        }
+        "dlc-ids" => {
+            serde_json::from_value::<store::DlcIds>(json.clone())
+                .map(|_| "Valid DlcIds structure".to_string())
+        }
+        "dlc-info" => {
+            serde_json::from_value::<store::DlcInfo>(json.clone())
+                .map(|_| "Valid DlcInfo structure".to_string())
+        }
+        "oracle-event-container" => {
+            serde_json::from_value::<oracles::OracleEventContainer>(json.clone())
+                .map(|_| "Valid OracleEventContainer structure".to_string())
+        }
+        "oracle-identifier" => {
+            serde_json::from_value::<oracles::OracleIdentifier>(json.clone())
+                .map(|_| "Valid OracleIdentifier structure".to_string())
+        }
         _ => {
             return output_error(&format!("Unsupported message type: {}", msg_type));
         }
@@ -578,21 +1543,26 @@ fn create_nonce_keypair() -> Result<(SecretKey, XOnlyPublicKey)> {
     Ok((nonce_priv, nonce_xpub))
 }
 
-fn create_oracle_announcement(event_type: &str, event_id: &str, maturity: u32) -> Result<()> {
-    // Generate oracle keypair
+fn create_oracle_announcement(event_type: &str, event_ids: &[String], maturity: u32) -> Result<()> {
+    if event_type != "enum" && event_type != "digit-decomposition" {
+        return output_error(&format!("Unsupported event type: {}", event_type));
+    }
+
+    // Generate oracle keypair, shared by every event so that a disjoint
+    // contract can use one oracle for all its sub-contracts
     let oracle_keypair = Keypair::new(SECP256K1, &mut thread_rng());
     let oracle_pubkey = XOnlyPublicKey::from_keypair(&oracle_keypair).0;
 
-    // Create event descriptor and nonces based on event type
-    let (event_descriptor, oracle_nonces) = match event_type {
-        "enum" => {
+    let mut announcements = Vec::new();
+    for event_id in event_ids {
+        // Create event descriptor and fresh nonces based on event type
+        let (event_descriptor, oracle_nonces) = if event_type == "enum" {
             let (_, nonce_pubkey) = create_nonce_keypair()?;
             let descriptor = EventDescriptor::EnumEvent(EnumEventDescriptor {
                 outcomes: vec!["win".to_string(), "lose".to_string(), "draw".to_string()],
             });
             (descriptor, vec![nonce_pubkey])
-        }
-        "digit-decomposition" => {
+        } else {
             // Generate 8 nonces for 8-digit binary decomposition
             let mut nonces = Vec::new();
             for _ in 0..8 {
@@ -607,53 +1577,65 @@ fn create_oracle_announcement(event_type: &str, event_id: &str, maturity: u32) -
                 nb_digits: 8,
             });
             (descriptor, nonces)
-        }
-        _ => {
-            return output_error(&format!("Unsupported event type: {}", event_type));
-        }
-    };
-
-    // Create oracle event
-    let oracle_event = OracleEvent {
-        oracle_nonces,
-        event_maturity_epoch: maturity,
-        event_descriptor,
-        event_id: event_id.to_string(),
-    };
+        };
+
+        // Create oracle event
+        let oracle_event = OracleEvent {
+            oracle_nonces,
+            event_maturity_epoch: maturity,
+            event_descriptor,
+            event_id: event_id.to_string(),
+        };
+
+        // Sign the oracle event
+        let mut event_hex = Vec::new();
+        oracle_event.write(&mut event_hex)
+            .context("Failed to serialize oracle event")?;
+        let hash = bitcoin::hashes::sha256::Hash::hash(&event_hex);
+        let msg = Message::from_digest(hash.to_byte_array());
+        let announcement_signature = SECP256K1.sign_schnorr(&msg, &oracle_keypair);
+
+        // Create oracle announcement
+        let announcement = OracleAnnouncement {
+            announcement_signature,
+            oracle_public_key: oracle_pubkey,
+            oracle_event,
+        };
+
+        // Serialize to hex using TLV format
+        let mut bytes = Vec::new();
+        write_as_tlv(&announcement, &mut bytes)
+            .context("Failed to serialize OracleAnnouncement as TLV")?;
+
+        // Convert to JSON for display
+        let json = serde_json::to_value(&announcement)
+            .context("Failed to convert OracleAnnouncement to JSON")?;
+
+        announcements.push(serde_json::json!({
+            "hex": hex::encode(bytes),
+            "data": json,
+        }));
+    }
 
-    // Sign the oracle event
-    let mut event_hex = Vec::new();
-    oracle_event.write(&mut event_hex)
-        .context("Failed to serialize oracle event")?;
-    let hash = bitcoin::hashes::sha256::Hash::hash(&event_hex);
-    let msg = Message::from_digest(hash.to_byte_array());
-    let announcement_signature = SECP256K1.sign_schnorr(&msg, &oracle_keypair);
-
-    // Create oracle announcement
-    let announcement = OracleAnnouncement {
-        announcement_signature,
-        oracle_public_key: oracle_pubkey,
-        oracle_event,
+    // A single event keeps the flat output; several events, as used by the
+    // sub-contracts of a disjoint contract, are listed in order
+    let output = if let [announcement] = announcements.as_slice() {
+        serde_json::json!({
+            "status": "success",
+            "messageType": "oracle-announcement",
+            "hex": announcement["hex"],
+            "data": announcement["data"],
+            "message": format!("Created {} oracle announcement", event_type)
+        })
+    } else {
+        serde_json::json!({
+            "status": "success",
+            "messageType": "oracle-announcements",
+            "data": announcements,
+            "message": format!("Created {} {} oracle announcements from one oracle", announcements.len(), event_type)
+        })
     };
 
-    // Serialize to hex using TLV format
-    let mut bytes = Vec::new();
-    write_as_tlv(&announcement, &mut bytes)
-        .context("Failed to serialize OracleAnnouncement as TLV")?;
-    let hex = hex::encode(bytes);
-
-    // Convert to JSON for display
-    let json = serde_json::to_value(&announcement)
-        .context("Failed to convert OracleAnnouncement to JSON")?;
-
-    let output = serde_json::json!({
-        "status": "success",
-        "messageType": "oracle-announcement",
-        "hex": hex,
-        "data": json,
-        "message": format!("Created {} oracle announcement", event_type)
-    });
-
     println!("{}", serde_json::to_string_pretty(&output)?);
     Ok(())
 }
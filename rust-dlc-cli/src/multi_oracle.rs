@@ -0,0 +1,279 @@
+//! Multi-oracle numeric outcomes with bounded difference (`oracleParams`).
+//!
+//! When oracles may disagree by a bounded amount, each digit prefix of the
+//! first (primary) oracle's CETs is paired with the prefixes the other
+//! (secondary) oracles may attest to instead. Differences below
+//! 2^minFailExp are always supported and differences of 2^maxErrorExp or
+//! more never are, except between outcomes that share a CET;
+//! `maximizeCoverage` widens support to just under 2^maxErrorExp. As in
+//! rust-dlc, only base 2 decompositions are supported.
+//!
+//! A primary prefix covering at least 2^maxErrorExp outcomes is attested by
+//! every oracle as is, and at each inner edge its outermost block is paired
+//! with the adjacent block across the edge. A smaller prefix is paired with
+//! the smallest prefix around it within its 2^maxErrorExp-aligned block and,
+//! where the supported range crosses that block's edge, with the prefix
+//! covering the overflow. Secondaries never straddle both edges at once.
+
+use crate::cets::{binomial, contract_cets, contract_infos, decompose, digit_base, CetOutcome};
+use crate::estimate::read_contract_source;
+use crate::{output_error, output_success_with_data};
+use anyhow::{bail, Result};
+use dlc_messages::contract_msgs::ContractDescriptor;
+use dlc_messages::oracle_msgs::OracleInfo;
+use serde_json::{json, Value};
+
+pub struct BoundedDifference {
+    nb_digits: usize,
+    max_error_exp: usize,
+    min_fail_exp: usize,
+    maximize_coverage: bool,
+}
+
+/// Prefixes attested at one edge of a primary prefix: the primary's
+/// prefix, the secondary prefix on the primary's side and the secondary
+/// prefix across the edge.
+struct Edge {
+    primary: Vec<u64>,
+    near: Vec<u64>,
+    far: Vec<u64>,
+}
+
+impl BoundedDifference {
+    pub fn new(
+        base: u64,
+        nb_digits: usize,
+        max_error_exp: usize,
+        min_fail_exp: usize,
+        maximize_coverage: bool,
+    ) -> Result<Self> {
+        if base != 2 {
+            bail!(
+                "Bounded difference requires a base 2 digit decomposition, not base {}",
+                base
+            );
+        }
+        if max_error_exp == 0 || max_error_exp >= nb_digits {
+            bail!(
+                "maxErrorExp {} must be between 1 and {} for {} digits",
+                max_error_exp,
+                nb_digits - 1,
+                nb_digits
+            );
+        }
+        if min_fail_exp >= max_error_exp {
+            bail!(
+                "minFailExp {} must be less than maxErrorExp {}",
+                min_fail_exp,
+                max_error_exp
+            );
+        }
+        Ok(BoundedDifference {
+            nb_digits,
+            max_error_exp,
+            min_fail_exp,
+            maximize_coverage,
+        })
+    }
+
+    /// How far past a prefix secondaries are supported.
+    fn margin(&self) -> u64 {
+        if self.maximize_coverage {
+            1 << (self.max_error_exp - 1)
+        } else {
+            1 << self.min_fail_exp
+        }
+    }
+
+    fn interval(&self, prefix: &[u64]) -> (u64, u64) {
+        let start =
+            prefix.iter().fold(0, |acc, digit| acc * 2 + digit) << (self.nb_digits - prefix.len());
+        (start, start + (1 << (self.nb_digits - prefix.len())) - 1)
+    }
+
+    /// The longest prefix shared by every outcome in `start..=end`.
+    fn covering_prefix(&self, start: u64, end: u64) -> Vec<u64> {
+        decompose(start, 2, self.nb_digits)
+            .into_iter()
+            .zip(decompose(end, 2, self.nb_digits))
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    }
+
+    /// Prefix combinations, one per oracle with the primary first, that
+    /// `threshold` oracles may attest to for a CET whose primary prefix is
+    /// `prefix`.
+    pub fn combinations(&self, prefix: &[u64], threshold: usize) -> Vec<Vec<Vec<u64>>> {
+        let (start, end) = self.interval(prefix);
+        let max_error = 1u64 << self.max_error_exp;
+        let max_outcome = (1u64 << self.nb_digits) - 1;
+        let margin = self.margin();
+        let secondaries = threshold.saturating_sub(1);
+
+        let mut edges = Vec::new();
+        let middle = if end - start + 1 >= max_error {
+            if start > 0 {
+                let near = self.covering_prefix(start, start + margin - 1);
+                edges.push(Edge {
+                    primary: near.clone(),
+                    near,
+                    far: self.covering_prefix(start - margin, start - 1),
+                });
+            }
+            if end < max_outcome {
+                let near = self.covering_prefix(end + 1 - margin, end);
+                edges.push(Edge {
+                    primary: near.clone(),
+                    near,
+                    far: self.covering_prefix(end + 1, end + margin),
+                });
+            }
+            prefix.to_vec()
+        } else {
+            let block_start = start - start % max_error;
+            let block_end = block_start + max_error - 1;
+            let low = start.saturating_sub(margin);
+            let high = (end + margin).min(max_outcome);
+            let middle = self.covering_prefix(low.max(block_start), high.min(block_end));
+            if low < block_start {
+                edges.push(Edge {
+                    primary: prefix.to_vec(),
+                    near: middle.clone(),
+                    far: self.covering_prefix(low, block_start - 1),
+                });
+            }
+            if high > block_end {
+                edges.push(Edge {
+                    primary: prefix.to_vec(),
+                    near: middle.clone(),
+                    far: self.covering_prefix(block_end + 1, high),
+                });
+            }
+            middle
+        };
+
+        let mut combinations = vec![std::iter::once(prefix.to_vec())
+            .chain(std::iter::repeat(middle).take(secondaries))
+            .collect::<Vec<_>>()];
+        // Every assignment of near/far to the secondaries with at least one
+        // across the edge; all-near is covered by the combination above.
+        for edge in &edges {
+            for mask in 1..1u64 << secondaries {
+                let mut combination = vec![edge.primary.clone()];
+                combination.extend((0..secondaries).map(|i| {
+                    if mask >> i & 1 == 1 {
+                        edge.far.clone()
+                    } else {
+                        edge.near.clone()
+                    }
+                }));
+                combinations.push(combination);
+            }
+        }
+        combinations
+    }
+}
+
+/// Bounded-difference parameters and threshold of an oracle info, if it has
+/// any.
+pub fn bounded_difference(
+    oracle_info: &OracleInfo,
+    base: u64,
+    nb_digits: u16,
+) -> Result<Option<(BoundedDifference, usize)>> {
+    let OracleInfo::Multi(multi) = oracle_info else {
+        return Ok(None);
+    };
+    let Some(params) = &multi.oracle_params else {
+        return Ok(None);
+    };
+    let bounded = BoundedDifference::new(
+        base,
+        nb_digits as usize,
+        params.max_error_exp as usize,
+        params.min_fail_exp as usize,
+        params.maximize_coverage,
+    )?;
+    Ok(Some((bounded, multi.threshold as usize)))
+}
+
+/// Per payout interval, the prefix combinations oracles may attest to in a
+/// multi-oracle numeric contract with bounded difference.
+pub fn multi_oracle_cets(offer_hex: Option<&str>, contract_info_json: Option<&str>) -> Result<()> {
+    let contract_info = match read_contract_source(offer_hex, contract_info_json) {
+        Ok((contract_info, _)) => contract_info,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    let cets = match contract_cets(&contract_info) {
+        Ok(cets) => cets,
+        Err(e) => return output_error(&format!("Failed to enumerate CETs: {}", e)),
+    };
+    let (_, infos) = contract_infos(&contract_info);
+
+    let mut sub_contracts = Vec::new();
+    for (index, (info, cets)) in infos.iter().zip(&cets).enumerate() {
+        let ContractDescriptor::NumericOutcomeContractDescriptor(descriptor) =
+            &info.contract_descriptor
+        else {
+            continue;
+        };
+        let OracleInfo::Multi(multi) = &info.oracle_info else {
+            continue;
+        };
+        let Some(params) = &multi.oracle_params else {
+            continue;
+        };
+        // The CETs were enumerated above, so the parameters are valid.
+        let bounded = BoundedDifference::new(
+            digit_base(&info.oracle_info)?,
+            descriptor.num_digits as usize,
+            params.max_error_exp as usize,
+            params.min_fail_exp as usize,
+            params.maximize_coverage,
+        )?;
+        let threshold = multi.threshold as usize;
+
+        let intervals: Vec<Value> = cets
+            .iter()
+            .filter_map(|cet| match &cet.outcome {
+                CetOutcome::Numeric {
+                    start,
+                    end,
+                    prefixes,
+                } => Some(json!({
+                    "start": start,
+                    "end": end,
+                    "offerPayout": cet.offer_payout,
+                    "acceptPayout": cet.accept_payout,
+                    "prefixes": prefixes.iter().map(|prefix| json!({
+                        "prefix": prefix,
+                        "combinations": bounded.combinations(prefix, threshold),
+                    })).collect::<Vec<_>>(),
+                })),
+                CetOutcome::Enumerated { .. } => None,
+            })
+            .collect();
+        let oracle_count = multi.oracle_announcements.len() as u64;
+        sub_contracts.push(json!({
+            "index": index,
+            "threshold": threshold,
+            "oracleCount": oracle_count,
+            "oracleSubsets": binomial(oracle_count, threshold as u64),
+            "maxErrorExp": params.max_error_exp,
+            "minFailExp": params.min_fail_exp,
+            "maximizeCoverage": params.maximize_coverage,
+            "intervals": intervals,
+            "adaptorSignatureCount": cets.iter().map(|cet| cet.adaptor_points).sum::<u64>(),
+        }));
+    }
+    if sub_contracts.is_empty() {
+        return output_error("Contract has no multi-oracle numeric sub-contract with oracleParams");
+    }
+
+    output_success_with_data(
+        "multi-oracle-cets",
+        &json!({ "subContracts": sub_contracts }),
+        "Computed bounded-difference prefix combinations",
+    )
+}
@@ -0,0 +1,399 @@
+//! Negotiation fields: rounding intervals an accepting party proposes in
+//! place of the offered ones.
+//!
+//! The current encoding is the untyped `Single`/`Disjoint` enum that rust-dlc
+//! carries in accept messages. Older node-dlc releases sent typed TLVs instead:
+//! `NegotiationFieldsV0` (55334, no proposal), `NegotiationFieldsV1` (55336,
+//! one set of rounding intervals) and `NegotiationFieldsV2` (55346, one V1
+//! record per disjoint sub-contract).
+
+use crate::cets::contract_infos;
+use crate::codec::{decode_hex, read_accept, read_offer};
+use crate::output_error;
+use crate::payout::{range_payouts, RangePayout};
+use crate::wire::{self, NodeMessage, Reader, Writer};
+use anyhow::{bail, Context, Result};
+use dlc_messages::contract_msgs::{ContractDescriptor, RoundingIntervals};
+use dlc_messages::ACCEPT_TYPE;
+use lightning::util::ser::Writeable;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Negotiation fields in node-dlc's JSON layout, encoded like rust-dlc's
+/// `NegotiationFields`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "variant")]
+pub enum Negotiation {
+    Single {
+        #[serde(rename = "roundingIntervals")]
+        rounding_intervals: RoundingIntervals,
+    },
+    Disjoint {
+        #[serde(rename = "negotiationFields")]
+        negotiation_fields: Vec<Negotiation>,
+    },
+}
+
+/// Empty rounding intervals (as in `NegotiationFieldsV0`) propose nothing.
+fn proposal(intervals: &RoundingIntervals) -> Option<&RoundingIntervals> {
+    Some(intervals).filter(|intervals| !intervals.intervals.is_empty())
+}
+
+impl Negotiation {
+    pub fn read(r: &mut Reader) -> Result<Self> {
+        match r.bigsize("variant")? {
+            0 => Ok(Negotiation::Single {
+                rounding_intervals: r.readable("roundingIntervals")?,
+            }),
+            1 => Ok(Negotiation::Disjoint {
+                negotiation_fields: r.vec("negotiationFields", Negotiation::read)?,
+            }),
+            other => bail!("Unknown negotiation fields variant {}", other),
+        }
+    }
+
+    pub fn write(&self, w: &mut Writer) {
+        match self {
+            Negotiation::Single { rounding_intervals } => {
+                w.bigsize(0);
+                w.writeable(rounding_intervals);
+            }
+            Negotiation::Disjoint { negotiation_fields } => {
+                w.bigsize(1);
+                w.vec(negotiation_fields, |w, fields| fields.write(w));
+            }
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(bytes);
+        let negotiation = Negotiation::read(&mut r)?;
+        if !r.is_empty() {
+            bail!(
+                "{} unread bytes after the negotiation fields",
+                bytes.len() - r.position()
+            );
+        }
+        Ok(negotiation)
+    }
+
+    /// Proposed intervals per sub-contract; `None` where nothing is proposed.
+    fn proposals(&self, sub_contracts: usize) -> Result<Vec<Option<&RoundingIntervals>>> {
+        match self {
+            Negotiation::Single { rounding_intervals } if sub_contracts == 1 => {
+                Ok(vec![proposal(rounding_intervals)])
+            }
+            Negotiation::Single { .. } => {
+                bail!("Single negotiation fields cannot apply to a disjoint contract")
+            }
+            Negotiation::Disjoint { negotiation_fields } => {
+                if negotiation_fields.len() != sub_contracts {
+                    bail!(
+                        "Disjoint negotiation fields have {} entries but the contract has {} sub-contracts",
+                        negotiation_fields.len(),
+                        sub_contracts
+                    );
+                }
+                negotiation_fields
+                    .iter()
+                    .map(|fields| match fields {
+                        Negotiation::Single { rounding_intervals } => {
+                            Ok(proposal(rounding_intervals))
+                        }
+                        Negotiation::Disjoint { .. } => {
+                            bail!("Nested disjoint negotiation fields are not supported")
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegotiationFieldsV0 {}
+
+impl NodeMessage for NegotiationFieldsV0 {
+    const TYPE: u64 = 55334;
+    const NAME: &'static str = "NegotiationFieldsV0";
+
+    fn read_body(_: &mut Reader) -> Result<Self> {
+        Ok(NegotiationFieldsV0 {})
+    }
+
+    fn write_body(&self, _: &mut Writer) {}
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NegotiationFieldsV1 {
+    pub rounding_intervals: RoundingIntervals,
+}
+
+impl NodeMessage for NegotiationFieldsV1 {
+    const TYPE: u64 = 55336;
+    const NAME: &'static str = "NegotiationFieldsV1";
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(NegotiationFieldsV1 {
+            rounding_intervals: r.readable("roundingIntervals")?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.writeable(&self.rounding_intervals);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NegotiationFieldsV2 {
+    pub negotiation_fields: Vec<NegotiationFieldsV1>,
+}
+
+impl NodeMessage for NegotiationFieldsV2 {
+    const TYPE: u64 = 55346;
+    const NAME: &'static str = "NegotiationFieldsV2";
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(NegotiationFieldsV2 {
+            negotiation_fields: r.vec("negotiationFields", NegotiationFieldsV1::read)?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.vec(&self.negotiation_fields, |w, fields| {
+            w.bytes(&fields.encode())
+        });
+    }
+}
+
+impl From<NegotiationFieldsV0> for Negotiation {
+    fn from(_: NegotiationFieldsV0) -> Self {
+        Negotiation::Single {
+            rounding_intervals: RoundingIntervals {
+                intervals: Vec::new(),
+            },
+        }
+    }
+}
+
+impl From<NegotiationFieldsV1> for Negotiation {
+    fn from(fields: NegotiationFieldsV1) -> Self {
+        Negotiation::Single {
+            rounding_intervals: fields.rounding_intervals,
+        }
+    }
+}
+
+impl From<NegotiationFieldsV2> for Negotiation {
+    fn from(fields: NegotiationFieldsV2) -> Self {
+        Negotiation::Disjoint {
+            negotiation_fields: fields
+                .negotiation_fields
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+/// Decode a whole input as one legacy TLV.
+fn read_legacy<T: NodeMessage + Into<Negotiation>>(bytes: &[u8]) -> Result<Negotiation> {
+    let (fields, consumed) = wire::decode::<T>(bytes)?;
+    if consumed != bytes.len() {
+        bail!("{} bytes after {}", bytes.len() - consumed, T::NAME);
+    }
+    Ok(fields.into())
+}
+
+/// Read negotiation fields given as node-dlc JSON, as hex of an accept
+/// message, of a V0/V1/V2 TLV or of the untyped encoding.
+fn read_negotiation(input: &str) -> Result<Negotiation> {
+    let trimmed = input.trim();
+    if trimmed.starts_with('{') {
+        return serde_json::from_str(trimmed).context("Failed to parse negotiation fields JSON");
+    }
+    let bytes = decode_hex(trimmed)?;
+    if bytes.starts_with(&ACCEPT_TYPE.to_be_bytes()) {
+        let accept = read_accept(trimmed)?;
+        let Some(fields) = accept.negotiation_fields else {
+            bail!("Accept message carries no negotiation fields");
+        };
+        return Negotiation::from_bytes(&fields.encode());
+    }
+    read_legacy::<NegotiationFieldsV0>(&bytes)
+        .or_else(|_| read_legacy::<NegotiationFieldsV1>(&bytes))
+        .or_else(|_| read_legacy::<NegotiationFieldsV2>(&bytes))
+        .or_else(|_| Negotiation::from_bytes(&bytes))
+        .context("Failed to decode negotiation fields")
+}
+
+fn check_intervals(intervals: &RoundingIntervals) -> Result<()> {
+    match intervals.intervals.first() {
+        None => bail!("Proposed rounding intervals must not be empty"),
+        Some(first) if first.begin_interval != 0 => bail!(
+            "Proposed rounding intervals must start at outcome 0, not {}",
+            first.begin_interval
+        ),
+        Some(_) => {}
+    }
+    for pair in intervals.intervals.windows(2) {
+        if pair[0].begin_interval >= pair[1].begin_interval {
+            bail!("Proposed rounding intervals must be strictly increasing");
+        }
+    }
+    if let Some(interval) = intervals
+        .intervals
+        .iter()
+        .find(|interval| interval.rounding_mod == 0)
+    {
+        bail!(
+            "Proposed rounding modulus for outcomes from {} must be positive",
+            interval.begin_interval
+        );
+    }
+    Ok(())
+}
+
+/// Outcome ranges on which the offer payout differs between two tables of
+/// the same descriptor.
+fn payout_changes(before: &[RangePayout], after: &[RangePayout]) -> Vec<Value> {
+    let mut changes: Vec<(u64, u64, u64, u64)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() && j < after.len() {
+        let (old, new) = (&before[i], &after[j]);
+        let start = old.start.max(new.start);
+        let end = old.end().min(new.end());
+        if old.offer_payout != new.offer_payout {
+            match changes.last_mut() {
+                Some(last)
+                    if last.1 + 1 == start
+                        && (last.2, last.3) == (old.offer_payout, new.offer_payout) =>
+                {
+                    last.1 = end;
+                }
+                _ => changes.push((start, end, old.offer_payout, new.offer_payout)),
+            }
+        }
+        if old.end() == end {
+            i += 1;
+        }
+        if new.end() == end {
+            j += 1;
+        }
+    }
+    changes
+        .into_iter()
+        .map(|(start, end, offered, proposed)| {
+            json!({
+                "start": start,
+                "end": end,
+                "offeredPayout": offered,
+                "proposedPayout": proposed,
+                "difference": proposed as i64 - offered as i64,
+            })
+        })
+        .collect()
+}
+
+/// Apply the proposed rounding intervals to each numeric sub-contract of the
+/// offer and report how the payout table changes.
+pub fn apply_negotiation(offer_hex: &str, negotiation: &str) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let negotiation = match read_negotiation(negotiation) {
+        Ok(negotiation) => negotiation,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    let (total_collateral, infos) = contract_infos(&offer.contract_info);
+    let proposals = match negotiation.proposals(infos.len()) {
+        Ok(proposals) => proposals,
+        Err(e) => return output_error(&e.to_string()),
+    };
+
+    let mut contracts = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, (info, proposal)) in infos.iter().zip(proposals).enumerate() {
+        let descriptor = match &info.contract_descriptor {
+            ContractDescriptor::NumericOutcomeContractDescriptor(descriptor) => descriptor,
+            ContractDescriptor::EnumeratedContractDescriptor(_) => {
+                if proposal.is_some() {
+                    warnings.push(format!(
+                        "Sub-contract {} is enumerated; rounding intervals do not apply",
+                        index
+                    ));
+                }
+                contracts
+                    .push(json!({ "index": index, "kind": "enum", "status": "not-applicable" }));
+                continue;
+            }
+        };
+        let Some(proposed) = proposal else {
+            contracts.push(json!({
+                "index": index,
+                "kind": "numeric",
+                "status": "unchanged",
+                "roundingIntervals": descriptor.rounding_intervals,
+            }));
+            continue;
+        };
+        if let Err(e) = check_intervals(proposed) {
+            return output_error(&format!("Sub-contract {}: {}", index, e));
+        }
+
+        let mut negotiated = descriptor.clone();
+        negotiated.rounding_intervals = proposed.clone();
+        let (before, after) = match (
+            range_payouts(descriptor, total_collateral),
+            range_payouts(&negotiated, total_collateral),
+        ) {
+            (Ok(before), Ok(after)) => (before, after),
+            (Err(e), _) => {
+                return output_error(&format!("Sub-contract {} as offered: {}", index, e))
+            }
+            (_, Err(e)) => {
+                return output_error(&format!("Sub-contract {} as negotiated: {}", index, e))
+            }
+        };
+        let changes = payout_changes(&before, &after);
+        let max_difference = changes
+            .iter()
+            .filter_map(|change| change["difference"].as_i64())
+            .map(i64::unsigned_abs)
+            .max()
+            .unwrap_or(0);
+        contracts.push(json!({
+            "index": index,
+            "kind": "numeric",
+            "status": "applied",
+            "offeredRoundingIntervals": descriptor.rounding_intervals,
+            "proposedRoundingIntervals": proposed,
+            "offeredRanges": before.len(),
+            "proposedRanges": after.len(),
+            "maxDifference": max_difference,
+            "changes": changes,
+        }));
+    }
+
+    let applied = contracts
+        .iter()
+        .filter(|contract| contract["status"] == "applied")
+        .count();
+    let data = json!({
+        "negotiationFields": negotiation,
+        "contracts": contracts,
+        "warnings": warnings,
+    });
+    crate::output_success_with_data(
+        "negotiation",
+        &data,
+        &format!(
+            "Applied proposed rounding intervals to {} of {} sub-contract(s)",
+            applied,
+            infos.len()
+        ),
+    )
+}
@@ -0,0 +1,147 @@
+//! Bitcoin network of a message: the chain hash mapped to a network, and the
+//! message's scriptPubKeys rendered as addresses on that network.
+
+use crate::decode::Decoded;
+use crate::transactions::funding_input_prev_tx;
+use anyhow::{anyhow, Result};
+use bitcoin::blockdata::constants::ChainHash;
+use bitcoin::{Address, Network, ScriptBuf};
+use dlc_messages::{AcceptDlc, FundingInput, OfferDlc};
+use serde_json::{json, Value};
+
+/// Parse a network name as accepted by `--network`.
+pub fn parse_network(name: &str) -> Result<Network> {
+    match name {
+        "mainnet" => Ok(Network::Bitcoin),
+        _ => name.parse().map_err(|_| {
+            anyhow!(
+                "Unsupported network: {} (expected mainnet, testnet, signet or regtest)",
+                name
+            )
+        }),
+    }
+}
+
+pub fn network_name(network: Network) -> String {
+    if network == Network::Bitcoin {
+        "mainnet".to_string()
+    } else {
+        network.to_string()
+    }
+}
+
+/// The payout and change scripts, and the outputs spent by the funding
+/// inputs, labelled with the field they come from.
+fn message_scripts(
+    payout_spk: &ScriptBuf,
+    change_spk: &ScriptBuf,
+    funding_inputs: &[FundingInput],
+    warnings: &mut Vec<String>,
+) -> Vec<(String, ScriptBuf)> {
+    let mut scripts = vec![
+        ("payoutSpk".to_string(), payout_spk.clone()),
+        ("changeSpk".to_string(), change_spk.clone()),
+    ];
+    for (index, input) in funding_inputs.iter().enumerate() {
+        let output = funding_input_prev_tx(input).ok().and_then(|prev_tx| {
+            prev_tx
+                .output
+                .get(input.prev_tx_vout as usize)
+                .map(|output| output.script_pubkey.clone())
+        });
+        match output {
+            Some(script) => scripts.push((format!("fundingInputs[{}]", index), script)),
+            None => warnings.push(format!(
+                "Funding input {} does not reference an output of its previous transaction",
+                index
+            )),
+        }
+    }
+    scripts
+}
+
+/// Network details of a decoded offer, accept or order offer: the network
+/// its chain hash names, its scripts as addresses, and any disagreement
+/// with the network the caller expected.
+pub fn network_report(decoded: &Decoded, expected: Option<Network>) -> Option<Value> {
+    let mut warnings = Vec::new();
+    let chain_hash: Option<ChainHash> = match decoded.message_type {
+        "offer" | "order-offer" => {
+            Some(serde_json::from_value(decoded.data.get("chainHash")?.clone()).ok()?)
+        }
+        "accept" => None,
+        _ => return None,
+    };
+    let scripts = match decoded.message_type {
+        "offer" => {
+            let offer: OfferDlc = serde_json::from_value(decoded.data.clone()).ok()?;
+            message_scripts(
+                &offer.payout_spk,
+                &offer.change_spk,
+                &offer.funding_inputs,
+                &mut warnings,
+            )
+        }
+        "accept" => {
+            let accept: AcceptDlc = serde_json::from_value(decoded.data.clone()).ok()?;
+            message_scripts(
+                &accept.payout_spk,
+                &accept.change_spk,
+                &accept.funding_inputs,
+                &mut warnings,
+            )
+        }
+        _ => Vec::new(),
+    };
+
+    let chain_network = chain_hash.and_then(Network::from_chain_hash);
+    if let Some(chain_hash) = chain_hash {
+        if chain_network.is_none() {
+            warnings.push(format!("Chain hash {} names no known network", chain_hash));
+        }
+    }
+    if let (Some(chain_network), Some(expected)) = (chain_network, expected) {
+        if chain_network != expected {
+            warnings.push(format!(
+                "Message is for {} but {} was expected; its addresses are not valid on {}",
+                network_name(chain_network),
+                network_name(expected),
+                network_name(expected)
+            ));
+        }
+    }
+
+    // Accepts carry no chain hash, so their scripts are rendered for the
+    // expected network, if one was given.
+    let network = chain_network.or(expected);
+    let addresses: Vec<Value> = match network {
+        Some(network) => scripts
+            .iter()
+            .map(|(field, script)| {
+                let address = Address::from_script(script, network)
+                    .ok()
+                    .map(|address| address.to_string());
+                if address.is_none() {
+                    warnings.push(format!("{} is not a standard address script", field));
+                }
+                json!({
+                    "field": field,
+                    "scriptPubKey": script.to_hex_string(),
+                    "address": address,
+                })
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    if network.is_none() && !scripts.is_empty() {
+        warnings.push("No network to render addresses for; pass --network".to_string());
+    }
+
+    Some(json!({
+        "chainHash": chain_hash.map(|chain_hash| chain_hash.to_string()),
+        "network": chain_network.map(network_name),
+        "expectedNetwork": expected.map(network_name),
+        "addresses": addresses,
+        "warnings": warnings,
+    }))
+}
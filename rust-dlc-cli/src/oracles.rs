@@ -0,0 +1,208 @@
+//! Oracle store records from node-dlc.
+//!
+//! An `OracleEventContainer` (TLV 61632) keeps an announcement together with
+//! its attestation, the oracle's name and URI, and the attested outcome. An
+//! `OracleIdentifier` (TLV 61472) names an oracle public key.
+
+use crate::codec::{decode_hex, read_wire_message};
+use crate::decode::{ORACLE_ANNOUNCEMENT_TYPE, ORACLE_ATTESTATION_TYPE};
+use crate::output_error;
+use crate::wire::{NodeMessage, Reader, Writer};
+use anyhow::{anyhow, bail, Context, Result};
+use dlc_messages::oracle_msgs::{EventDescriptor, OracleAnnouncement, OracleAttestation};
+use dlc_messages::ser_impls::read_as_tlv;
+use lightning::io::Cursor;
+use lightning::ln::wire::Type;
+use lightning::util::ser::Readable;
+use secp256k1_zkp::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OracleEventContainer {
+    pub oracle_name: String,
+    pub uri: String,
+    pub announcement: OracleAnnouncement,
+    pub attestation: OracleAttestation,
+    pub outcome: String,
+}
+
+impl NodeMessage for OracleEventContainer {
+    const TYPE: u64 = 61632;
+    const NAME: &'static str = "OracleEventContainer";
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(OracleEventContainer {
+            oracle_name: r.string("oracleName")?,
+            uri: r.string("uri")?,
+            announcement: r.tlv("announcement")?,
+            attestation: r.tlv("attestation")?,
+            outcome: r.string("outcome")?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.string(&self.oracle_name);
+        w.string(&self.uri);
+        w.tlv(&self.announcement);
+        w.tlv(&self.attestation);
+        w.string(&self.outcome);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OracleIdentifier {
+    pub oracle_name: String,
+    pub oracle_pubkey: XOnlyPublicKey,
+}
+
+impl NodeMessage for OracleIdentifier {
+    const TYPE: u64 = 61472;
+    const NAME: &'static str = "OracleIdentifier";
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(OracleIdentifier {
+            oracle_name: r.string("oracleName")?,
+            oracle_pubkey: XOnlyPublicKey::from_slice(r.take(32, "oraclePubkey")?)
+                .context("Invalid oraclePubkey")?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.string(&self.oracle_name);
+        w.bytes(&self.oracle_pubkey.serialize());
+    }
+}
+
+/// Oracle messages as produced by `create-oracle-announcement` and
+/// `create-oracle-attestation` (TLV), or with a plain 2-byte type.
+fn read_oracle_message<T: Readable + Type>(
+    hex_str: &str,
+    expected_type: u16,
+    name: &str,
+) -> Result<T> {
+    let bytes = decode_hex(hex_str)?;
+    read_as_tlv::<T, _>(&mut Cursor::new(&bytes))
+        .map_err(|e| anyhow!("{:?}", e))
+        .or_else(|_| read_wire_message(&bytes, expected_type, name))
+        .with_context(|| format!("Failed to parse {}", name))
+}
+
+/// The attested outcome as one string: the enum outcome, or the number
+/// spelled by the digits (most significant first, after the sign digit of a
+/// signed event).
+fn attested_outcome(
+    announcement: &OracleAnnouncement,
+    attestation: &OracleAttestation,
+) -> Result<String> {
+    match &announcement.oracle_event.event_descriptor {
+        EventDescriptor::EnumEvent(_) => match attestation.outcomes.as_slice() {
+            [outcome] => Ok(outcome.clone()),
+            outcomes => bail!(
+                "Enum attestation should have one outcome, got {}",
+                outcomes.len()
+            ),
+        },
+        EventDescriptor::DigitDecompositionEvent(descriptor) => {
+            let mut digits = attestation.outcomes.as_slice();
+            let mut sign = "";
+            if descriptor.is_signed {
+                let Some((first, rest)) = digits.split_first() else {
+                    bail!("Signed attestation has no sign outcome");
+                };
+                sign = if first == "-" { "-" } else { "" };
+                digits = rest;
+            }
+            let mut value: u64 = 0;
+            for digit in digits {
+                let digit: u64 = digit
+                    .parse()
+                    .with_context(|| format!("Outcome digit {:?} is not a number", digit))?;
+                value = value
+                    .checked_mul(descriptor.base as u64)
+                    .and_then(|value| value.checked_add(digit))
+                    .ok_or_else(|| anyhow!("Attested outcome overflows u64"))?;
+            }
+            Ok(format!("{}{}", sign, value))
+        }
+    }
+}
+
+/// Bundle an announcement and its attestation into an oracle event container.
+pub fn container_from(
+    announcement_hex: &str,
+    attestation_hex: &str,
+    oracle_name: &str,
+    uri: &str,
+    outcome: Option<&str>,
+) -> Result<()> {
+    let announcement = match read_oracle_message::<OracleAnnouncement>(
+        announcement_hex,
+        ORACLE_ANNOUNCEMENT_TYPE,
+        "OracleAnnouncement",
+    ) {
+        Ok(announcement) => announcement,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    let attestation = match read_oracle_message::<OracleAttestation>(
+        attestation_hex,
+        ORACLE_ATTESTATION_TYPE,
+        "OracleAttestation",
+    ) {
+        Ok(attestation) => attestation,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+
+    if attestation.event_id != announcement.oracle_event.event_id {
+        return output_error(&format!(
+            "Attestation is for event {} but the announcement is for {}",
+            attestation.event_id, announcement.oracle_event.event_id
+        ));
+    }
+    let mut warnings = Vec::new();
+    if attestation.oracle_public_key != announcement.oracle_public_key {
+        warnings.push(format!(
+            "Attestation is signed by {} but the announcement by {}",
+            attestation.oracle_public_key, announcement.oracle_public_key
+        ));
+    }
+    if attestation.signatures.len() != announcement.oracle_event.oracle_nonces.len() {
+        warnings.push(format!(
+            "Attestation has {} signatures for {} announced nonces",
+            attestation.signatures.len(),
+            announcement.oracle_event.oracle_nonces.len()
+        ));
+    }
+
+    let outcome = match outcome {
+        Some(outcome) => outcome.to_string(),
+        None => match attested_outcome(&announcement, &attestation) {
+            Ok(outcome) => outcome,
+            Err(e) => return output_error(&format!("{:#}; pass --outcome", e)),
+        },
+    };
+
+    let container = OracleEventContainer {
+        oracle_name: oracle_name.to_string(),
+        uri: uri.to_string(),
+        announcement,
+        attestation,
+        outcome,
+    };
+    let data = json!({
+        "container": serde_json::to_value(&container)
+            .context("Failed to convert OracleEventContainer to JSON")?,
+        "hex": hex::encode(container.encode()),
+        "warnings": warnings,
+    });
+    crate::output_success_with_data(
+        "oracle-event-container",
+        &data,
+        &format!(
+            "Bundled event {} with outcome {}",
+            container.announcement.oracle_event.event_id, container.outcome
+        ),
+    )
+}
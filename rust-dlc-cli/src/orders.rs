@@ -0,0 +1,415 @@
+//! Order messages from node-dlc's order-book (IRC marketplace) flow.
+//!
+//! An `OrderOffer` (type 62770) advertises a contract before any funding
+//! details are exchanged and an `OrderAccept` (type 62772) takes it up,
+//! optionally with counter-proposed terms. Offers end in a TLV stream that
+//! carries `OrderMetadata` (62774), `OrderIrcInfo` (62776),
+//! `OrderPositionInfo` (62778) and `BatchFundingGroup` records; records of
+//! other types are kept so they survive a round trip.
+
+use crate::batch::BatchFundingGroup;
+use crate::cets::contract_infos;
+use crate::locktime::LOCKTIME_THRESHOLD;
+use crate::wire::{hex_array, hex_bytes, Framing, NodeMessage, Reader, Writer};
+use anyhow::{bail, Result};
+use dlc_messages::contract_msgs::ContractInfo;
+use serde::{Deserialize, Serialize};
+
+/// Wire type of an OrderOffer.
+pub const ORDER_OFFER_TYPE: u16 = 62770;
+/// Wire type of an OrderAccept.
+pub const ORDER_ACCEPT_TYPE: u16 = 62772;
+
+/// The only protocol version node-dlc accepts in an order offer.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Same minimum as node-dlc's `OrderOffer.validate`.
+const MIN_OFFER_COLLATERAL: u64 = 1000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderOffer {
+    #[serde(default = "default_protocol_version")]
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub contract_flags: u8,
+    #[serde(with = "hex_array")]
+    pub chain_hash: [u8; 32],
+    #[serde(with = "hex_array")]
+    pub temporary_contract_id: [u8; 32],
+    pub contract_info: ContractInfo,
+    pub offer_collateral: u64,
+    pub fee_rate_per_vb: u64,
+    pub cet_locktime: u32,
+    pub refund_locktime: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<OrderMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub irc_info: Option<OrderIrcInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position_info: Option<OrderPositionInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub batch_funding_groups: Vec<BatchFundingGroup>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown_tlvs: Vec<UnknownTlv>,
+}
+
+fn default_protocol_version() -> u32 {
+    PROTOCOL_VERSION
+}
+
+/// A TLV record this tool does not interpret, kept for re-encoding.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnknownTlv {
+    #[serde(rename = "type")]
+    pub tlv_type: u64,
+    #[serde(with = "hex_bytes")]
+    pub value: Vec<u8>,
+}
+
+impl OrderOffer {
+    /// The checks node-dlc's `OrderOffer.validate` applies.
+    pub fn validate(&self) -> Result<()> {
+        if self.protocol_version != PROTOCOL_VERSION {
+            bail!(
+                "Unsupported protocol version: {}, expected: {}",
+                self.protocol_version,
+                PROTOCOL_VERSION
+            );
+        }
+        if self.offer_collateral < MIN_OFFER_COLLATERAL {
+            bail!("offerCollateral must be at least {}", MIN_OFFER_COLLATERAL);
+        }
+        if (self.cet_locktime < LOCKTIME_THRESHOLD) != (self.refund_locktime < LOCKTIME_THRESHOLD) {
+            bail!("cetLocktime and refundLocktime must be in the same units");
+        }
+        if self.cet_locktime >= self.refund_locktime {
+            bail!("cetLocktime must be less than refundLocktime");
+        }
+        let (total_collateral, _) = contract_infos(&self.contract_info);
+        if total_collateral <= self.offer_collateral {
+            bail!(
+                "totalCollateral {} must be greater than offerCollateral {}",
+                total_collateral,
+                self.offer_collateral
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Whether an order offer body starts with its protocol version. Offers from
+/// before protocolVersion was added start with the flags byte; node-dlc tells
+/// them apart with the same heuristic.
+pub fn has_protocol_version(body: &[u8]) -> bool {
+    let mut peek = Reader::new(body);
+    matches!(
+        (peek.u32("protocolVersion"), peek.u8("contractFlags")),
+        (Ok(1..=10), Ok(0))
+    )
+}
+
+impl NodeMessage for OrderOffer {
+    const TYPE: u64 = ORDER_OFFER_TYPE as u64;
+    const NAME: &'static str = "OrderOffer";
+    const FRAMING: Framing = Framing::Wire;
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        let protocol_version = if has_protocol_version(r.remaining()) {
+            r.u32("protocolVersion")?
+        } else {
+            PROTOCOL_VERSION
+        };
+
+        let mut offer = OrderOffer {
+            protocol_version,
+            contract_flags: r.u8("contractFlags")?,
+            chain_hash: r.array("chainHash")?,
+            temporary_contract_id: r.array("temporaryContractId")?,
+            contract_info: r.readable("contractInfo")?,
+            offer_collateral: r.u64("offerCollateral")?,
+            fee_rate_per_vb: r.u64("feeRatePerVb")?,
+            cet_locktime: r.u32("cetLocktime")?,
+            refund_locktime: r.u32("refundLocktime")?,
+            metadata: None,
+            irc_info: None,
+            position_info: None,
+            batch_funding_groups: Vec::new(),
+            unknown_tlvs: Vec::new(),
+        };
+
+        while !r.is_empty() {
+            let tlv_type = r.bigsize("type")?;
+            let value = r.var_bytes("value")?;
+            match tlv_type {
+                OrderMetadata::TYPE => offer.metadata = Some(OrderMetadata::from_body(value)?),
+                OrderIrcInfo::TYPE => offer.irc_info = Some(OrderIrcInfo::from_body(value)?),
+                OrderPositionInfo::TYPE => {
+                    offer.position_info = Some(OrderPositionInfo::from_body(value)?)
+                }
+                BatchFundingGroup::TYPE => offer
+                    .batch_funding_groups
+                    .push(BatchFundingGroup::from_body(value)?),
+                _ => offer.unknown_tlvs.push(UnknownTlv {
+                    tlv_type,
+                    value: value.to_vec(),
+                }),
+            }
+        }
+        Ok(offer)
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.u32(self.protocol_version);
+        w.u8(self.contract_flags);
+        w.bytes(&self.chain_hash);
+        w.bytes(&self.temporary_contract_id);
+        w.writeable(&self.contract_info);
+        w.u64(self.offer_collateral);
+        w.u64(self.fee_rate_per_vb);
+        w.u32(self.cet_locktime);
+        w.u32(self.refund_locktime);
+
+        if let Some(metadata) = &self.metadata {
+            w.bytes(&metadata.encode());
+        }
+        if let Some(irc_info) = &self.irc_info {
+            w.bytes(&irc_info.encode());
+        }
+        if let Some(position_info) = &self.position_info {
+            w.bytes(&position_info.encode());
+        }
+        for group in &self.batch_funding_groups {
+            w.bytes(&group.encode());
+        }
+        for tlv in &self.unknown_tlvs {
+            w.bigsize(tlv.tlv_type);
+            w.var_bytes(&tlv.value);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderAccept {
+    #[serde(with = "hex_array")]
+    pub temp_order_id: [u8; 32],
+    #[serde(default)]
+    pub negotiation_fields: Option<OrderNegotiationFields>,
+}
+
+/// Terms the accepting party proposes instead of the offered ones.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "variant")]
+pub enum OrderNegotiationFields {
+    Single,
+    Disjoint {
+        #[serde(rename = "orderOffer")]
+        order_offer: Box<OrderOffer>,
+    },
+}
+
+impl NodeMessage for OrderAccept {
+    const TYPE: u64 = ORDER_ACCEPT_TYPE as u64;
+    const NAME: &'static str = "OrderAccept";
+    const FRAMING: Framing = Framing::Wire;
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        let temp_order_id = r.array("tempOrderId")?;
+        let negotiation_fields = match r.u8("negotiationFields flag")? {
+            0 => None,
+            1 => Some(match r.bigsize("negotiationFields variant")? {
+                0 => OrderNegotiationFields::Single,
+                1 => {
+                    let mut offer = Reader::new(r.var_bytes("orderOffer")?);
+                    let order_offer = OrderOffer::read(&mut offer)?;
+                    OrderNegotiationFields::Disjoint {
+                        order_offer: Box::new(order_offer),
+                    }
+                }
+                variant => bail!("Unknown negotiationFields variant {}", variant),
+            }),
+            flag => bail!("Invalid negotiationFields flag {}", flag),
+        };
+        Ok(OrderAccept {
+            temp_order_id,
+            negotiation_fields,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.bytes(&self.temp_order_id);
+        match &self.negotiation_fields {
+            None => w.u8(0),
+            Some(OrderNegotiationFields::Single) => {
+                w.u8(1);
+                w.bigsize(0);
+            }
+            Some(OrderNegotiationFields::Disjoint { order_offer }) => {
+                w.u8(1);
+                w.bigsize(1);
+                w.var_bytes(&order_offer.encode());
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderMetadata {
+    pub offer_id: String,
+    #[serde(default)]
+    pub created_at: u32,
+    #[serde(default)]
+    pub good_till: u32,
+}
+
+impl NodeMessage for OrderMetadata {
+    const TYPE: u64 = 62774;
+    const NAME: &'static str = "OrderMetadata";
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        let offer_id = r.string("offerId")?;
+        // Early node-dlc releases wrote only the offer id.
+        let (created_at, good_till) = if r.is_empty() {
+            (0, 0)
+        } else {
+            (r.u32("createdAt")?, r.u32("goodTill")?)
+        };
+        Ok(OrderMetadata {
+            offer_id,
+            created_at,
+            good_till,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.string(&self.offer_id);
+        w.u32(self.created_at);
+        w.u32(self.good_till);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderIrcInfo {
+    pub nick: String,
+    #[serde(with = "hex_array")]
+    pub pub_key: [u8; 33],
+}
+
+impl NodeMessage for OrderIrcInfo {
+    const TYPE: u64 = 62776;
+    const NAME: &'static str = "OrderIrcInfo";
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(OrderIrcInfo {
+            nick: r.string("nick")?,
+            pub_key: r.array("pubKey")?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.string(&self.nick);
+        w.bytes(&self.pub_key);
+    }
+}
+
+/// Which party the fees are shifted to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DlcParty {
+    #[default]
+    Neither,
+    Offeror,
+    Acceptor,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Neither,
+    Buy,
+    Sell,
+}
+
+/// Position details; the instrument fields are only encoded when
+/// `instrumentName` is set.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderPositionInfo {
+    #[serde(default)]
+    pub shift_for_fees: DlcParty,
+    pub fees: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument_name: Option<String>,
+    #[serde(default)]
+    pub contract_size: u64,
+    #[serde(default)]
+    pub direction: Direction,
+    #[serde(default)]
+    pub price: u64,
+    #[serde(default)]
+    pub extra_precision: u16,
+}
+
+impl NodeMessage for OrderPositionInfo {
+    const TYPE: u64 = 62778;
+    const NAME: &'static str = "OrderPositionInfo";
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        let shift_for_fees = match r.u8("shiftForFees")? {
+            0 => DlcParty::Neither,
+            1 => DlcParty::Offeror,
+            2 => DlcParty::Acceptor,
+            value => bail!("Invalid shiftForFees value {}", value),
+        };
+        let mut info = OrderPositionInfo {
+            shift_for_fees,
+            fees: r.u64("fees")?,
+            instrument_name: None,
+            contract_size: 0,
+            direction: Direction::Neither,
+            price: 0,
+            extra_precision: 0,
+        };
+        if !r.is_empty() {
+            info.instrument_name = Some(r.string("instrumentName")?);
+            info.contract_size = r.u64("contractSize")?;
+            info.direction = match r.u8("direction")? {
+                0 => Direction::Neither,
+                1 => Direction::Buy,
+                2 => Direction::Sell,
+                value => bail!("Invalid direction value {}", value),
+            };
+            info.price = r.u64("price")?;
+            info.extra_precision = r.u16("extraPrecision")?;
+        }
+        Ok(info)
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.u8(match self.shift_for_fees {
+            DlcParty::Neither => 0,
+            DlcParty::Offeror => 1,
+            DlcParty::Acceptor => 2,
+        });
+        w.u64(self.fees);
+        // node-dlc skips the instrument fields for an empty name too.
+        if let Some(name) = self
+            .instrument_name
+            .as_deref()
+            .filter(|name| !name.is_empty())
+        {
+            w.string(name);
+            w.u64(self.contract_size);
+            w.u8(match self.direction {
+                Direction::Neither => 0,
+                Direction::Buy => 1,
+                Direction::Sell => 2,
+            });
+            w.u64(self.price);
+            w.u16(self.extra_precision);
+        }
+    }
+}
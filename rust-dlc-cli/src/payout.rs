@@ -0,0 +1,163 @@
+//! Evaluation of numeric payout functions into contiguous outcome ranges.
+//!
+//! This mirrors the payout curve handling of rust-dlc's manager crate so that
+//! we can derive CETs from the message types alone.
+
+use anyhow::{bail, Result};
+use dlc_messages::contract_msgs::{
+    HyperbolaPayoutCurvePiece, NumericOutcomeContractDescriptor, PayoutCurvePiece, PayoutPoint,
+    PolynomialPayoutCurvePiece, RoundingIntervals,
+};
+use serde::Serialize;
+
+/// A contiguous range of outcomes that all pay the offer party the same amount.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangePayout {
+    pub start: u64,
+    pub count: u64,
+    pub offer_payout: u64,
+}
+
+impl RangePayout {
+    pub fn end(&self) -> u64 {
+        self.start + self.count - 1
+    }
+}
+
+fn point_value(point: &PayoutPoint) -> (f64, f64) {
+    (
+        point.event_outcome as f64,
+        point.outcome_payout as f64 + point.extra_precision as f64 / (1 << 16) as f64,
+    )
+}
+
+fn evaluate_polynomial(
+    piece: &PolynomialPayoutCurvePiece,
+    left: &PayoutPoint,
+    right: &PayoutPoint,
+    outcome: u64,
+) -> f64 {
+    // Lagrange interpolation over the piece endpoints and its interior points
+    let mut points = vec![point_value(left)];
+    points.extend(piece.payout_points.iter().map(point_value));
+    points.push(point_value(right));
+
+    let x = outcome as f64;
+    let mut result = 0.0;
+    for (i, (xi, yi)) in points.iter().enumerate() {
+        let mut term = *yi;
+        for (j, (xj, _)) in points.iter().enumerate() {
+            if i != j {
+                term *= (x - xj) / (xi - xj);
+            }
+        }
+        result += term;
+    }
+    result
+}
+
+fn evaluate_hyperbola(piece: &HyperbolaPayoutCurvePiece, outcome: u64) -> f64 {
+    let translated = outcome as f64 - piece.translate_outcome;
+    let sqrt_term = (translated * translated - 4.0 * piece.a * piece.b).sqrt();
+    let x_hat = if piece.use_positive_piece {
+        (translated + sqrt_term) / (2.0 * piece.a)
+    } else {
+        (translated - sqrt_term) / (2.0 * piece.a)
+    };
+    piece.c * x_hat + piece.d / x_hat + piece.translate_payout
+}
+
+/// Round a payout using the rounding interval that applies to `outcome`.
+pub fn round_payout(intervals: &RoundingIntervals, outcome: u64, payout: f64) -> Result<f64> {
+    let rounding_mod = match intervals
+        .intervals
+        .iter()
+        .rev()
+        .find(|interval| interval.begin_interval <= outcome)
+    {
+        Some(interval) => interval.rounding_mod as f64,
+        None => bail!("No rounding interval covers outcome {}", outcome),
+    };
+
+    if rounding_mod <= 0.0 {
+        bail!("Rounding modulus must be positive for outcome {}", outcome);
+    }
+
+    let remainder = payout.rem_euclid(rounding_mod);
+    let rounded = if remainder >= rounding_mod / 2.0 {
+        payout + rounding_mod - remainder
+    } else {
+        payout - remainder
+    };
+    Ok(rounded.round())
+}
+
+/// Evaluate the rounded offer payout for every outcome of the descriptor and
+/// group equal consecutive payouts into ranges.
+pub fn range_payouts(
+    descriptor: &NumericOutcomeContractDescriptor,
+    total_collateral: u64,
+) -> Result<Vec<RangePayout>> {
+    let function = &descriptor.payout_function;
+    if function.payout_function_pieces.is_empty() {
+        bail!("Payout function has no pieces");
+    }
+
+    let mut ranges: Vec<RangePayout> = Vec::new();
+    for (i, piece) in function.payout_function_pieces.iter().enumerate() {
+        let left = &piece.end_point;
+        let right = function
+            .payout_function_pieces
+            .get(i + 1)
+            .map(|next| &next.end_point)
+            .unwrap_or(&function.last_endpoint);
+
+        if right.event_outcome < left.event_outcome {
+            bail!("Payout function pieces are not in increasing outcome order");
+        }
+
+        // Pieces share their endpoints, only the first piece owns its left one
+        let start = if i == 0 {
+            left.event_outcome
+        } else {
+            left.event_outcome + 1
+        };
+        for outcome in start..=right.event_outcome {
+            let raw = match &piece.payout_curve_piece {
+                PayoutCurvePiece::PolynomialPayoutCurvePiece(p) => {
+                    evaluate_polynomial(p, left, right, outcome)
+                }
+                PayoutCurvePiece::HyperbolaPayoutCurvePiece(h) => evaluate_hyperbola(h, outcome),
+            };
+            // rust-dlc rejects a payout curve that leaves the collateral
+            // bounds rather than clipping it; only rounding up may overshoot.
+            if raw.is_nan() || raw < 0.0 {
+                bail!("Payout {} at outcome {} is negative", raw, outcome);
+            }
+            if raw > total_collateral as f64 {
+                bail!(
+                    "Payout {} at outcome {} exceeds the total collateral {}",
+                    raw,
+                    outcome,
+                    total_collateral
+                );
+            }
+            let rounded = round_payout(&descriptor.rounding_intervals, outcome, raw)?;
+            let payout = (rounded as u64).min(total_collateral);
+
+            match ranges.last_mut() {
+                Some(last) if last.offer_payout == payout && last.end() + 1 == outcome => {
+                    last.count += 1;
+                }
+                _ => ranges.push(RangePayout {
+                    start: outcome,
+                    count: 1,
+                    offer_payout: payout,
+                }),
+            }
+        }
+    }
+
+    Ok(ranges)
+}
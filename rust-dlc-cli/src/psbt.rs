@@ -0,0 +1,341 @@
+//! The funding transaction as a BIP174 PSBT, so the funding inputs can be
+//! signed by an external wallet, and signed PSBTs turned back into the
+//! `FundingSignatures` a sign message carries.
+
+use crate::codec::{decode_hex, read_accept, read_offer};
+use crate::transactions::{
+    build_dlc_transactions, fund_output_index, funding_input_outpoint, funding_input_prev_tx,
+};
+use crate::{output_error, output_success_with_data};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use bitcoin::bip32::{DerivationPath, Fingerprint, KeySource};
+use bitcoin::psbt::Input;
+use bitcoin::sighash::EcdsaSighashType;
+use bitcoin::{CompressedPublicKey, Psbt, ScriptBuf};
+use dlc::make_funding_redeemscript;
+use dlc_messages::{
+    AcceptDlc, FundingInput, FundingSignature, FundingSignatures, OfferDlc, WitnessElement,
+};
+use lightning::util::ser::Writeable;
+use secp256k1_zkp::PublicKey;
+use serde_json::json;
+
+/// Parse a `--key-origin` value of the form `PUBKEY=FINGERPRINT/PATH`.
+fn parse_key_origin(value: &str) -> Result<(PublicKey, KeySource)> {
+    let (pubkey, origin) = value.split_once('=').ok_or_else(|| {
+        anyhow!(
+            "Key origin {} is not of the form PUBKEY=FINGERPRINT/PATH",
+            value
+        )
+    })?;
+    let pubkey: PublicKey = pubkey
+        .parse()
+        .with_context(|| format!("Invalid public key in key origin {}", value))?;
+    let (fingerprint, path) = origin.split_once('/').unwrap_or((origin, ""));
+    let fingerprint: Fingerprint = fingerprint
+        .parse()
+        .with_context(|| format!("Invalid fingerprint in key origin {}", value))?;
+    let path: DerivationPath = path
+        .parse()
+        .with_context(|| format!("Invalid derivation path in key origin {}", value))?;
+    Ok((pubkey, (fingerprint, path)))
+}
+
+fn p2wpkh(pubkey: &PublicKey) -> ScriptBuf {
+    ScriptBuf::new_p2wpkh(&CompressedPublicKey(*pubkey).wpubkey_hash())
+}
+
+/// Whether `pubkey` is one of the keys spending a funding input: the key of
+/// a P2WPKH (or P2SH-wrapped P2WPKH) output, or either key of a DLC input's
+/// 2-of-2.
+fn spends_input(pubkey: &PublicKey, input: &FundingInput, script_pubkey: &ScriptBuf) -> bool {
+    match &input.dlc_input {
+        Some(dlc_input) => {
+            *pubkey == dlc_input.local_fund_pubkey || *pubkey == dlc_input.remote_fund_pubkey
+        }
+        None => {
+            let script = p2wpkh(pubkey);
+            *script_pubkey == script || input.redeem_script == script
+        }
+    }
+}
+
+/// The funding inputs of both parties, with the party each belongs to.
+fn party_inputs<'a>(
+    offer: &'a OfferDlc,
+    accept: &'a AcceptDlc,
+) -> impl Iterator<Item = (&'static str, &'a FundingInput)> {
+    offer
+        .funding_inputs
+        .iter()
+        .map(|input| ("offer", input))
+        .chain(accept.funding_inputs.iter().map(|input| ("accept", input)))
+}
+
+/// Build the funding transaction of an offer/accept pair as a PSBT, with the
+/// spent outputs, scripts and any known BIP32 origins of the signing keys.
+pub fn funding_psbt(offer_hex: &str, accept_hex: &str, key_origins: &[String]) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let accept = match read_accept(accept_hex) {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode accept: {}", e)),
+    };
+    let origins = match key_origins
+        .iter()
+        .map(|value| parse_key_origin(value))
+        .collect::<Result<Vec<_>>>()
+    {
+        Ok(origins) => origins,
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+    let transactions = match build_dlc_transactions(&offer, &accept) {
+        Ok(transactions) => transactions,
+        Err(e) => return output_error(&e.to_string()),
+    };
+    let output_index = fund_output_index(&transactions)?;
+    let mut psbt = Psbt::from_unsigned_tx(transactions.fund.clone())
+        .map_err(|e| anyhow!("Failed to create PSBT: {}", e))?;
+
+    let mut inputs = Vec::new();
+    for (index, txin) in transactions.fund.input.iter().enumerate() {
+        let (party, input) = party_inputs(&offer, &accept)
+            .find(|(_, input)| funding_input_outpoint(input).ok() == Some(txin.previous_output))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Funding transaction input {} matches no funding input",
+                    index
+                )
+            })?;
+        let prev_tx = funding_input_prev_tx(input)?;
+        let spent = prev_tx
+            .output
+            .get(input.prev_tx_vout as usize)
+            .cloned()
+            .ok_or_else(|| anyhow!("Previous transaction has no output {}", input.prev_tx_vout))?;
+
+        let psbt_input = &mut psbt.inputs[index];
+        if !input.redeem_script.is_empty() {
+            psbt_input.redeem_script = Some(input.redeem_script.clone());
+        }
+        if let Some(dlc_input) = &input.dlc_input {
+            psbt_input.witness_script = Some(make_funding_redeemscript(
+                &dlc_input.local_fund_pubkey,
+                &dlc_input.remote_fund_pubkey,
+            ));
+        }
+        for (pubkey, source) in &origins {
+            if spends_input(pubkey, input, &spent.script_pubkey) {
+                psbt_input.bip32_derivation.insert(*pubkey, source.clone());
+            }
+        }
+        psbt_input.sighash_type = Some(EcdsaSighashType::All.into());
+        psbt_input.witness_utxo = Some(spent.clone());
+        psbt_input.non_witness_utxo = Some(prev_tx);
+
+        inputs.push(json!({
+            "index": index,
+            "party": party,
+            "serialId": input.input_serial_id,
+            "outpoint": txin.previous_output.to_string(),
+            "value": spent.value.to_sat(),
+            "dlcInput": input.dlc_input.is_some(),
+            "derivations": psbt_input.bip32_derivation.len(),
+        }));
+    }
+
+    let funding_keys = [offer.funding_pubkey, accept.funding_pubkey];
+    for (index, output) in transactions.fund.output.iter().enumerate() {
+        let psbt_output = &mut psbt.outputs[index];
+        if index == output_index as usize {
+            psbt_output.witness_script = Some(transactions.funding_script_pubkey.clone());
+        }
+        for (pubkey, source) in &origins {
+            let controls = if index == output_index as usize {
+                funding_keys.contains(pubkey)
+            } else {
+                output.script_pubkey == p2wpkh(pubkey)
+            };
+            if controls {
+                psbt_output.bip32_derivation.insert(*pubkey, source.clone());
+            }
+        }
+    }
+
+    let fee = psbt
+        .fee()
+        .map_err(|e| anyhow!("Failed to compute funding fee: {}", e))?;
+    let data = json!({
+        "psbt": BASE64.encode(psbt.serialize()),
+        "fundTxid": transactions.fund.compute_txid().to_string(),
+        "fundOutputIndex": output_index,
+        "fee": fee.to_sat(),
+        "inputs": inputs,
+    });
+
+    output_success_with_data("funding-psbt", &data, "Built funding transaction PSBT")
+}
+
+/// Read a PSBT given as base64 or hex.
+fn read_psbt(text: &str) -> Result<Psbt> {
+    let text = text.trim();
+    let bytes = match decode_hex(text) {
+        Ok(bytes) => bytes,
+        Err(_) => BASE64
+            .decode(text)
+            .context("PSBT is neither hex nor base64")?,
+    };
+    Psbt::deserialize(&bytes).map_err(|e| anyhow!("Failed to parse PSBT: {}", e))
+}
+
+/// Witness stack for a funding input from its (combined) PSBT input: the
+/// finalized witness if a signer already produced one, otherwise one built
+/// from the partial signatures.
+fn input_witness(psbt_input: &Input, input: &FundingInput) -> Result<Vec<Vec<u8>>> {
+    if let Some(witness) = &psbt_input.final_script_witness {
+        return Ok(witness.to_vec());
+    }
+    match &input.dlc_input {
+        Some(dlc_input) => {
+            let script = make_funding_redeemscript(
+                &dlc_input.local_fund_pubkey,
+                &dlc_input.remote_fund_pubkey,
+            );
+            // CHECKMULTISIG takes signatures in redeem script key order.
+            let mut pubkeys = [dlc_input.local_fund_pubkey, dlc_input.remote_fund_pubkey];
+            pubkeys.sort_by_key(|pk| pk.serialize());
+            let mut witness = vec![Vec::new()];
+            for pubkey in pubkeys {
+                let signature = psbt_input
+                    .partial_sigs
+                    .get(&bitcoin::PublicKey::new(pubkey))
+                    .ok_or_else(|| anyhow!("Missing signature for 2-of-2 key {}", pubkey))?;
+                witness.push(signature.to_vec());
+            }
+            witness.push(script.to_bytes());
+            Ok(witness)
+        }
+        None => {
+            let mut partial_sigs = psbt_input.partial_sigs.iter();
+            match (partial_sigs.next(), partial_sigs.next()) {
+                (Some((pubkey, signature)), None) => {
+                    Ok(vec![signature.to_vec(), pubkey.to_bytes()])
+                }
+                (None, _) => bail!("Input is not signed"),
+                (Some(_), Some(_)) => bail!(
+                    "Input has {} partial signatures, expected one P2WPKH signature",
+                    psbt_input.partial_sigs.len()
+                ),
+            }
+        }
+    }
+}
+
+/// Combine signed funding PSBTs and extract one party's funding signatures,
+/// in the order of that party's funding inputs, as carried by a sign
+/// message.
+pub fn finalize_funding(
+    offer_hex: &str,
+    accept_hex: &str,
+    psbts: &[String],
+    party: &str,
+) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let accept = match read_accept(accept_hex) {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode accept: {}", e)),
+    };
+    let funding_inputs = match party {
+        "offer" => &offer.funding_inputs,
+        "accept" => &accept.funding_inputs,
+        _ => {
+            return output_error(&format!(
+                "Unsupported party: {} (expected offer or accept)",
+                party
+            ))
+        }
+    };
+    let transactions = match build_dlc_transactions(&offer, &accept) {
+        Ok(transactions) => transactions,
+        Err(e) => return output_error(&e.to_string()),
+    };
+    let fund_txid = transactions.fund.compute_txid();
+
+    let mut combined: Option<Psbt> = None;
+    for (index, text) in psbts.iter().enumerate() {
+        let psbt = match read_psbt(text) {
+            Ok(psbt) => psbt,
+            Err(e) => return output_error(&format!("PSBT {}: {:#}", index, e)),
+        };
+        let txid = psbt.unsigned_tx.compute_txid();
+        if txid != fund_txid {
+            return output_error(&format!(
+                "PSBT {} spends into transaction {}, not the funding transaction {}",
+                index, txid, fund_txid
+            ));
+        }
+        match combined.as_mut() {
+            Some(combined) => {
+                if let Err(e) = combined.combine(psbt) {
+                    return output_error(&format!("Failed to combine PSBT {}: {}", index, e));
+                }
+            }
+            None => combined = Some(psbt),
+        }
+    }
+    let Some(psbt) = combined else {
+        return output_error("No PSBT given");
+    };
+
+    let signatures = funding_inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            let outpoint = funding_input_outpoint(input)?;
+            let index = psbt
+                .unsigned_tx
+                .input
+                .iter()
+                .position(|txin| txin.previous_output == outpoint)
+                .ok_or_else(|| anyhow!("Funding transaction does not spend {}", outpoint))?;
+            let witness = input_witness(&psbt.inputs[index], input)
+                .with_context(|| format!("Funding input {} ({})", i, outpoint))?;
+            Ok(FundingSignature {
+                witness_elements: witness
+                    .into_iter()
+                    .map(|witness| WitnessElement { witness })
+                    .collect(),
+            })
+        })
+        .collect::<Result<Vec<_>>>();
+    let funding_signatures = match signatures {
+        Ok(funding_signatures) => FundingSignatures { funding_signatures },
+        Err(e) => return output_error(&format!("{:#}", e)),
+    };
+
+    let data = json!({
+        "party": party,
+        "fundTxid": fund_txid.to_string(),
+        "psbt": BASE64.encode(psbt.serialize()),
+        "fundingSignatures": serde_json::to_value(&funding_signatures)
+            .context("Failed to convert funding signatures to JSON")?,
+        "hex": hex::encode(funding_signatures.encode()),
+    });
+
+    output_success_with_data(
+        "finalize-funding",
+        &data,
+        &format!(
+            "Extracted {} funding signatures for the {} party",
+            funding_signatures.funding_signatures.len(),
+            party
+        ),
+    )
+}
@@ -0,0 +1,147 @@
+//! The refund transaction of an offer/accept pair and the refund signatures
+//! the accept and sign messages carry for it.
+
+use crate::codec::{read_accept, read_offer, read_sign};
+use crate::flow::{check_refund_signature, CheckStatus, FlowCheck};
+use crate::locktime::locktime_json;
+use crate::transactions::{build_dlc_transactions, fund_output_index, transaction_json};
+use crate::{output_error, output_success_with_data};
+use anyhow::{anyhow, Result};
+use bitcoin::hashes::Hash;
+use bitcoin::sighash::{EcdsaSighashType, SighashCache};
+use serde_json::json;
+
+/// Rebuild the refund transaction of an offer/accept pair, with each party's
+/// refund output and the sighash both parties sign.
+pub fn build_refund(offer_hex: &str, accept_hex: &str) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let accept = match read_accept(accept_hex) {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode accept: {}", e)),
+    };
+    let transactions = match build_dlc_transactions(&offer, &accept) {
+        Ok(transactions) => transactions,
+        Err(e) => return output_error(&e.to_string()),
+    };
+    let refund = &transactions.refund;
+    let output_index = fund_output_index(&transactions)?;
+    let fund_value = transactions.fund.output[output_index as usize].value;
+    let sighash = SighashCache::new(refund)
+        .p2wsh_signature_hash(
+            0,
+            &transactions.funding_script_pubkey,
+            fund_value,
+            EcdsaSighashType::All,
+        )
+        .map_err(|e| anyhow!("Failed to compute refund sighash: {}", e))?;
+
+    // The refund returns each party's collateral to its payout script; an
+    // output below the dust limit is dropped from the transaction.
+    let mut warnings = Vec::new();
+    let payouts: Vec<_> = [
+        ("offer", &offer.payout_spk, offer.offer_collateral),
+        ("accept", &accept.payout_spk, accept.accept_collateral),
+    ]
+    .into_iter()
+    .map(|(party, payout_spk, collateral)| {
+        let index = refund.output.iter().position(|output| {
+            output.script_pubkey == *payout_spk && output.value.to_sat() == collateral
+        });
+        if index.is_none() && collateral > 0 {
+            warnings.push(format!(
+                "Refund has no output returning the {} party's collateral of {} sat",
+                party, collateral
+            ));
+        }
+        json!({
+            "party": party,
+            "scriptPubKey": payout_spk.to_hex_string(),
+            "collateral": collateral,
+            "outputIndex": index,
+        })
+    })
+    .collect();
+
+    let data = json!({
+        "refundLocktime": locktime_json(offer.refund_locktime),
+        "fundTxid": transactions.fund.compute_txid().to_string(),
+        "fundOutputIndex": output_index,
+        "fundOutputValue": fund_value.to_sat(),
+        "sighash": hex::encode(sighash.to_byte_array()),
+        "payouts": payouts,
+        "refundTransaction": transaction_json(refund)?,
+        "warnings": warnings,
+    });
+
+    output_success_with_data(
+        "refund-transaction",
+        &data,
+        "Built refund transaction from offer and accept",
+    )
+}
+
+/// Verify the refund signature of an accept message against the accepting
+/// party's funding pubkey and, when given, that of a sign message against
+/// the offering party's.
+pub fn verify_refund_sig(offer_hex: &str, accept_hex: &str, sign_hex: Option<&str>) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let accept = match read_accept(accept_hex) {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode accept: {}", e)),
+    };
+    let sign = match sign_hex.map(read_sign).transpose() {
+        Ok(sign) => sign,
+        Err(e) => return output_error(&format!("Failed to decode sign: {}", e)),
+    };
+    let transactions = match build_dlc_transactions(&offer, &accept) {
+        Ok(transactions) => transactions,
+        Err(e) => return output_error(&e.to_string()),
+    };
+
+    let checks = vec![
+        FlowCheck::new(
+            "acceptRefundSignature",
+            check_refund_signature(
+                &transactions,
+                &accept.refund_signature,
+                &accept.funding_pubkey,
+            ),
+        ),
+        match &sign {
+            Some(sign) => FlowCheck::new(
+                "signRefundSignature",
+                check_refund_signature(
+                    &transactions,
+                    &sign.refund_signature,
+                    &offer.funding_pubkey,
+                ),
+            ),
+            None => FlowCheck::skip("signRefundSignature", "No sign message provided"),
+        },
+    ];
+    let valid = checks.iter().all(|check| check.status != CheckStatus::Fail);
+
+    let output = json!({
+        "status": if valid { "success" } else { "error" },
+        "messageType": "refund-signature-verification",
+        "data": {
+            "valid": valid,
+            "refundTxid": transactions.refund.compute_txid().to_string(),
+            "checks": checks,
+        },
+        "message": if valid {
+            "Refund signatures verify against the funding pubkeys"
+        } else {
+            "One or more refund signatures do not verify"
+        }
+    });
+
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
@@ -0,0 +1,224 @@
+//! Pre-trade summary of an offer: what each party puts in, what it can get
+//! out, when it is liquidated, what it pays in fees and when the contract
+//! matures and refunds.
+
+use crate::cets::{
+    contract_cets, contract_infos, oracle_announcements, outcome_summary, sub_contract_ranges,
+    CetInfo,
+};
+use crate::codec::{read_accept, read_offer};
+use crate::locktime::{format_timestamp, locktime_json, LOCKTIME_THRESHOLD};
+use crate::{output_error, output_success_with_data};
+use anyhow::Result;
+use bitcoin::ScriptBuf;
+use dlc_messages::FundingInput;
+use serde_json::{json, Value};
+
+/// Shared weight of the funding transaction, split evenly between parties.
+const FUND_TX_BASE_WEIGHT: u64 = 214;
+/// Shared weight of a CET or the refund, split evenly between parties.
+const CET_BASE_WEIGHT: u64 = 500;
+/// Weight of a funding input before its script sig and witness.
+const TX_INPUT_BASE_WEIGHT: u64 = 164;
+/// Weight of an output's value and script length, before the script.
+const OUTPUT_BASE_WEIGHT: u64 = 36;
+/// Witness length of a P2WPKH input, assumed for an accepter not yet known.
+const P2WPKH_WITNESS_LEN: u64 = 107;
+/// Length of a P2WPKH script, assumed for an accepter not yet known.
+const P2WPKH_SCRIPT_LEN: u64 = 22;
+
+/// Fee for a weight at a fee rate, rounded up to whole vbytes as rust-dlc
+/// does.
+fn weight_to_fee(weight: u64, fee_rate_per_vb: u64) -> u64 {
+    weight.div_ceil(4) * fee_rate_per_vb
+}
+
+/// Length of the script sig pushing a P2SH redeem script, if any.
+fn script_sig_len(redeem_script: &ScriptBuf) -> u64 {
+    let len = redeem_script.len() as u64;
+    match len {
+        0 => 0,
+        1..=75 => len + 1,
+        76..=255 => len + 2,
+        _ => len + 3,
+    }
+}
+
+/// One party's share of the funding and CET fees, following rust-dlc's
+/// `PartyParams::get_change_output_and_fees`. Inputs are given as
+/// (script sig length, max witness length) pairs.
+fn party_fees(
+    inputs: &[(u64, u64)],
+    change_spk_len: u64,
+    payout_spk_len: u64,
+    fee_rate_per_vb: u64,
+    estimated: bool,
+) -> Value {
+    let inputs_weight: u64 = inputs
+        .iter()
+        .map(|(script_sig_len, witness_len)| {
+            TX_INPUT_BASE_WEIGHT + 4 * script_sig_len + witness_len
+        })
+        .sum();
+    let fund_weight =
+        FUND_TX_BASE_WEIGHT / 2 + inputs_weight + OUTPUT_BASE_WEIGHT + 4 * change_spk_len;
+    let cet_weight = CET_BASE_WEIGHT / 2 + OUTPUT_BASE_WEIGHT + 4 * payout_spk_len;
+    let fund_fee = weight_to_fee(fund_weight, fee_rate_per_vb);
+    let cet_fee = weight_to_fee(cet_weight, fee_rate_per_vb);
+    json!({
+        "fundingFee": fund_fee,
+        "cetOrRefundFee": cet_fee,
+        "total": fund_fee + cet_fee,
+        "estimated": estimated,
+    })
+}
+
+fn input_sizes(inputs: &[FundingInput]) -> Vec<(u64, u64)> {
+    inputs
+        .iter()
+        .map(|input| {
+            (
+                script_sig_len(&input.redeem_script),
+                u64::from(input.max_witness_len),
+            )
+        })
+        .collect()
+}
+
+/// Collateral, payout bounds and liquidation outcomes of one party.
+fn party_summary(
+    collateral: u64,
+    cets: &[Vec<CetInfo>],
+    payout: fn(&CetInfo) -> u64,
+    fees: Value,
+) -> Value {
+    let payouts = cets.iter().flatten().map(payout);
+    let liquidations: Vec<Value> = cets
+        .iter()
+        .enumerate()
+        .flat_map(|(contract, cets)| {
+            cets.iter().filter(|cet| payout(cet) == 0).map(move |cet| {
+                json!({
+                    "contract": contract,
+                    "outcome": outcome_summary(&cet.outcome),
+                })
+            })
+        })
+        .collect();
+    json!({
+        "collateral": collateral,
+        "minPayout": payouts.clone().min(),
+        "maxPayout": payouts.max(),
+        "liquidationOutcomes": liquidations,
+        "fees": fees,
+    })
+}
+
+/// Summarize an offer for each party, using the accept's inputs and scripts
+/// for the accepting party's fees when given and a single P2WPKH input
+/// otherwise.
+pub fn report(offer_hex: &str, accept_hex: Option<&str>) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let accept = match accept_hex.map(read_accept).transpose() {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode accept: {}", e)),
+    };
+    let cets = match contract_cets(&offer.contract_info) {
+        Ok(cets) => cets,
+        Err(e) => return output_error(&format!("Failed to enumerate CETs: {}", e)),
+    };
+    let (total_collateral, infos) = contract_infos(&offer.contract_info);
+    let mut warnings = Vec::new();
+
+    let accept_collateral = total_collateral.saturating_sub(offer.offer_collateral);
+    if offer.offer_collateral > total_collateral {
+        warnings.push(format!(
+            "Offer collateral {} exceeds the total collateral {}",
+            offer.offer_collateral, total_collateral
+        ));
+    }
+    if let Some(accept) = &accept {
+        if accept.accept_collateral != accept_collateral {
+            warnings.push(format!(
+                "Accept collateral {} differs from the {} the offer leaves",
+                accept.accept_collateral, accept_collateral
+            ));
+        }
+    }
+
+    let fee_rate = offer.fee_rate_per_vb;
+    let offer_fees = party_fees(
+        &input_sizes(&offer.funding_inputs),
+        offer.change_spk.len() as u64,
+        offer.payout_spk.len() as u64,
+        fee_rate,
+        false,
+    );
+    let accept_fees = match &accept {
+        Some(accept) => party_fees(
+            &input_sizes(&accept.funding_inputs),
+            accept.change_spk.len() as u64,
+            accept.payout_spk.len() as u64,
+            fee_rate,
+            false,
+        ),
+        None => party_fees(
+            &[(0, P2WPKH_WITNESS_LEN)],
+            P2WPKH_SCRIPT_LEN,
+            P2WPKH_SCRIPT_LEN,
+            fee_rate,
+            true,
+        ),
+    };
+
+    let maturities: Vec<Value> = infos
+        .iter()
+        .flat_map(|info| oracle_announcements(&info.oracle_info))
+        .map(|announcement| {
+            let event = &announcement.oracle_event;
+            if event.event_maturity_epoch >= offer.refund_locktime
+                && offer.refund_locktime >= LOCKTIME_THRESHOLD
+            {
+                warnings.push(format!(
+                    "Event {} matures at or after the refund locktime",
+                    event.event_id
+                ));
+            }
+            json!({
+                "eventId": event.event_id,
+                "maturityEpoch": event.event_maturity_epoch,
+                "date": format_timestamp(u64::from(event.event_maturity_epoch)),
+            })
+        })
+        .collect();
+
+    let data = json!({
+        "totalCollateral": total_collateral,
+        "feeRatePerVb": fee_rate,
+        "cetCount": cets.iter().map(Vec::len).sum::<usize>(),
+        "adaptorSignatureCount": cets.iter().flatten().map(|cet| cet.adaptor_points).sum::<u64>(),
+        "subContracts": sub_contract_ranges(&offer.contract_info, &cets),
+        "offer": party_summary(
+            offer.offer_collateral,
+            &cets,
+            |cet| cet.offer_payout,
+            offer_fees,
+        ),
+        "accept": party_summary(accept_collateral, &cets, |cet| cet.accept_payout, accept_fees),
+        "dates": {
+            "maturities": maturities,
+            "cetLocktime": locktime_json(offer.cet_locktime),
+            "refundLocktime": locktime_json(offer.refund_locktime),
+        },
+        "warnings": warnings,
+    });
+
+    output_success_with_data(
+        "contract-report",
+        &data,
+        "Summarized offer for both parties",
+    )
+}
@@ -0,0 +1,435 @@
+//! Records that node-dlc persists in LevelDB, and a reader for key/value
+//! pairs dumped from its DLC store.
+//!
+//! Besides the offer, accept and sign messages, `leveldb-dlc-store.ts` keeps
+//! one `DlcTransactions` record (type 61230) per contract, and the IRC and
+//! oracle stores keep `DlcIds` lists (61232). `DlcInfo` (61234) holds the
+//! per-store message counters. Keys are a one-byte prefix followed by the id
+//! the record is stored under.
+
+use crate::codec::decode_hex;
+use crate::decode::{decode_as, detect};
+use crate::output_error;
+use crate::wire::{self, hex_array, Framing, NodeMessage, Reader, Writer};
+use anyhow::{anyhow, bail, Result};
+use bitcoin::Transaction;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Wire type of a DlcTransactions record.
+pub const DLC_TRANSACTIONS_TYPE: u16 = 61230;
+/// Wire type of a DlcIds record.
+pub const DLC_IDS_TYPE: u16 = 61232;
+/// Wire type of a DlcInfo record.
+pub const DLC_INFO_TYPE: u16 = 61234;
+
+/// Serde helpers for transactions carried as hex of their consensus
+/// encoding. node-dlc length-prefixes them with a u16, which bounds the size.
+mod tx_hex {
+    use bitcoin::Transaction;
+    use serde::{Deserialize, Deserializer};
+
+    pub fn parse(bytes: &[u8]) -> Result<Transaction, String> {
+        if bytes.len() > u16::MAX as usize {
+            return Err(format!(
+                "transaction of {} bytes exceeds the u16 length prefix",
+                bytes.len()
+            ));
+        }
+        bitcoin::consensus::deserialize(bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let bytes = hex::decode(String::deserialize(d)?).map_err(serde::de::Error::custom)?;
+        parse(&bytes).map_err(serde::de::Error::custom)?;
+        Ok(bytes)
+    }
+
+    pub mod vec {
+        use serde::{Deserialize, Deserializer};
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Vec<u8>>, D::Error> {
+            Vec::<String>::deserialize(d)?
+                .into_iter()
+                .map(|text| {
+                    let bytes = hex::decode(text).map_err(serde::de::Error::custom)?;
+                    super::parse(&bytes).map_err(serde::de::Error::custom)?;
+                    Ok(bytes)
+                })
+                .collect()
+        }
+    }
+}
+
+/// How a stored contract was closed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CloseType {
+    #[default]
+    NotClosed,
+    ExecuteClose,
+    RefundClose,
+    CooperativeClose,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockEpoch {
+    #[serde(with = "hex_array")]
+    pub hash: [u8; 32],
+    pub height: u32,
+}
+
+impl BlockEpoch {
+    fn read(r: &mut Reader, name: &str) -> Result<Self> {
+        Ok(BlockEpoch {
+            hash: r.array(name)?,
+            height: r.u32(name)?,
+        })
+    }
+
+    fn write(&self, w: &mut Writer) {
+        w.bytes(&self.hash);
+        w.u32(self.height);
+    }
+}
+
+/// node-dlc's `DlcTransactions` store record (named apart from rust-dlc's
+/// `DlcTransactions`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DlcTransactionsRecord {
+    #[serde(with = "hex_array")]
+    pub contract_id: [u8; 32],
+    #[serde(
+        serialize_with = "wire::hex_bytes::serialize",
+        deserialize_with = "tx_hex::deserialize"
+    )]
+    pub fund_tx: Vec<u8>,
+    pub fund_tx_vout: u32,
+    #[serde(default)]
+    pub fund_epoch: BlockEpoch,
+    #[serde(default)]
+    pub fund_broadcast_height: u32,
+    #[serde(
+        serialize_with = "wire::hex_bytes::serialize",
+        deserialize_with = "tx_hex::deserialize"
+    )]
+    pub refund_tx: Vec<u8>,
+    #[serde(
+        serialize_with = "wire::hex_vec::serialize",
+        deserialize_with = "tx_hex::vec::deserialize"
+    )]
+    pub cets: Vec<Vec<u8>>,
+    #[serde(default)]
+    pub close_epoch: BlockEpoch,
+    #[serde(default, with = "hex_array")]
+    pub close_tx_hash: [u8; 32],
+    #[serde(default)]
+    pub close_type: CloseType,
+    #[serde(default)]
+    pub close_broadcast_height: u32,
+}
+
+fn read_tx(r: &mut Reader, name: &str) -> Result<Vec<u8>> {
+    let len = r.u16(name)?;
+    let bytes = r.take(len as usize, name)?;
+    tx_hex::parse(bytes).map_err(|e| anyhow!("Invalid {}: {}", name, e))?;
+    Ok(bytes.to_vec())
+}
+
+fn write_tx(w: &mut Writer, tx: &[u8]) {
+    w.u16(tx.len() as u16);
+    w.bytes(tx);
+}
+
+impl DlcTransactionsRecord {
+    /// Txids of the stored transactions, for display next to the record.
+    fn summary(&self) -> Result<Value> {
+        let fund_tx: Transaction = bitcoin::consensus::deserialize(&self.fund_tx)?;
+        let refund_tx: Transaction = bitcoin::consensus::deserialize(&self.refund_tx)?;
+        let fund_output = fund_tx
+            .output
+            .get(self.fund_tx_vout as usize)
+            .ok_or_else(|| anyhow!("Funding transaction has no output {}", self.fund_tx_vout))?;
+        Ok(json!({
+            "fundOutpoint": format!("{}:{}", fund_tx.compute_txid(), self.fund_tx_vout),
+            "fundValue": fund_output.value.to_sat(),
+            "fundScriptPubKey": hex::encode(fund_output.script_pubkey.as_bytes()),
+            "refundTxid": refund_tx.compute_txid().to_string(),
+            "cetCount": self.cets.len(),
+        }))
+    }
+}
+
+impl NodeMessage for DlcTransactionsRecord {
+    const TYPE: u64 = DLC_TRANSACTIONS_TYPE as u64;
+    const NAME: &'static str = "DlcTransactions";
+    const FRAMING: Framing = Framing::Wire;
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(DlcTransactionsRecord {
+            contract_id: r.array("contractId")?,
+            fund_tx: read_tx(r, "fundTx")?,
+            fund_tx_vout: r.u32("fundTxVout")?,
+            fund_epoch: BlockEpoch::read(r, "fundEpoch")?,
+            fund_broadcast_height: r.u32("fundBroadcastHeight")?,
+            refund_tx: read_tx(r, "refundTx")?,
+            cets: r.vec("cets", |r| read_tx(r, "cet"))?,
+            close_epoch: BlockEpoch::read(r, "closeEpoch")?,
+            close_tx_hash: r.array("closeTxHash")?,
+            close_type: match r.u8("closeType")? {
+                0 => CloseType::NotClosed,
+                1 => CloseType::ExecuteClose,
+                2 => CloseType::RefundClose,
+                3 => CloseType::CooperativeClose,
+                other => bail!("Invalid closeType {}", other),
+            },
+            close_broadcast_height: r.u32("closeBroadcastHeight")?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.bytes(&self.contract_id);
+        write_tx(w, &self.fund_tx);
+        w.u32(self.fund_tx_vout);
+        self.fund_epoch.write(w);
+        w.u32(self.fund_broadcast_height);
+        write_tx(w, &self.refund_tx);
+        w.vec(&self.cets, |w, cet| write_tx(w, cet));
+        self.close_epoch.write(w);
+        w.bytes(&self.close_tx_hash);
+        w.u8(match self.close_type {
+            CloseType::NotClosed => 0,
+            CloseType::ExecuteClose => 1,
+            CloseType::RefundClose => 2,
+            CloseType::CooperativeClose => 3,
+        });
+        w.u32(self.close_broadcast_height);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DlcIds {
+    #[serde(
+        serialize_with = "wire::hex_vec::serialize",
+        deserialize_with = "deserialize_ids"
+    )]
+    pub ids: Vec<Vec<u8>>,
+}
+
+/// Ids are written without a length prefix, so each must be 32 bytes.
+fn deserialize_ids<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<Vec<u8>>, D::Error> {
+    let ids = wire::hex_vec::deserialize(d)?;
+    if let Some(id) = ids.iter().find(|id| id.len() != 32) {
+        return Err(serde::de::Error::custom(format!(
+            "ids must be 32 bytes, got {}",
+            id.len()
+        )));
+    }
+    Ok(ids)
+}
+
+impl NodeMessage for DlcIds {
+    const TYPE: u64 = DLC_IDS_TYPE as u64;
+    const NAME: &'static str = "DlcIds";
+    const FRAMING: Framing = Framing::Wire;
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(DlcIds {
+            ids: r.vec("ids", |r| Ok(r.take(32, "id")?.to_vec()))?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.vec(&self.ids, |w, id| w.bytes(id));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DlcInfo {
+    pub num_dlc_offers: u32,
+    pub num_dlc_accepts: u32,
+    pub num_dlc_signs: u32,
+    pub num_dlc_cancels: u32,
+    pub num_dlc_closes: u32,
+    pub num_dlc_transactions: u32,
+}
+
+impl NodeMessage for DlcInfo {
+    const TYPE: u64 = DLC_INFO_TYPE as u64;
+    const NAME: &'static str = "DlcInfo";
+    const FRAMING: Framing = Framing::Wire;
+
+    fn read_body(r: &mut Reader) -> Result<Self> {
+        Ok(DlcInfo {
+            num_dlc_offers: r.u32("numDlcOffers")?,
+            num_dlc_accepts: r.u32("numDlcAccepts")?,
+            num_dlc_signs: r.u32("numDlcSigns")?,
+            num_dlc_cancels: r.u32("numDlcCancels")?,
+            num_dlc_closes: r.u32("numDlcCloses")?,
+            num_dlc_transactions: r.u32("numDlcTransactions")?,
+        })
+    }
+
+    fn write_body(&self, w: &mut Writer) {
+        w.u32(self.num_dlc_offers);
+        w.u32(self.num_dlc_accepts);
+        w.u32(self.num_dlc_signs);
+        w.u32(self.num_dlc_cancels);
+        w.u32(self.num_dlc_closes);
+        w.u32(self.num_dlc_transactions);
+    }
+}
+
+/// What a DLC store key holds, from its prefix byte.
+struct KeyKind {
+    prefix: u8,
+    name: &'static str,
+    /// Message type of the value, or `None` for contract id references.
+    message_type: Option<&'static str>,
+}
+
+const KEY_KINDS: &[KeyKind] = &[
+    KeyKind {
+        prefix: 50,
+        name: "DlcOffer",
+        message_type: Some("offer"),
+    },
+    KeyKind {
+        prefix: 51,
+        name: "DlcAccept",
+        message_type: Some("accept"),
+    },
+    KeyKind {
+        prefix: 52,
+        name: "DlcSign",
+        message_type: Some("sign"),
+    },
+    KeyKind {
+        prefix: 53,
+        name: "DlcTransactions",
+        message_type: Some("dlc-transactions"),
+    },
+    KeyKind {
+        prefix: 54,
+        name: "Outpoint",
+        message_type: None,
+    },
+    KeyKind {
+        prefix: 55,
+        name: "ScriptPubKey",
+        message_type: None,
+    },
+    KeyKind {
+        prefix: 58,
+        name: "TempContractId",
+        message_type: None,
+    },
+];
+
+fn key_json(key: &[u8]) -> Result<(Value, &'static KeyKind)> {
+    let Some((&prefix, id)) = key.split_first() else {
+        bail!("Key is empty");
+    };
+    let Some(kind) = KEY_KINDS.iter().find(|kind| kind.prefix == prefix) else {
+        bail!(
+            "Unknown or unsupported DLC store key prefix {} (cancel and close records cannot be decoded)",
+            prefix
+        );
+    };
+    // Outpoint keys hold the "txid:vout" string, the others raw bytes.
+    let id = match kind.name {
+        "Outpoint" => Value::String(String::from_utf8_lossy(id).into_owned()),
+        _ => Value::String(hex::encode(id)),
+    };
+    Ok((
+        json!({ "prefix": prefix, "record": kind.name, "id": id }),
+        kind,
+    ))
+}
+
+/// Decode a value dumped from node-dlc's LevelDB stores and check that it
+/// re-encodes to the same bytes. With `key_hex`, the key prefix selects the
+/// record type; without it the type is detected.
+pub fn inspect_store_record(value_hex: &str, key_hex: Option<&str>) -> Result<()> {
+    let value = match decode_hex(value_hex) {
+        Ok(value) => value,
+        Err(e) => return output_error(&e.to_string()),
+    };
+    let (key, kind) = match key_hex.map(|key| decode_hex(key).and_then(|key| key_json(&key))) {
+        None => (Value::Null, None),
+        Some(Ok((key, kind))) => (key, Some(kind)),
+        Some(Err(e)) => return output_error(&format!("Invalid key: {:#}", e)),
+    };
+
+    if let Some(KeyKind {
+        message_type: None,
+        name,
+        ..
+    }) = kind
+    {
+        if value.len() != 32 {
+            return output_error(&format!(
+                "{} keys reference a 32-byte contract id, got {} bytes",
+                name,
+                value.len()
+            ));
+        }
+        return crate::output_success_with_data(
+            "store-record",
+            &json!({ "key": key, "contractId": hex::encode(&value) }),
+            &format!("{} reference to contract {}", name, hex::encode(&value)),
+        );
+    }
+
+    let decoded = match kind.and_then(|kind| kind.message_type) {
+        Some(message_type) => decode_as(&value, message_type),
+        None => detect(&value),
+    };
+    let decoded = match decoded {
+        Ok(decoded) => decoded,
+        Err(e) => return output_error(&format!("Failed to decode record: {:#}", e)),
+    };
+
+    let trailing_bytes = value.len() - decoded.consumed;
+    let reencoded = crate::serialize_json(decoded.message_type, &decoded.data).ok();
+    let round_trip = reencoded.as_ref().map(|bytes| bytes[..] == value[..]);
+    let mut warnings = Vec::new();
+    if trailing_bytes > 0 {
+        warnings.push(format!("{} bytes after the record", trailing_bytes));
+    }
+    match round_trip {
+        Some(false) => warnings.push("Record does not re-encode to the stored bytes".to_string()),
+        None => warnings.push(format!(
+            "Re-encoding {} records is not supported",
+            decoded.message_type
+        )),
+        Some(true) => {}
+    }
+    let summary = if decoded.message_type == "dlc-transactions" {
+        serde_json::from_value::<DlcTransactionsRecord>(decoded.data.clone())
+            .map_err(anyhow::Error::from)
+            .and_then(|record| record.summary())
+            .unwrap_or_else(|e| {
+                warnings.push(format!("Failed to summarize transactions: {}", e));
+                Value::Null
+            })
+    } else {
+        Value::Null
+    };
+
+    let data = json!({
+        "key": key,
+        "recordType": decoded.message_type,
+        "record": decoded.data,
+        "summary": summary,
+        "roundTrip": round_trip,
+        "trailingBytes": trailing_bytes,
+        "warnings": warnings,
+    });
+    crate::output_success_with_data(
+        "store-record",
+        &data,
+        &format!("Decoded {} store record", decoded.message_type),
+    )
+}
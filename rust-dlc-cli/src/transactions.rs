@@ -0,0 +1,283 @@
+//! Reconstruction of the DLC transactions from an offer/accept pair.
+
+use crate::cets::{contract_cets, outcome_summary, sub_contract_ranges};
+use crate::codec::{read_accept, read_offer};
+use crate::{output_error, output_success_with_data};
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::hashes::Hash;
+use bitcoin::{Amount, OutPoint, Transaction, Txid};
+use dlc::{DlcInputInfo, DlcTransactions, PartyParams, Payout, TxInputInfo};
+use dlc_messages::{AcceptDlc, FundingInput, OfferDlc};
+use serde_json::Value;
+
+/// Parse the previous transaction referenced by a funding input.
+pub fn funding_input_prev_tx(input: &FundingInput) -> Result<Transaction> {
+    bitcoin::consensus::deserialize(&input.prev_tx)
+        .context("Failed to parse funding input previous transaction")
+}
+
+/// Value of the output spent by a funding input.
+pub fn funding_input_value(input: &FundingInput) -> Result<Amount> {
+    let prev_tx = funding_input_prev_tx(input)?;
+    prev_tx
+        .output
+        .get(input.prev_tx_vout as usize)
+        .map(|output| output.value)
+        .ok_or_else(|| {
+            anyhow!(
+                "Funding input references vout {} but previous transaction {} has {} outputs",
+                input.prev_tx_vout,
+                prev_tx.compute_txid(),
+                prev_tx.output.len()
+            )
+        })
+}
+
+pub fn funding_input_outpoint(input: &FundingInput) -> Result<OutPoint> {
+    Ok(OutPoint {
+        txid: funding_input_prev_tx(input)?.compute_txid(),
+        vout: input.prev_tx_vout,
+    })
+}
+
+/// Split funding inputs into plain inputs and inputs spending an earlier
+/// contract's funding output, which rust-dlc takes separately so it can
+/// account for the 2-of-2 witness. The amount covers both kinds.
+fn tx_input_infos(
+    inputs: &[FundingInput],
+) -> Result<(Vec<TxInputInfo>, Vec<DlcInputInfo>, Amount)> {
+    let mut infos = Vec::with_capacity(inputs.len());
+    let mut dlc_infos = Vec::new();
+    let mut total = Amount::ZERO;
+    for input in inputs {
+        let value = funding_input_value(input)?;
+        total += value;
+        match &input.dlc_input {
+            Some(dlc_input) => dlc_infos.push(DlcInputInfo {
+                fund_tx: funding_input_prev_tx(input)?,
+                fund_vout: input.prev_tx_vout,
+                local_fund_pubkey: dlc_input.local_fund_pubkey,
+                remote_fund_pubkey: dlc_input.remote_fund_pubkey,
+                fund_amount: value,
+                max_witness_len: input.max_witness_len as usize,
+                input_serial_id: input.input_serial_id,
+                contract_id: dlc_input.contract_id,
+            }),
+            None => infos.push(TxInputInfo {
+                outpoint: funding_input_outpoint(input)?,
+                max_witness_len: input.max_witness_len as usize,
+                redeem_script: input.redeem_script.clone(),
+                serial_id: input.input_serial_id,
+            }),
+        }
+    }
+    Ok((infos, dlc_infos, total))
+}
+
+pub fn offer_party_params(offer: &OfferDlc) -> Result<PartyParams> {
+    let (inputs, dlc_inputs, input_amount) = tx_input_infos(&offer.funding_inputs)?;
+    Ok(PartyParams {
+        fund_pubkey: offer.funding_pubkey,
+        change_script_pubkey: offer.change_spk.clone(),
+        change_serial_id: offer.change_serial_id,
+        payout_script_pubkey: offer.payout_spk.clone(),
+        payout_serial_id: offer.payout_serial_id,
+        inputs,
+        dlc_inputs,
+        input_amount,
+        collateral: Amount::from_sat(offer.offer_collateral),
+    })
+}
+
+pub fn accept_party_params(accept: &AcceptDlc) -> Result<PartyParams> {
+    let (inputs, dlc_inputs, input_amount) = tx_input_infos(&accept.funding_inputs)?;
+    Ok(PartyParams {
+        fund_pubkey: accept.funding_pubkey,
+        change_script_pubkey: accept.change_spk.clone(),
+        change_serial_id: accept.change_serial_id,
+        payout_script_pubkey: accept.payout_spk.clone(),
+        payout_serial_id: accept.payout_serial_id,
+        inputs,
+        dlc_inputs,
+        input_amount,
+        collateral: Amount::from_sat(accept.accept_collateral),
+    })
+}
+
+/// Payouts for every CET of the contract, in the order rust-dlc creates them.
+pub fn contract_payouts(offer: &OfferDlc) -> Result<Vec<Payout>> {
+    Ok(contract_cets(&offer.contract_info)?
+        .into_iter()
+        .flatten()
+        .map(|cet| Payout {
+            offer: Amount::from_sat(cet.offer_payout),
+            accept: Amount::from_sat(cet.accept_payout),
+        })
+        .collect())
+}
+
+/// Build the funding, refund and CET transactions for an offer/accept pair.
+pub fn build_dlc_transactions(offer: &OfferDlc, accept: &AcceptDlc) -> Result<DlcTransactions> {
+    if offer.temporary_contract_id != accept.temporary_contract_id {
+        bail!("Accept temporary contract id does not match the offer");
+    }
+
+    let offer_params = offer_party_params(offer)?;
+    let accept_params = accept_party_params(accept)?;
+    let payouts = contract_payouts(offer)?;
+
+    dlc::create_dlc_transactions(
+        &offer_params,
+        &accept_params,
+        &payouts,
+        offer.refund_locktime,
+        offer.fee_rate_per_vb,
+        0,
+        offer.cet_locktime,
+        offer.fund_output_serial_id,
+    )
+    .map_err(|e| anyhow!("Failed to create DLC transactions: {:?}", e))
+}
+
+/// Index of the 2-of-2 funding output within the funding transaction.
+pub fn fund_output_index(transactions: &DlcTransactions) -> Result<u16> {
+    let funding_spk = transactions.funding_script_pubkey.to_p2wsh();
+    let index = transactions
+        .fund
+        .output
+        .iter()
+        .position(|output| output.script_pubkey == funding_spk)
+        .ok_or_else(|| anyhow!("Funding transaction has no output paying to the funding script"))?;
+    u16::try_from(index).context("Funding output index does not fit in a u16")
+}
+
+/// Compute the final contract id as specified in the dlcspecs: the funding
+/// txid (in its displayed, big-endian byte order) XORed with the temporary
+/// contract id, with the funding output index XORed into the last two bytes.
+pub fn compute_contract_id(
+    fund_txid: Txid,
+    fund_output_index: u16,
+    temporary_id: &[u8; 32],
+) -> [u8; 32] {
+    let txid_bytes = fund_txid.to_byte_array();
+    let mut id = [0u8; 32];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = txid_bytes[31 - i] ^ temporary_id[i];
+    }
+    id[30] ^= (fund_output_index >> 8) as u8;
+    id[31] ^= (fund_output_index & 0xff) as u8;
+    id
+}
+
+/// Build the funding transaction for an offer/accept pair and print the
+/// temporary and final contract ids.
+pub fn show_contract_id(offer_hex: &str, accept_hex: &str) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let accept = match read_accept(accept_hex) {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode accept: {}", e)),
+    };
+
+    let transactions = match build_dlc_transactions(&offer, &accept) {
+        Ok(transactions) => transactions,
+        Err(e) => return output_error(&e.to_string()),
+    };
+    let output_index = fund_output_index(&transactions)?;
+    let fund_txid = transactions.fund.compute_txid();
+    let contract_id = compute_contract_id(fund_txid, output_index, &offer.temporary_contract_id);
+
+    let data = serde_json::json!({
+        "temporaryContractId": hex::encode(offer.temporary_contract_id),
+        "contractId": hex::encode(contract_id),
+        "fundTxid": fund_txid.to_string(),
+        "fundTxidInternalBytes": hex::encode(fund_txid.to_byte_array()),
+        "fundOutputIndex": output_index,
+        "fundTransaction": transaction_json(&transactions.fund)?,
+    });
+
+    output_success_with_data(
+        "contract-id",
+        &data,
+        "Computed contract id from funding transaction",
+    )
+}
+
+/// Build the funding, refund and CET transactions for an offer/accept pair,
+/// with each CET's sub-contract and outcomes and the CET index range of every
+/// sub-contract.
+pub fn show_transactions(offer_hex: &str, accept_hex: &str) -> Result<()> {
+    let offer = match read_offer(offer_hex) {
+        Ok(offer) => offer,
+        Err(e) => return output_error(&format!("Failed to decode offer: {}", e)),
+    };
+    let accept = match read_accept(accept_hex) {
+        Ok(accept) => accept,
+        Err(e) => return output_error(&format!("Failed to decode accept: {}", e)),
+    };
+
+    let transactions = match build_dlc_transactions(&offer, &accept) {
+        Ok(transactions) => transactions,
+        Err(e) => return output_error(&e.to_string()),
+    };
+    let contract_cets = match contract_cets(&offer.contract_info) {
+        Ok(cets) => cets,
+        Err(e) => return output_error(&format!("Failed to enumerate CETs: {}", e)),
+    };
+    let cet_count: usize = contract_cets.iter().map(Vec::len).sum();
+    if cet_count != transactions.cets.len() {
+        return output_error(&format!(
+            "Enumerated {} CETs but rust-dlc built {}",
+            cet_count,
+            transactions.cets.len()
+        ));
+    }
+    let ranges = sub_contract_ranges(&offer.contract_info, &contract_cets);
+    let cets = contract_cets
+        .iter()
+        .enumerate()
+        .flat_map(|(sub_contract, cets)| cets.iter().map(move |cet| (sub_contract, cet)))
+        .zip(&transactions.cets)
+        .enumerate()
+        .map(|(index, ((sub_contract, cet), tx))| {
+            Ok(serde_json::json!({
+                "index": index,
+                "subContract": sub_contract,
+                "outcome": outcome_summary(&cet.outcome),
+                "offerPayout": cet.offer_payout,
+                "acceptPayout": cet.accept_payout,
+                "cet": transaction_json(tx)?,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let data = serde_json::json!({
+        "fundTransaction": transaction_json(&transactions.fund)?,
+        "fundOutputIndex": fund_output_index(&transactions)?,
+        "refundTransaction": transaction_json(&transactions.refund)?,
+        "subContracts": ranges,
+        "cetCount": cets.len(),
+        "cets": cets,
+    });
+
+    output_success_with_data(
+        "contract-transactions",
+        &data,
+        &format!(
+            "Built funding, refund and {} CETs across {} sub-contracts",
+            transactions.cets.len(),
+            ranges.len()
+        ),
+    )
+}
+
+/// JSON view of a transaction with its hex encoding and txid.
+pub fn transaction_json(tx: &Transaction) -> Result<Value> {
+    Ok(serde_json::json!({
+        "txid": tx.compute_txid().to_string(),
+        "hex": bitcoin::consensus::encode::serialize_hex(tx),
+        "transaction": serde_json::to_value(tx).context("Failed to convert transaction to JSON")?,
+    }))
+}
@@ -0,0 +1,146 @@
+//! Test vectors in the dlcspecs layout, generated from scenario descriptions.
+//!
+//! A scenario file is a JSON object or array of objects such as
+//!
+//! ```json
+//! {"name": "enum_3_of_5", "seed": 7, "contract": "enum", "oracles": 5, "threshold": 3}
+//! ```
+//!
+//! where every field except `name` is optional, `dlcInputs` funds the offer
+//! from an earlier contract's funding output and `network` picks the chain
+//! hash (regtest by default). Each scenario becomes one
+//! vector file with `offer_message`, `accept_message` and `sign_message`, each
+//! holding the canonical `message` JSON and its `serialized` hex.
+
+use crate::generate::{encode, ContractKind, Generator, Options};
+use crate::input::read_source;
+use crate::network::parse_network;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Scenario {
+    name: String,
+    #[serde(default)]
+    seed: u64,
+    contract: Option<String>,
+    oracles: Option<u64>,
+    threshold: Option<u64>,
+    #[serde(default)]
+    bounded_difference: bool,
+    sub_contracts: Option<u64>,
+    #[serde(default)]
+    dlc_inputs: bool,
+    network: Option<String>,
+}
+
+impl Scenario {
+    fn options(&self) -> Result<Options> {
+        let options = Options {
+            contract: self
+                .contract
+                .as_deref()
+                .map(ContractKind::parse)
+                .transpose()?,
+            oracles: self.oracles,
+            threshold: self.threshold,
+            bounded_difference: self.bounded_difference,
+            sub_contracts: self.sub_contracts,
+            dlc_inputs: self.dlc_inputs,
+            network: self.network.as_deref().map(parse_network).transpose()?,
+        };
+        options.validate()?;
+        Ok(options)
+    }
+}
+
+fn read_scenarios(path: &str) -> Result<Vec<Scenario>> {
+    let source = if path == "-" { None } else { Some(path) };
+    let contents = read_source(source)?;
+    let json: Value = serde_json::from_slice(&contents).context("Scenario file is not JSON")?;
+    let scenarios: Vec<Scenario> = match json {
+        Value::Array(_) => serde_json::from_value(json),
+        _ => serde_json::from_value(json).map(|scenario| vec![scenario]),
+    }
+    .context("Invalid scenario")?;
+
+    let mut names = BTreeSet::new();
+    for scenario in &scenarios {
+        if scenario.name.is_empty() || scenario.name.contains(['/', '\\']) {
+            bail!("Invalid scenario name: {:?}", scenario.name);
+        }
+        if !names.insert(scenario.name.as_str()) {
+            bail!("Duplicate scenario name: {}", scenario.name);
+        }
+    }
+    Ok(scenarios)
+}
+
+fn vector_message(msg_type: &str, json: &Value) -> Result<Value> {
+    let (bytes, canonical) = encode(msg_type, json)?;
+    Ok(json!({ "message": canonical, "serialized": hex::encode(bytes) }))
+}
+
+fn vector(scenario: &Scenario) -> Result<Value> {
+    let dlc = Generator::with_options(scenario.seed, scenario.options()?).dlc()?;
+    Ok(json!({
+        "offer_message": vector_message("offer", &dlc.offer)?,
+        "accept_message": vector_message("accept", &dlc.accept)?,
+        "sign_message": vector_message("sign", &dlc.sign)?,
+    }))
+}
+
+/// Generate one vector per scenario, writing `<name>.json` files into
+/// `output_dir` when given and printing them otherwise.
+pub fn generate_vectors(scenario_path: &str, output_dir: Option<&str>) -> Result<()> {
+    let scenarios = match read_scenarios(scenario_path) {
+        Ok(scenarios) => scenarios,
+        Err(e) => return crate::output_error(&format!("{:#}", e)),
+    };
+
+    let mut vectors = serde_json::Map::new();
+    for scenario in &scenarios {
+        match vector(scenario) {
+            Ok(vector) => {
+                vectors.insert(scenario.name.clone(), vector);
+            }
+            Err(e) => {
+                return crate::output_error(&format!(
+                    "Failed to generate scenario {}: {:#}",
+                    scenario.name, e
+                ))
+            }
+        }
+    }
+
+    if let Some(output_dir) = output_dir {
+        let dir = Path::new(output_dir);
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create output directory {}", dir.display()))?;
+        for (name, vector) in &vectors {
+            let path = dir.join(format!("{}.json", name));
+            fs::write(&path, serde_json::to_string_pretty(vector)? + "\n")
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        let files: Vec<String> = vectors
+            .keys()
+            .map(|name| format!("{}.json", name))
+            .collect();
+        return crate::output_success_with_data(
+            "test-vectors",
+            &json!({ "outputDir": output_dir, "files": files }),
+            &format!("Wrote {} test vector(s) to {}", files.len(), output_dir),
+        );
+    }
+
+    crate::output_success_with_data(
+        "test-vectors",
+        &json!({ "vectors": vectors }),
+        &format!("Generated {} test vector(s)", vectors.len()),
+    )
+}
@@ -0,0 +1,357 @@
+//! Codec for the node-dlc messages that rust-dlc does not implement.
+//!
+//! Most of these messages are TLV records: a BigSize type, a BigSize length
+//! and the body. Top-level messages such as orders instead carry a 2-byte
+//! type like the rust-dlc wire messages. Bodies use the same primitives as
+//! the rust-dlc encodings (big-endian integers, BigSize counts and
+//! length-prefixed byte strings) and may embed rust-dlc types, so the JSON
+//! produced here sits alongside the rust-dlc serde output.
+
+use anyhow::{anyhow, bail, Context, Result};
+use dlc_messages::ser_impls::read_as_tlv;
+use lightning::io::Cursor;
+use lightning::ln::wire::Type;
+use lightning::util::ser::{Readable, Writeable};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    pub fn take(&mut self, n: usize, name: &str) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unexpected end of input reading {} ({} bytes needed at offset {}, {} available)",
+                    name,
+                    n,
+                    self.pos,
+                    self.bytes.len() - self.pos
+                )
+            })?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn u8(&mut self, name: &str) -> Result<u8> {
+        Ok(self.take(1, name)?[0])
+    }
+
+    pub fn u16(&mut self, name: &str) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2, name)?.try_into()?))
+    }
+
+    pub fn u32(&mut self, name: &str) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4, name)?.try_into()?))
+    }
+
+    pub fn u64(&mut self, name: &str) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8, name)?.try_into()?))
+    }
+
+    /// BigSize as used by lightning and the dlcspecs; non-canonical
+    /// encodings are rejected.
+    pub fn bigsize(&mut self, name: &str) -> Result<u64> {
+        let (value, min) = match self.u8(name)? {
+            0xff => (self.u64(name)?, 0x1_0000_0000),
+            0xfe => (self.u32(name)? as u64, 0x1_0000),
+            0xfd => (self.u16(name)? as u64, 0xfd),
+            b => return Ok(b as u64),
+        };
+        if value < min {
+            bail!("Non-canonical BigSize {} for {}", value, name);
+        }
+        Ok(value)
+    }
+
+    /// BigSize length followed by that many bytes.
+    pub fn var_bytes(&mut self, name: &str) -> Result<&'a [u8]> {
+        let len = self.bigsize(name)?;
+        self.take(usize::try_from(len)?, name)
+    }
+
+    pub fn array<const N: usize>(&mut self, name: &str) -> Result<[u8; N]> {
+        Ok(self.take(N, name)?.try_into()?)
+    }
+
+    pub fn string(&mut self, name: &str) -> Result<String> {
+        let bytes = self.var_bytes(name)?;
+        String::from_utf8(bytes.to_vec()).with_context(|| format!("{} is not UTF-8", name))
+    }
+
+    /// A rust-dlc type embedded in the message.
+    pub fn readable<T: Readable>(&mut self, name: &str) -> Result<T> {
+        let mut cursor = Cursor::new(self.remaining());
+        let value =
+            T::read(&mut cursor).map_err(|e| anyhow!("Failed to parse {}: {:?}", name, e))?;
+        self.pos += cursor.position() as usize;
+        Ok(value)
+    }
+
+    /// A rust-dlc type embedded as a TLV record, such as an oracle message.
+    pub fn tlv<T: Readable + Type>(&mut self, name: &str) -> Result<T> {
+        let mut cursor = Cursor::new(self.remaining());
+        let value = read_as_tlv::<T, _>(&mut cursor)
+            .map_err(|e| anyhow!("Failed to parse {} TLV: {:?}", name, e))?;
+        self.pos += cursor.position() as usize;
+        Ok(value)
+    }
+
+    /// BigSize count followed by that many items.
+    pub fn vec<T>(
+        &mut self,
+        name: &str,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let count = self.bigsize(name)?;
+        // Each item takes at least one byte, which bounds the allocation.
+        if count > (self.bytes.len() - self.pos) as u64 {
+            bail!("{} count {} exceeds the remaining input", name, count);
+        }
+        (0..count).map(|_| item(self)).collect()
+    }
+}
+
+#[derive(Default)]
+pub struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub fn bigsize(&mut self, value: u64) {
+        match value {
+            0..=0xfc => self.u8(value as u8),
+            0xfd..=0xffff => {
+                self.u8(0xfd);
+                self.u16(value as u16);
+            }
+            0x1_0000..=0xffff_ffff => {
+                self.u8(0xfe);
+                self.u32(value as u32);
+            }
+            _ => {
+                self.u8(0xff);
+                self.u64(value);
+            }
+        }
+    }
+
+    pub fn var_bytes(&mut self, bytes: &[u8]) {
+        self.bigsize(bytes.len() as u64);
+        self.bytes(bytes);
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.var_bytes(value.as_bytes());
+    }
+
+    pub fn writeable<T: Writeable>(&mut self, value: &T) {
+        self.bytes(&value.encode());
+    }
+
+    pub fn tlv<T: Writeable + Type>(&mut self, value: &T) {
+        self.bigsize(value.type_id() as u64);
+        self.var_bytes(&value.encode());
+    }
+
+    pub fn vec<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T)) {
+        self.bigsize(items.len() as u64);
+        for value in items {
+            item(self, value);
+        }
+    }
+}
+
+/// How a message is framed on the wire.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /// BigSize type and BigSize length before the body.
+    Tlv,
+    /// A 2-byte type before the body, which runs to the end of the input.
+    Wire,
+}
+
+/// A node-dlc message.
+pub trait NodeMessage: Sized + Serialize + DeserializeOwned {
+    const TYPE: u64;
+    const NAME: &'static str;
+    const FRAMING: Framing = Framing::Tlv;
+
+    fn read_body(r: &mut Reader) -> Result<Self>;
+    fn write_body(&self, w: &mut Writer);
+
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Writer::default();
+        self.write_body(&mut body);
+        let body = body.into_bytes();
+
+        let mut w = Writer::default();
+        match Self::FRAMING {
+            Framing::Tlv => {
+                w.bigsize(Self::TYPE);
+                w.var_bytes(&body);
+            }
+            Framing::Wire => {
+                w.u16(Self::TYPE as u16);
+                w.bytes(&body);
+            }
+        }
+        w.into_bytes()
+    }
+
+    /// Parse a TLV value, which must be consumed exactly.
+    fn from_body(body: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(body);
+        let value =
+            Self::read_body(&mut r).with_context(|| format!("Failed to parse {}", Self::NAME))?;
+        if !r.is_empty() {
+            bail!(
+                "{} unread bytes at the end of {}",
+                body.len() - r.position(),
+                Self::NAME
+            );
+        }
+        Ok(value)
+    }
+
+    /// Read one message, including its type (and length for TLVs).
+    fn read(r: &mut Reader) -> Result<Self> {
+        let msg_type = match Self::FRAMING {
+            Framing::Tlv => r.bigsize("type")?,
+            Framing::Wire => r.u16("type")? as u64,
+        };
+        if msg_type != Self::TYPE {
+            bail!(
+                "Invalid message type: expected {} ({}), got {}",
+                Self::TYPE,
+                Self::NAME,
+                msg_type
+            );
+        }
+        match Self::FRAMING {
+            Framing::Tlv => Self::from_body(r.var_bytes(Self::NAME)?),
+            Framing::Wire => {
+                Self::read_body(r).with_context(|| format!("Failed to parse {}", Self::NAME))
+            }
+        }
+    }
+}
+
+/// Decode a message from the start of `bytes`, returning it and the number
+/// of bytes consumed.
+pub fn decode<T: NodeMessage>(bytes: &[u8]) -> Result<(T, usize)> {
+    let mut r = Reader::new(bytes);
+    let value = T::read(&mut r)?;
+    Ok((value, r.position()))
+}
+
+/// Parse the JSON form of a message and encode it.
+pub fn serialize<T: NodeMessage>(json: &Value) -> Result<Vec<u8>> {
+    let value: T = serde_json::from_value(json.clone())
+        .with_context(|| format!("Failed to parse JSON as {}", T::NAME))?;
+    Ok(value.encode())
+}
+
+/// Serde helpers for byte strings carried as hex in JSON.
+pub mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        hex::decode(String::deserialize(d)?).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Like [`hex_bytes`], for fixed-size fields such as ids and hashes.
+pub mod hex_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        d: D,
+    ) -> Result<[u8; N], D::Error> {
+        let bytes = hex::decode(String::deserialize(d)?).map_err(serde::de::Error::custom)?;
+        let len = bytes.len();
+        bytes
+            .try_into()
+            .map_err(|_| serde::de::Error::custom(format!("expected {} bytes, got {}", N, len)))
+    }
+}
+
+/// Like [`hex_bytes`], for lists of byte strings.
+pub mod hex_vec {
+    use serde::ser::SerializeSeq;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(items: &[Vec<u8>], s: S) -> Result<S::Ok, S::Error> {
+        let mut seq = s.serialize_seq(Some(items.len()))?;
+        for item in items {
+            seq.serialize_element(&hex::encode(item))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Vec<u8>>, D::Error> {
+        Vec::<String>::deserialize(d)?
+            .into_iter()
+            .map(|text| hex::decode(text).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}